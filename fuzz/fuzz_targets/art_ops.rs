@@ -0,0 +1,101 @@
+//! Applies the operations encoded by the input to `Art` and to a `BTreeMap`, which must agree.
+//!
+//! The first byte picks `MAX_PARTIAL_LEN`, then every operation is an opcode, the length
+//! of the prefix it keeps from the previous key, and the length and bytes of its new tail.
+//! Reusing the previous key makes long shared paths and keys that are prefixes of others.
+
+#![no_main]
+
+use std::collections::BTreeMap;
+use std::ops::Bound;
+
+use artful::Art;
+use libfuzzer_sys::fuzz_target;
+
+struct Input<'a> {
+    data: &'a [u8],
+    last_key: Vec<u8>,
+}
+
+impl Input<'_> {
+    fn byte(&mut self) -> Option<u8> {
+        let (byte, rest) = self.data.split_first()?;
+        self.data = rest;
+        Some(*byte)
+    }
+
+    fn key(&mut self) -> Option<Vec<u8>> {
+        let shared = self.byte()? as usize % (self.last_key.len() + 1);
+        let tail = self.byte()? as usize % 8;
+        let mut key = self.last_key[..shared].to_vec();
+        for _ in 0..tail {
+            key.push(self.byte()?);
+        }
+        self.last_key.clone_from(&key);
+        Some(key)
+    }
+
+    fn bound(&mut self) -> Option<Bound<Vec<u8>>> {
+        Some(match self.byte()? % 3 {
+            0 => Bound::Unbounded,
+            1 => Bound::Included(self.key()?),
+            _ => Bound::Excluded(self.key()?),
+        })
+    }
+}
+
+fn run<const MAX_PARTIAL_LEN: usize>(mut input: Input) {
+    let mut art = Art::<Vec<u8>, u8, MAX_PARTIAL_LEN>::new();
+    let mut btree = BTreeMap::new();
+    while let Some(op) = input.byte() {
+        let Some(key) = input.key() else { break };
+        match op % 4 {
+            0 | 1 => {
+                let val = op / 4;
+                assert_eq!(art.insert(key.clone(), val), btree.insert(key, val));
+            }
+            2 => assert_eq!(art.remove(&key), btree.remove(&key)),
+            _ => {
+                assert_eq!(art.get(&key), btree.get(&key));
+                let (Some(start), Some(end)) = (input.bound(), input.bound()) else {
+                    break;
+                };
+                let empty = match (&start, &end) {
+                    (Bound::Excluded(start), Bound::Excluded(end)) => start >= end,
+                    (
+                        Bound::Included(start) | Bound::Excluded(start),
+                        Bound::Included(end) | Bound::Excluded(end),
+                    ) => start > end,
+                    _ => false,
+                };
+                if !empty {
+                    let range = (start, end);
+                    let found = art.range::<Vec<u8>, _>(range.clone());
+                    assert!(found.eq(btree.range(range)));
+                }
+            }
+        }
+        assert_eq!(art.size(), btree.len());
+    }
+
+    if let Err(error) = art.validate() {
+        panic!("{}", error);
+    }
+    assert!(art.iter().eq(btree.iter()));
+}
+
+fuzz_target!(|data: &[u8]| {
+    let Some((variant, data)) = data.split_first() else {
+        return;
+    };
+    let input = Input {
+        data,
+        last_key: Vec::new(),
+    };
+    match variant % 4 {
+        0 => run::<0>(input),
+        1 => run::<1>(input),
+        2 => run::<4>(input),
+        _ => run::<8>(input),
+    }
+});
@@ -0,0 +1,402 @@
+//! Allocators for the inner nodes and leaves of an [Art](crate::Art).
+//!
+//! Every inner node and every leaf of the tree is an allocation of its own. [Global] hands
+//! them to the global allocator one by one. [FreeList] keeps the blocks given back when a
+//! node grows, shrinks or is removed, and reuses them for the next node of the same size.
+//! [Arena] carves the blocks out of large chunks and releases the chunks all at once when it
+//! is dropped, so dropping a tree does not visit its nodes unless the keys or values need
+//! to be dropped.
+//!
+//! # Examples
+//! ```rust
+//! use artful::alloc::Arena;
+//! use artful::Art;
+//!
+//! let mut art: Art<u64, u64, 8, Arena> = Art::new_in(Arena::new());
+//! for i in 0..10_000 {
+//!     art.insert(i, i * 2);
+//! }
+//! assert_eq!(art.get(&4096), Some(&8192));
+//! ```
+
+use std::alloc::{self, Layout};
+use std::cmp::max;
+use std::ptr::NonNull;
+
+/// Allocates the blocks that hold the nodes and leaves of a tree.
+///
+/// The tree owns its allocator and calls it under `&mut self`, so an allocator needs no
+/// synchronization. Layouts are never zero-sized and are aligned to at least 8 bytes, the
+/// tree keeps the type of a node in the low bits of its address.
+///
+/// # Safety
+///
+/// The tree writes its nodes into the blocks and tags their addresses, so
+/// [allocate](NodeAllocator::allocate) must return a valid block of at least
+/// `layout.size()` bytes, aligned to `layout.align()`, which is at least 8. The block must
+/// be exclusive to the caller until it is given back with
+/// [deallocate](NodeAllocator::deallocate).
+pub unsafe trait NodeAllocator {
+    /// Whether dropping the allocator releases every block it handed out. The tree then
+    /// skips visiting its nodes on drop when its keys and values do not need to be dropped.
+    const RELEASES_ON_DROP: bool = false;
+
+    /// Allocates a block for the layout. Running out of memory aborts through
+    /// [handle_alloc_error](std::alloc::handle_alloc_error), as `Box` does.
+    fn allocate(&mut self, layout: Layout) -> NonNull<u8>;
+
+    /// Gives a block back to the allocator.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must have been returned by [allocate](NodeAllocator::allocate) of this allocator
+    /// with the same layout, and must not have been given back already.
+    unsafe fn deallocate(&mut self, ptr: NonNull<u8>, layout: Layout);
+}
+
+/// The global allocator, every node is allocated and freed on its own like a `Box`.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Global;
+
+unsafe impl NodeAllocator for Global {
+    fn allocate(&mut self, layout: Layout) -> NonNull<u8> {
+        let ptr = unsafe { alloc::alloc(layout) };
+        NonNull::new(ptr).unwrap_or_else(|| alloc::handle_alloc_error(layout))
+    }
+
+    unsafe fn deallocate(&mut self, ptr: NonNull<u8>, layout: Layout) {
+        alloc::dealloc(ptr.as_ptr(), layout)
+    }
+}
+
+/// Free blocks kept by layout, each block holds the address of the next free block of its
+/// size class. A tree uses five layouts, so the classes are searched in order.
+#[derive(Default)]
+struct SizeClasses {
+    heads: Vec<(Layout, Option<NonNull<u8>>)>,
+}
+
+impl SizeClasses {
+    /// Whether a free block of the layout can hold the address of the next one.
+    fn fits(layout: Layout) -> bool {
+        layout.size() >= size_of::<usize>() && layout.align() >= align_of::<usize>()
+    }
+
+    fn pop(&mut self, layout: Layout) -> Option<NonNull<u8>> {
+        let (_, head) = self.heads.iter_mut().find(|(class, _)| *class == layout)?;
+        let block = (*head)?;
+        *head = unsafe { block.cast::<Option<NonNull<u8>>>().read() };
+        Some(block)
+    }
+
+    /// Keeps the block for reuse, returns false when the block is too small to be linked.
+    fn push(&mut self, block: NonNull<u8>, layout: Layout) -> bool {
+        if !SizeClasses::fits(layout) {
+            return false;
+        }
+        let index = match self.heads.iter().position(|(class, _)| *class == layout) {
+            Some(index) => index,
+            None => {
+                self.heads.push((layout, None));
+                self.heads.len() - 1
+            }
+        };
+        let head = &mut self.heads[index].1;
+        unsafe { block.cast::<Option<NonNull<u8>>>().write(*head) };
+        *head = Some(block);
+        true
+    }
+
+    /// Removes every free block, calling `f` on each.
+    fn drain(&mut self, mut f: impl FnMut(NonNull<u8>, Layout)) {
+        for (layout, head) in self.heads.drain(..) {
+            let mut next = head;
+            while let Some(block) = next {
+                next = unsafe { block.cast::<Option<NonNull<u8>>>().read() };
+                f(block, layout);
+            }
+        }
+    }
+}
+
+/// Keeps the blocks of freed nodes in per-size-class free lists and hands them out again
+/// before asking the inner allocator, so that growing and shrinking nodes back and forth
+/// does not go through `malloc` and `free`. The cached blocks are freed when the allocator
+/// is dropped.
+pub struct FreeList<A: NodeAllocator = Global> {
+    inner: A,
+    free: SizeClasses,
+}
+
+impl FreeList {
+    pub fn new() -> FreeList {
+        FreeList::new_in(Global)
+    }
+}
+
+impl<A: NodeAllocator> FreeList<A> {
+    /// Creates a free list caching the blocks of `inner`.
+    pub fn new_in(inner: A) -> FreeList<A> {
+        FreeList {
+            inner,
+            free: SizeClasses::default(),
+        }
+    }
+}
+
+impl<A: NodeAllocator + Default> Default for FreeList<A> {
+    fn default() -> FreeList<A> {
+        FreeList::new_in(A::default())
+    }
+}
+
+unsafe impl<A: NodeAllocator> NodeAllocator for FreeList<A> {
+    fn allocate(&mut self, layout: Layout) -> NonNull<u8> {
+        match self.free.pop(layout) {
+            Some(block) => block,
+            None => self.inner.allocate(layout),
+        }
+    }
+
+    unsafe fn deallocate(&mut self, ptr: NonNull<u8>, layout: Layout) {
+        if !self.free.push(ptr, layout) {
+            self.inner.deallocate(ptr, layout);
+        }
+    }
+}
+
+impl<A: NodeAllocator> Drop for FreeList<A> {
+    fn drop(&mut self) {
+        let inner = &mut self.inner;
+        self.free
+            .drain(|block, layout| unsafe { inner.deallocate(block, layout) });
+    }
+}
+
+// the blocks are owned by the allocator and only reached through `&mut self`.
+unsafe impl<A: NodeAllocator + Send> Send for FreeList<A> {}
+unsafe impl<A: NodeAllocator + Sync> Sync for FreeList<A> {}
+
+/// Allocates blocks by bumping a pointer through chunks of memory, and releases the chunks
+/// when it is dropped.
+///
+/// Freed blocks go to per-size-class free lists and are reused, a chunk is not returned to
+/// the global allocator before the arena is dropped. Chunks start at 4 KiB and double up
+/// to 1 MiB, a larger block gets a chunk of its own.
+pub struct Arena {
+    chunks: Vec<(NonNull<u8>, Layout)>,
+    /// The free part of the last chunk, as addresses.
+    next: usize,
+    end: usize,
+    free: SizeClasses,
+}
+
+const MIN_CHUNK_SIZE: usize = 4 << 10;
+const MAX_CHUNK_SIZE: usize = 1 << 20;
+const CHUNK_ALIGN: usize = 64;
+
+impl Arena {
+    pub fn new() -> Arena {
+        Arena {
+            chunks: Vec::new(),
+            next: 0,
+            end: 0,
+            free: SizeClasses::default(),
+        }
+    }
+
+    /// Returns the number of bytes of the chunks allocated so far.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use artful::alloc::Arena;
+    ///
+    /// assert_eq!(Arena::new().allocated_bytes(), 0);
+    /// ```
+    pub fn allocated_bytes(&self) -> usize {
+        self.chunks.iter().map(|(_, layout)| layout.size()).sum()
+    }
+
+    /// Allocates a chunk large enough for the layout and bumps from it from now on.
+    fn grow(&mut self, layout: Layout) {
+        let doubled = match self.chunks.last() {
+            Some((_, last)) => (last.size() * 2).clamp(MIN_CHUNK_SIZE, MAX_CHUNK_SIZE),
+            None => MIN_CHUNK_SIZE,
+        };
+        let size = max(doubled, layout.size() + layout.align());
+        let chunk_layout = Layout::from_size_align(size, CHUNK_ALIGN).expect("chunk layout");
+        let chunk = Global.allocate(chunk_layout);
+        self.chunks.push((chunk, chunk_layout));
+        self.next = chunk.as_ptr() as usize;
+        self.end = self.next + size;
+    }
+}
+
+impl Default for Arena {
+    fn default() -> Arena {
+        Arena::new()
+    }
+}
+
+unsafe impl NodeAllocator for Arena {
+    const RELEASES_ON_DROP: bool = true;
+
+    fn allocate(&mut self, layout: Layout) -> NonNull<u8> {
+        if let Some(block) = self.free.pop(layout) {
+            return block;
+        }
+
+        let mut start = self.next.next_multiple_of(layout.align());
+        if self.next == 0 || start + layout.size() > self.end {
+            self.grow(layout);
+            start = self.next.next_multiple_of(layout.align());
+        }
+        self.next = start + layout.size();
+        let (chunk, _) = self.chunks.last().expect("the arena has a chunk");
+        // the block is derived from the chunk to keep the provenance of the allocation.
+        let offset = start - chunk.as_ptr() as usize;
+        unsafe { chunk.add(offset) }
+    }
+
+    unsafe fn deallocate(&mut self, ptr: NonNull<u8>, layout: Layout) {
+        // a block too small for the free list stays unused until the arena is dropped.
+        self.free.push(ptr, layout);
+    }
+}
+
+impl Drop for Arena {
+    fn drop(&mut self) {
+        for (chunk, layout) in self.chunks.drain(..) {
+            unsafe { Global.deallocate(chunk, layout) };
+        }
+    }
+}
+
+// the chunks are owned by the arena and only reached through `&mut self`.
+unsafe impl Send for Arena {}
+unsafe impl Sync for Arena {}
+
+#[cfg(test)]
+mod test {
+    use std::alloc::Layout;
+    use std::collections::BTreeSet;
+    use std::ptr::NonNull;
+
+    use super::{Arena, FreeList, Global, NodeAllocator};
+    use crate::Art;
+
+    /// Counts the blocks handed out by the global allocator.
+    #[derive(Default)]
+    struct Counting {
+        allocated: usize,
+        live: usize,
+    }
+
+    unsafe impl NodeAllocator for Counting {
+        fn allocate(&mut self, layout: Layout) -> NonNull<u8> {
+            self.allocated += 1;
+            self.live += 1;
+            Global.allocate(layout)
+        }
+
+        unsafe fn deallocate(&mut self, ptr: NonNull<u8>, layout: Layout) {
+            self.live -= 1;
+            Global.deallocate(ptr, layout)
+        }
+    }
+
+    #[test]
+    fn free_list_reuses_blocks() {
+        let mut art: Art<u32, u32, 8, FreeList<Counting>> = Art::default();
+        // a node below the root grows up to a Node256 and shrinks back to a Node4.
+        for round in 0..3 {
+            for i in 0..256 {
+                art.insert(i, round);
+            }
+            for i in 0..256 {
+                assert_eq!(art.remove(&i), Some(round));
+            }
+            assert_eq!(art.size(), 0);
+        }
+        let allocated = art.allocator().inner.allocated;
+        for i in 0..256 {
+            art.insert(i, 0);
+        }
+        assert_eq!(art.allocator().inner.allocated, allocated);
+        assert_eq!(art.validate(), Ok(()));
+    }
+
+    #[test]
+    fn arena_trees_move_between_threads() {
+        fn shared<T: Send + Sync>(_: &T) {}
+
+        let mut art: Art<u32, u32, 8, Arena> = Art::new_in(Arena::new());
+        for i in 0..1000 {
+            art.insert(i, i);
+        }
+        shared(&art);
+        let art = std::thread::spawn(move || {
+            assert_eq!(art.get(&500), Some(&500));
+            art
+        })
+        .join()
+        .unwrap();
+        assert_eq!(art.size(), 1000);
+    }
+
+    #[test]
+    fn arena_blocks_are_distinct_and_aligned() {
+        let mut arena = Arena::new();
+        let layouts = [
+            Layout::from_size_align(24, 8).unwrap(),
+            Layout::from_size_align(2072, 8).unwrap(),
+            Layout::from_size_align(8, 8).unwrap(),
+            // larger than the largest chunk.
+            Layout::from_size_align(3 << 20, 64).unwrap(),
+        ];
+        let mut seen = BTreeSet::new();
+        for i in 0..40 {
+            let layout = layouts[i % layouts.len()];
+            let block = arena.allocate(layout);
+            assert_eq!(block.as_ptr() as usize % layout.align(), 0);
+            assert!(seen.insert(block.as_ptr() as usize));
+            unsafe { block.as_ptr().write_bytes(0xAB, layout.size()) };
+        }
+        assert!(arena.allocated_bytes() >= 10 * (3 << 20));
+
+        let block = arena.allocate(layouts[0]);
+        unsafe { arena.deallocate(block, layouts[0]) };
+        assert_eq!(arena.allocate(layouts[0]), block);
+    }
+
+    #[test]
+    fn arena_builds_clones_and_takes_apart() {
+        let entries = (0..5000u32).map(|i| (i * 3, i));
+        let art = Art::<u32, u32, 8, Arena>::from_sorted_iter(entries.clone()).unwrap();
+        assert!(art.allocator().allocated_bytes() > 0);
+        let mut copy = art.clone();
+        for i in 0..2500 {
+            assert_eq!(copy.remove(&(i * 6)), Some(i * 2));
+        }
+        assert_eq!(copy.validate(), Ok(()));
+        assert!(art.into_iter().eq(entries));
+        // the nodes not reached by the iterator are freed when it is dropped.
+        assert_eq!(copy.into_iter().rev().take(10).count(), 10);
+    }
+
+    #[test]
+    fn arena_drops_keys_and_values() {
+        let counter = std::rc::Rc::new(());
+        {
+            let mut art: Art<String, std::rc::Rc<()>, 8, Arena> = Art::new_in(Arena::new());
+            for i in 0..1000 {
+                art.insert(format!("{}", i), counter.clone());
+            }
+            for i in 0..100 {
+                art.remove(&format!("{}", i));
+            }
+            assert_eq!(std::rc::Rc::strong_count(&counter), 901);
+        }
+        assert_eq!(std::rc::Rc::strong_count(&counter), 1);
+    }
+}
@@ -1,12 +1,30 @@
-use crate::node::ArtNode;
+use std::borrow::Borrow;
+use std::cmp::Ordering;
+use std::fmt::{self, Debug};
+use std::hash::{Hash, Hasher};
+use std::io;
+use std::ops::{Bound, Index, RangeBounds};
+
+use crate::alloc::{Global, NodeAllocator};
+use crate::bulk::BulkBuilder;
+use crate::node::{ArtNode, InsertSite};
 use crate::ArtKey;
 
+pub use crate::bulk::BulkLoadError;
+pub use crate::entry::{Entry, OccupiedEntry, VacantEntry};
+pub use crate::iter::{
+    IntoIter, Iter, IterMut, Keys, PrefixIter, PrefixIterMut, Range, RangeMut, Values, ValuesMut,
+};
+pub use crate::stats::{ArtStats, NodeStats};
+pub use crate::validate::{ValidationError, Violation};
+
 /// Art is an **adaptive radix tree**, which are also known as radix trees and
 /// prefix trees.
 ///
 /// Art requires 3 generic parameters, where `K` needs to implement the [ArtKey] trait and `V` is
 /// self-explanatory. `MAX_PARTIAL_LEN` specifies the size of the array used by each inner node to
-/// store the common prefix.
+/// store the common prefix. The optional fourth parameter is the [NodeAllocator] holding the
+/// nodes and leaves, see [alloc](crate::alloc).
 ///
 /// **Note:** `MAX_PARTIAL_LEN` is designed as a constant generic parameter because setting its size
 /// requires users trade-off.
@@ -35,20 +53,102 @@ use crate::ArtKey;
 ///
 /// See [The Adaptive Radix Tree: ARTful indexing for Main-Memory Databases](https://db.in.tum.de/~leis/papers/ART.pdf)
 /// for more information.
-pub struct Art<K, V, const MAX_PARTIAL_LEN: usize = 8>
+pub struct Art<K, V, const MAX_PARTIAL_LEN: usize = 8, A = Global>
 where
     K: ArtKey,
+    A: NodeAllocator,
 {
     size: usize,
     root: ArtNode<K, V, MAX_PARTIAL_LEN>,
+    alloc: A,
 }
 
 impl<K: ArtKey, V, const MAX_PARTIAL_LEN: usize> Art<K, V, MAX_PARTIAL_LEN> {
     pub fn new() -> Art<K, V, MAX_PARTIAL_LEN> {
+        Art::new_in(Global)
+    }
+}
+
+impl<K: ArtKey, V, const MAX_PARTIAL_LEN: usize, A: NodeAllocator> Art<K, V, MAX_PARTIAL_LEN, A> {
+    /// Creates an empty map whose nodes and leaves are allocated by `alloc`.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use artful::alloc::FreeList;
+    /// use artful::Art;
+    ///
+    /// let mut art = Art::<String, u32, 8, FreeList>::new_in(FreeList::new());
+    /// art.insert("a".to_string(), 1);
+    /// assert_eq!(art.get("a"), Some(&1));
+    /// ```
+    pub fn new_in(alloc: A) -> Art<K, V, MAX_PARTIAL_LEN, A> {
         Art {
             size: 0,
             root: ArtNode::none(),
+            alloc,
+        }
+    }
+
+    /// Returns a reference to the allocator of the map.
+    pub fn allocator(&self) -> &A {
+        &self.alloc
+    }
+
+    /// Builds a map from key-value pairs sorted by strictly increasing keys.
+    ///
+    /// The tree is built bottom-up in a single pass, every inner node is allocated once at
+    /// its final size with its compressed path, instead of growing and splitting nodes as
+    /// repeated inserts do. The resulting tree has the same shape as the one built by
+    /// inserting the pairs.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a key is less than or equal to the key before it.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use artful::art::BulkLoadError;
+    /// use artful::Art;
+    ///
+    /// let art = Art::<u32, &str, 8>::from_sorted_iter([(1, "a"), (2, "b"), (256, "c")]).unwrap();
+    /// assert_eq!(art.get(&256), Some(&"c"));
+    ///
+    /// let result = Art::<u32, &str, 8>::from_sorted_iter([(2, "b"), (1, "a")]);
+    /// assert_eq!(result.err(), Some(BulkLoadError::OutOfOrder { index: 1 }));
+    /// ```
+    pub fn from_sorted_iter<I>(iter: I) -> Result<Art<K, V, MAX_PARTIAL_LEN, A>, BulkLoadError>
+    where
+        I: IntoIterator<Item = (K, V)>,
+        A: Default,
+    {
+        let mut builder = BulkBuilder::new_in(A::default());
+        for (key, val) in iter {
+            builder.push(key, val)?;
         }
+        Ok(Art::from_builder(builder))
+    }
+
+    /// Writes the tree in the binary format of [disk](crate::disk), which an
+    /// [ArtView](crate::disk::ArtView) queries in place.
+    ///
+    /// Values are written as their bytes. The writer is not buffered here, so a file should
+    /// be wrapped in a [BufWriter](std::io::BufWriter).
+    ///
+    /// # Errors
+    ///
+    /// Returns any error of the writer.
+    pub fn write_to<W: io::Write>(&self, writer: W) -> io::Result<()>
+    where
+        V: AsRef<[u8]>,
+    {
+        crate::disk::write(&self.root, self.size, writer)
+    }
+
+    pub(crate) fn from_builder(
+        builder: BulkBuilder<K, V, MAX_PARTIAL_LEN, A>,
+    ) -> Art<K, V, MAX_PARTIAL_LEN, A> {
+        let (root, size, alloc) = builder.finish();
+        Art { size, root, alloc }
     }
 
     /// Returns a reference to the value corresponding to the key.
@@ -61,12 +161,21 @@ impl<K: ArtKey, V, const MAX_PARTIAL_LEN: usize> Art<K, V, MAX_PARTIAL_LEN> {
     /// let mut art = Art::<i32, &str, 8>::new();
     /// assert_eq!(art.insert(37, "a"), None);
     /// assert_eq!(art.insert(37, "b"), Some("a"));
+    ///
+    /// let mut art = Art::<String, i32, 8>::new();
+    /// art.insert("a".to_string(), 1);
+    /// assert_eq!(art.get("a"), Some(&1));
     /// ```
-    pub fn get(&self, key: &K) -> Option<&V> {
-        ArtNode::get(&self.root, key.get_bytes(), 0)
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: ArtKey + ?Sized,
+    {
+        ArtNode::get(&self.root, key.get_bytes().as_ref(), 0)
     }
 
-    /// Returns the key-value pair corresponding to the supplied key.
+    /// Returns the key-value pair corresponding to the supplied key, the returned key is the
+    /// one stored in the map.
     ///
     /// The key may be any borrowed form of the map’s key type and must be implementation `ArtKey` trait.
     ///
@@ -77,13 +186,18 @@ impl<K: ArtKey, V, const MAX_PARTIAL_LEN: usize> Art<K, V, MAX_PARTIAL_LEN> {
     /// art.insert(1, "a");
     /// assert_eq!(art.get_key_value(&1), Some((&1, &"a")));
     /// assert_eq!(art.get_key_value(&2), None);
+    ///
+    /// let mut art = Art::<String, i32, 8>::new();
+    /// art.insert("a".to_string(), 1);
+    /// assert_eq!(art.get_key_value("a"), Some((&"a".to_string(), &1)));
     /// ```
-    pub fn get_key_value<'a>(&'a self, key: &'a K) -> Option<(&'a K, &'a V)> {
-        if let Some(val) = ArtNode::get(&self.root, key.get_bytes(), 0) {
-            return Some((key, &*val));
-        }
-
-        None
+    pub fn get_key_value<Q>(&self, key: &Q) -> Option<(&K, &V)>
+    where
+        K: Borrow<Q>,
+        Q: ArtKey + ?Sized,
+    {
+        let leaf = ArtNode::get_leaf(&self.root, key.get_bytes().as_ref(), 0)?;
+        Some((&leaf.key, &leaf.val))
     }
 
     /// Returns a mutable reference to the value corresponding to the key.
@@ -104,8 +218,12 @@ impl<K: ArtKey, V, const MAX_PARTIAL_LEN: usize> Art<K, V, MAX_PARTIAL_LEN> {
     /// }
     /// assert_eq!(art.get(&1), Some(&"b"));
     /// ```
-    pub fn get_mut(&mut self, key: &K) -> Option<&mut V> {
-        ArtNode::get_mut(&mut self.root, key.get_bytes(), 0)
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: ArtKey + ?Sized,
+    {
+        ArtNode::get_mut(&mut self.root, key.get_bytes().as_ref(), 0)
     }
 
     /// Inserts a key-value pair into the map.
@@ -123,7 +241,7 @@ impl<K: ArtKey, V, const MAX_PARTIAL_LEN: usize> Art<K, V, MAX_PARTIAL_LEN> {
     /// assert_eq!(art.get(&1), Some(&"a"));
     /// ```
     pub fn insert(&mut self, key: K, val: V) -> Option<V> {
-        if let Some(old_val) = ArtNode::insert(&mut self.root, key, val, 0) {
+        if let Some(old_val) = ArtNode::insert(&mut self.root, &mut self.alloc, key, val, 0) {
             return Some(old_val);
         }
 
@@ -131,15 +249,146 @@ impl<K: ArtKey, V, const MAX_PARTIAL_LEN: usize> Art<K, V, MAX_PARTIAL_LEN> {
         None
     }
 
+    /// Gets the given key's corresponding entry in the map for in-place manipulation.
+    ///
+    /// The tree is walked once, inserting into a vacant entry continues from where the walk
+    /// stopped.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use artful::Art;
+    ///
+    /// let mut art = Art::<String, usize, 8>::new();
+    /// for word in ["a", "b", "a", "ab"] {
+    ///     *art.entry(word.to_string()).or_insert(0) += 1;
+    /// }
+    /// assert_eq!(art.get("a"), Some(&2));
+    /// assert_eq!(art.size(), 3);
+    /// ```
+    pub fn entry(&mut self, key: K) -> Entry<'_, K, V, MAX_PARTIAL_LEN, A> {
+        let site = ArtNode::seek_insert_site(&mut self.root, key.get_bytes().as_ref(), 0);
+        match site {
+            InsertSite::Occupied(leaf) => Entry::Occupied(OccupiedEntry { leaf }),
+            InsertSite::Vacant(site) => Entry::Vacant(VacantEntry {
+                key,
+                site,
+                size: &mut self.size,
+                alloc: &mut self.alloc,
+            }),
+        }
+    }
+
     /// Removes a key from the map, returning the value at the key if the key was previously in the map.
     ///
     /// The key may be any borrowed form of the map’s key type and must be implementation `ArtKey` trait.
-    pub fn remove(&mut self, key: &K) -> Option<V> {
-        let val = ArtNode::remove(&mut self.root, key.get_bytes(), 0)?;
+    ///
+    /// # Examples
+    /// ```rust
+    /// use artful::Art;
+    ///
+    /// let mut art = Art::<String, i32, 8>::new();
+    /// art.insert("a".to_string(), 1);
+    /// assert_eq!(art.remove("a"), Some(1));
+    /// assert_eq!(art.remove("a"), None);
+    /// ```
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: ArtKey + ?Sized,
+    {
+        let (_, val) =
+            ArtNode::remove(&mut self.root, &mut self.alloc, key.get_bytes().as_ref(), 0)?;
         self.size -= 1;
         Some(val)
     }
 
+    /// Returns the first key-value pair in the map. The key in this pair is the minimum key
+    /// in the map.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use artful::Art;
+    ///
+    /// let mut art = Art::<u64, &str, 8>::new();
+    /// assert_eq!(art.first_key_value(), None);
+    /// art.insert(256, "b");
+    /// art.insert(1, "a");
+    /// assert_eq!(art.first_key_value(), Some((&1, &"a")));
+    /// ```
+    pub fn first_key_value(&self) -> Option<(&K, &V)> {
+        let leaf = ArtNode::minimum_child(&self.root)?;
+        Some((&leaf.key, &leaf.val))
+    }
+
+    /// Returns the last key-value pair in the map. The key in this pair is the maximum key
+    /// in the map.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use artful::Art;
+    ///
+    /// let mut art = Art::<u64, &str, 8>::new();
+    /// art.insert(256, "b");
+    /// art.insert(1, "a");
+    /// assert_eq!(art.last_key_value(), Some((&256, &"b")));
+    /// ```
+    pub fn last_key_value(&self) -> Option<(&K, &V)> {
+        let leaf = ArtNode::maximum_child(&self.root)?;
+        Some((&leaf.key, &leaf.val))
+    }
+
+    /// Removes and returns the first element in the map. The key of this element is the
+    /// minimum key that was in the map.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use artful::Art;
+    ///
+    /// let mut art = Art::<u64, &str, 8>::new();
+    /// art.insert(256, "b");
+    /// art.insert(1, "a");
+    /// assert_eq!(art.pop_first(), Some((1, "a")));
+    /// assert_eq!(art.pop_first(), Some((256, "b")));
+    /// assert_eq!(art.pop_first(), None);
+    /// ```
+    pub fn pop_first(&mut self) -> Option<(K, V)> {
+        let key = ArtNode::minimum_child(&self.root)?
+            .key
+            .get_bytes()
+            .as_ref()
+            .to_vec();
+        self.remove_bytes(&key)
+    }
+
+    /// Removes and returns the last element in the map. The key of this element is the
+    /// maximum key that was in the map.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use artful::Art;
+    ///
+    /// let mut art = Art::<u64, &str, 8>::new();
+    /// art.insert(256, "b");
+    /// art.insert(1, "a");
+    /// assert_eq!(art.pop_last(), Some((256, "b")));
+    /// assert_eq!(art.pop_last(), Some((1, "a")));
+    /// assert_eq!(art.pop_last(), None);
+    /// ```
+    pub fn pop_last(&mut self) -> Option<(K, V)> {
+        let key = ArtNode::maximum_child(&self.root)?
+            .key
+            .get_bytes()
+            .as_ref()
+            .to_vec();
+        self.remove_bytes(&key)
+    }
+
+    fn remove_bytes(&mut self, key: &[u8]) -> Option<(K, V)> {
+        let entry = ArtNode::remove(&mut self.root, &mut self.alloc, key, 0)?;
+        self.size -= 1;
+        Some(entry)
+    }
+
     /// Returns the size of key-value pairs in Art.
     ///
     /// For insertion, size is incremented only when the key is different. For deletion size is decremented
@@ -157,6 +406,464 @@ impl<K: ArtKey, V, const MAX_PARTIAL_LEN: usize> Art<K, V, MAX_PARTIAL_LEN> {
     pub fn size(&self) -> usize {
         self.size
     }
+
+    /// Returns the number of nodes of each type, their memory usage and the depth of
+    /// the leaves.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use artful::Art;
+    ///
+    /// let mut art = Art::<String, u32, 8>::new();
+    /// art.insert("apple".to_string(), 1);
+    /// art.insert("apricot".to_string(), 2);
+    ///
+    /// let stats = art.stats();
+    /// assert_eq!(stats.node4.count, 1);
+    /// assert_eq!(stats.leaves.count, 2);
+    /// assert_eq!(stats.max_depth, 1);
+    /// ```
+    pub fn stats(&self) -> ArtStats {
+        ArtStats::collect(&self.root)
+    }
+
+    /// Walks the tree and checks its structural invariants, returning the first violation
+    /// with the key bytes leading to the bad node.
+    ///
+    /// The children of every node must match its header, with sorted key bytes in Node4
+    /// and Node16 and a consistent index in Node48, and no node may be smaller than a
+    /// removal leaves it. The compressed paths must match every leaf below them, and the
+    /// number of leaves must match [Art::size]. This is meant for tests and fuzzing, it
+    /// visits every node.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use artful::Art;
+    ///
+    /// let mut art = Art::<String, u32, 8>::new();
+    /// for i in 0..1000 {
+    ///     art.insert(format!("key{}", i), i);
+    /// }
+    /// for i in 0..500 {
+    ///     art.remove(&format!("key{}", i * 2));
+    /// }
+    /// assert_eq!(art.validate(), Ok(()));
+    /// ```
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        crate::validate::validate(&self.root, self.size)
+    }
+
+    /// Gets an iterator over the entries of the map, sorted by key.
+    ///
+    /// Keys are ordered by their bytes in lexicographic order, a key that is a prefix
+    /// of another key comes first.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use artful::Art;
+    ///
+    /// let mut art = Art::<String, i32, 8>::new();
+    /// art.insert("b".to_string(), 2);
+    /// art.insert("ab".to_string(), 1);
+    /// art.insert("a".to_string(), 0);
+    ///
+    /// let mut iter = art.iter();
+    /// assert_eq!(iter.next(), Some((&"a".to_string(), &0)));
+    /// assert_eq!(iter.next_back(), Some((&"b".to_string(), &2)));
+    /// assert_eq!(iter.next(), Some((&"ab".to_string(), &1)));
+    /// assert_eq!(iter.next(), None);
+    /// ```
+    pub fn iter(&self) -> Iter<'_, K, V, MAX_PARTIAL_LEN> {
+        Iter::new(&self.root, self.size)
+    }
+
+    /// Gets a mutable iterator over the entries of the map, sorted by key.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use artful::Art;
+    ///
+    /// let mut art = Art::<String, i32, 8>::new();
+    /// art.insert("a".to_string(), 1);
+    /// art.insert("b".to_string(), 2);
+    ///
+    /// for (_, val) in art.iter_mut() {
+    ///     *val += 10;
+    /// }
+    /// assert_eq!(art.get(&"a".to_string()), Some(&11));
+    /// assert_eq!(art.get(&"b".to_string()), Some(&12));
+    /// ```
+    pub fn iter_mut(&mut self) -> IterMut<'_, K, V, MAX_PARTIAL_LEN> {
+        IterMut::new(&mut self.root, self.size)
+    }
+
+    /// Gets an iterator over the keys of the map, in sorted order.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use artful::Art;
+    ///
+    /// let mut art = Art::<String, i32, 8>::new();
+    /// art.insert("b".to_string(), 2);
+    /// art.insert("a".to_string(), 1);
+    ///
+    /// let keys: Vec<&String> = art.keys().collect();
+    /// assert_eq!(keys, ["a", "b"]);
+    /// ```
+    pub fn keys(&self) -> Keys<'_, K, V, MAX_PARTIAL_LEN> {
+        Keys { inner: self.iter() }
+    }
+
+    /// Gets an iterator over the values of the map, in order by key.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use artful::Art;
+    ///
+    /// let mut art = Art::<String, i32, 8>::new();
+    /// art.insert("b".to_string(), 2);
+    /// art.insert("a".to_string(), 1);
+    ///
+    /// let values: Vec<&i32> = art.values().collect();
+    /// assert_eq!(values, [&1, &2]);
+    /// ```
+    pub fn values(&self) -> Values<'_, K, V, MAX_PARTIAL_LEN> {
+        Values { inner: self.iter() }
+    }
+
+    /// Gets a mutable iterator over the values of the map, in order by key.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use artful::Art;
+    ///
+    /// let mut art = Art::<String, String, 8>::new();
+    /// art.insert("a".to_string(), "x".to_string());
+    ///
+    /// for val in art.values_mut() {
+    ///     val.push('y');
+    /// }
+    /// assert_eq!(art.get(&"a".to_string()), Some(&"xy".to_string()));
+    /// ```
+    pub fn values_mut(&mut self) -> ValuesMut<'_, K, V, MAX_PARTIAL_LEN> {
+        ValuesMut {
+            inner: self.iter_mut(),
+        }
+    }
+
+    /// Gets an iterator over the entries whose key bytes start with `prefix`, sorted by key.
+    ///
+    /// The tree is descended once to the node covering the prefix, then every leaf below
+    /// it is yielded.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use artful::Art;
+    ///
+    /// let mut art = Art::<String, i32, 8>::new();
+    /// art.insert("tenant/a/x".to_string(), 1);
+    /// art.insert("tenant/b/y".to_string(), 2);
+    /// art.insert("tenant/a/z".to_string(), 3);
+    ///
+    /// let values: Vec<&i32> = art.prefix_iter(b"tenant/a/").map(|(_, v)| v).collect();
+    /// assert_eq!(values, [&1, &3]);
+    /// assert_eq!(art.prefix_iter(b"tenant/c").next(), None);
+    /// ```
+    pub fn prefix_iter(&self, prefix: &[u8]) -> PrefixIter<'_, K, V, MAX_PARTIAL_LEN> {
+        PrefixIter::new(&self.root, prefix)
+    }
+
+    /// Gets a mutable iterator over the entries whose key bytes start with `prefix`,
+    /// sorted by key.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use artful::Art;
+    ///
+    /// let mut art = Art::<String, i32, 8>::new();
+    /// art.insert("user/1".to_string(), 1);
+    /// art.insert("user/2".to_string(), 2);
+    /// art.insert("group/1".to_string(), 3);
+    ///
+    /// for (_, val) in art.prefix_iter_mut(b"user/") {
+    ///     *val = 0;
+    /// }
+    /// assert_eq!(art.values().collect::<Vec<_>>(), [&3, &0, &0]);
+    /// ```
+    pub fn prefix_iter_mut(&mut self, prefix: &[u8]) -> PrefixIterMut<'_, K, V, MAX_PARTIAL_LEN> {
+        PrefixIterMut::new(&mut self.root, prefix)
+    }
+
+    /// Constructs a double-ended iterator over a sub-range of entries in the map, sorted
+    /// by key.
+    ///
+    /// Each end of the range may be included, excluded or unbounded. Subtrees outside of
+    /// the range are pruned while descending, they are never visited.
+    ///
+    /// # Panics
+    ///
+    /// Panics if range `start > end`. Panics if range `start == end` and both bounds are
+    /// `Excluded`.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use artful::Art;
+    /// use std::ops::Bound::{Excluded, Included};
+    ///
+    /// let mut art = Art::<String, i32, 8>::new();
+    /// for (i, key) in ["a", "ab", "abc", "b", "c"].iter().enumerate() {
+    ///     art.insert(key.to_string(), i as i32);
+    /// }
+    ///
+    /// let values: Vec<&i32> = art.range::<str, _>((Included("ab"), Excluded("c"))).map(|(_, v)| v).collect();
+    /// assert_eq!(values, [&1, &2, &3]);
+    ///
+    /// let range = (Excluded("a".to_string()), Included("b".to_string()));
+    /// assert_eq!(art.range(range).next_back(), Some((&"b".to_string(), &3)));
+    /// ```
+    pub fn range<T, R>(&self, range: R) -> Range<'_, K, V, MAX_PARTIAL_LEN>
+    where
+        K: Borrow<T>,
+        T: ArtKey + ?Sized,
+        R: RangeBounds<T>,
+    {
+        let (lower, upper) = range_bounds(&range);
+        Range::new(&self.root, as_slice(&lower), as_slice(&upper))
+    }
+
+    /// Constructs a mutable double-ended iterator over a sub-range of entries in the map,
+    /// sorted by key.
+    ///
+    /// # Panics
+    ///
+    /// Panics if range `start > end`. Panics if range `start == end` and both bounds are
+    /// `Excluded`.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use artful::Art;
+    /// use std::ops::Bound::{Included, Unbounded};
+    ///
+    /// let mut art = Art::<String, i32, 8>::new();
+    /// for (i, key) in ["a", "b", "c"].iter().enumerate() {
+    ///     art.insert(key.to_string(), i as i32);
+    /// }
+    ///
+    /// for (_, val) in art.range_mut::<str, _>((Included("b"), Unbounded)) {
+    ///     *val += 10;
+    /// }
+    /// assert_eq!(art.get("a"), Some(&0));
+    /// assert_eq!(art.get("c"), Some(&12));
+    /// ```
+    pub fn range_mut<T, R>(&mut self, range: R) -> RangeMut<'_, K, V, MAX_PARTIAL_LEN>
+    where
+        K: Borrow<T>,
+        T: ArtKey + ?Sized,
+        R: RangeBounds<T>,
+    {
+        let (lower, upper) = range_bounds(&range);
+        RangeMut::new(&mut self.root, as_slice(&lower), as_slice(&upper))
+    }
+}
+
+type BoundBytes<'a, K> = Bound<<K as ArtKey>::Bytes<'a>>;
+
+/// Converts the bounds of a range to the key bytes, panics on a decreasing range just like
+/// the `BTreeMap` does.
+fn range_bounds<'a, T: ArtKey + ?Sized + 'a, R: RangeBounds<T>>(
+    range: &'a R,
+) -> (BoundBytes<'a, T>, BoundBytes<'a, T>) {
+    let lower = range.start_bound().map(|key| key.get_bytes());
+    let upper = range.end_bound().map(|key| key.get_bytes());
+    match (as_slice(&lower), as_slice(&upper)) {
+        (Bound::Excluded(start), Bound::Excluded(end)) if start == end => {
+            panic!("range start and end are equal and excluded in Art")
+        }
+        (
+            Bound::Included(start) | Bound::Excluded(start),
+            Bound::Included(end) | Bound::Excluded(end),
+        ) if start > end => panic!("range start is greater than range end in Art"),
+        _ => (lower, upper),
+    }
+}
+
+#[inline]
+fn as_slice<B: AsRef<[u8]>>(bound: &Bound<B>) -> Bound<&[u8]> {
+    bound.as_ref().map(|bytes| bytes.as_ref())
+}
+
+impl<K: ArtKey, V, const MAX_PARTIAL_LEN: usize, A: NodeAllocator + Default> Default
+    for Art<K, V, MAX_PARTIAL_LEN, A>
+{
+    fn default() -> Art<K, V, MAX_PARTIAL_LEN, A> {
+        Art::new_in(A::default())
+    }
+}
+
+impl<K: ArtKey, V, const MAX_PARTIAL_LEN: usize, A: NodeAllocator + Default> FromIterator<(K, V)>
+    for Art<K, V, MAX_PARTIAL_LEN, A>
+{
+    fn from_iter<T: IntoIterator<Item = (K, V)>>(iter: T) -> Art<K, V, MAX_PARTIAL_LEN, A> {
+        let mut art = Art::default();
+        art.extend(iter);
+        art
+    }
+}
+
+impl<K: ArtKey, V, const MAX_PARTIAL_LEN: usize, A: NodeAllocator> Extend<(K, V)>
+    for Art<K, V, MAX_PARTIAL_LEN, A>
+{
+    #[inline]
+    fn extend<T: IntoIterator<Item = (K, V)>>(&mut self, iter: T) {
+        for (key, val) in iter {
+            self.insert(key, val);
+        }
+    }
+}
+
+impl<'a, K: ArtKey + Copy, V: Copy, const MAX_PARTIAL_LEN: usize, A: NodeAllocator>
+    Extend<(&'a K, &'a V)> for Art<K, V, MAX_PARTIAL_LEN, A>
+{
+    fn extend<T: IntoIterator<Item = (&'a K, &'a V)>>(&mut self, iter: T) {
+        self.extend(iter.into_iter().map(|(key, val)| (*key, *val)));
+    }
+}
+
+impl<K: ArtKey, V, const MAX_PARTIAL_LEN: usize, A: NodeAllocator> IntoIterator
+    for Art<K, V, MAX_PARTIAL_LEN, A>
+{
+    type Item = (K, V);
+    type IntoIter = IntoIter<K, V, MAX_PARTIAL_LEN, A>;
+
+    /// Gets an owning iterator over the entries of the map, sorted by key.
+    fn into_iter(self) -> IntoIter<K, V, MAX_PARTIAL_LEN, A> {
+        // the iterator takes over the nodes and the allocator, nothing is left to drop.
+        let art = std::mem::ManuallyDrop::new(self);
+        let root = unsafe { std::ptr::read(&art.root) };
+        let alloc = unsafe { std::ptr::read(&art.alloc) };
+        IntoIter::new(root, art.size, alloc)
+    }
+}
+
+impl<'a, K: ArtKey, V, const MAX_PARTIAL_LEN: usize, A: NodeAllocator> IntoIterator
+    for &'a Art<K, V, MAX_PARTIAL_LEN, A>
+{
+    type Item = (&'a K, &'a V);
+    type IntoIter = Iter<'a, K, V, MAX_PARTIAL_LEN>;
+
+    fn into_iter(self) -> Iter<'a, K, V, MAX_PARTIAL_LEN> {
+        self.iter()
+    }
+}
+
+impl<'a, K: ArtKey, V, const MAX_PARTIAL_LEN: usize, A: NodeAllocator> IntoIterator
+    for &'a mut Art<K, V, MAX_PARTIAL_LEN, A>
+{
+    type Item = (&'a K, &'a mut V);
+    type IntoIter = IterMut<'a, K, V, MAX_PARTIAL_LEN>;
+
+    fn into_iter(self) -> IterMut<'a, K, V, MAX_PARTIAL_LEN> {
+        self.iter_mut()
+    }
+}
+
+impl<K, Q, V, const MAX_PARTIAL_LEN: usize, A: NodeAllocator> Index<&Q>
+    for Art<K, V, MAX_PARTIAL_LEN, A>
+where
+    K: ArtKey + Borrow<Q>,
+    Q: ArtKey + ?Sized,
+{
+    type Output = V;
+
+    /// Returns a reference to the value corresponding to the supplied key.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the key is not present in the `Art`.
+    #[inline]
+    fn index(&self, key: &Q) -> &V {
+        self.get(key).expect("no entry found for key")
+    }
+}
+
+/// The copy gets an allocator of its own.
+impl<K, V, const MAX_PARTIAL_LEN: usize, A> Clone for Art<K, V, MAX_PARTIAL_LEN, A>
+where
+    K: ArtKey + Clone,
+    V: Clone,
+    A: NodeAllocator + Default,
+{
+    fn clone(&self) -> Art<K, V, MAX_PARTIAL_LEN, A> {
+        let mut alloc = A::default();
+        Art {
+            size: self.size,
+            root: self.root.clone_in(&mut alloc),
+            alloc,
+        }
+    }
+}
+
+impl<K: ArtKey, V, const MAX_PARTIAL_LEN: usize, A: NodeAllocator> Drop
+    for Art<K, V, MAX_PARTIAL_LEN, A>
+{
+    fn drop(&mut self) {
+        let root = std::mem::take(&mut self.root);
+        if A::RELEASES_ON_DROP && !std::mem::needs_drop::<K>() && !std::mem::needs_drop::<V>() {
+            // the allocator is dropped next and releases every node at once.
+            std::mem::forget(root);
+            return;
+        }
+        root.free(&mut self.alloc);
+    }
+}
+
+impl<K: ArtKey + Debug, V: Debug, const MAX_PARTIAL_LEN: usize, A: NodeAllocator> Debug
+    for Art<K, V, MAX_PARTIAL_LEN, A>
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+/// Maps are equal when they hold the same entries, regardless of the shape of their trees.
+impl<K: ArtKey + PartialEq, V: PartialEq, const MAX_PARTIAL_LEN: usize, A: NodeAllocator> PartialEq
+    for Art<K, V, MAX_PARTIAL_LEN, A>
+{
+    fn eq(&self, other: &Art<K, V, MAX_PARTIAL_LEN, A>) -> bool {
+        self.size == other.size && self.iter().eq(other.iter())
+    }
+}
+
+impl<K: ArtKey + Eq, V: Eq, const MAX_PARTIAL_LEN: usize, A: NodeAllocator> Eq
+    for Art<K, V, MAX_PARTIAL_LEN, A>
+{
+}
+
+impl<K: ArtKey + Hash, V: Hash, const MAX_PARTIAL_LEN: usize, A: NodeAllocator> Hash
+    for Art<K, V, MAX_PARTIAL_LEN, A>
+{
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_usize(self.size);
+        for entry in self.iter() {
+            entry.hash(state);
+        }
+    }
+}
+
+impl<K: ArtKey + PartialOrd, V: PartialOrd, const MAX_PARTIAL_LEN: usize, A: NodeAllocator>
+    PartialOrd for Art<K, V, MAX_PARTIAL_LEN, A>
+{
+    fn partial_cmp(&self, other: &Art<K, V, MAX_PARTIAL_LEN, A>) -> Option<Ordering> {
+        self.iter().partial_cmp(other.iter())
+    }
+}
+
+impl<K: ArtKey + Ord, V: Ord, const MAX_PARTIAL_LEN: usize, A: NodeAllocator> Ord
+    for Art<K, V, MAX_PARTIAL_LEN, A>
+{
+    fn cmp(&self, other: &Art<K, V, MAX_PARTIAL_LEN, A>) -> Ordering {
+        self.iter().cmp(other.iter())
+    }
 }
 
 #[cfg(test)]
@@ -177,14 +884,14 @@ mod test {
     ) {
         let mut art = Art::<T, T, 8>::new();
         // get after insert and get
-        for i in range.clone().into_iter() {
+        for i in range.clone() {
             assert_eq!(art.get(&i), None);
             assert_eq!(art.insert(i, i), None);
             assert_eq!(art.get(&i), Some(&i));
         }
 
         // get_mut after get
-        for i in range.clone().into_iter() {
+        for i in range.clone() {
             let old_val = art.get_mut(&i).unwrap();
             *old_val += mut_value;
             assert_eq!(art.get(&i), Some(&(i + mut_value)))
@@ -236,4 +943,893 @@ mod test {
     fn basic_primitive_u64() {
         primitive::<u64>(0..10000000, 1);
     }
+
+    #[test]
+    fn iter_sorted() {
+        let mut art = Art::<String, usize, 8>::new();
+        let mut keys = vec![
+            "",
+            "a",
+            "ab",
+            "abc",
+            "abd",
+            "b",
+            "ba",
+            "this:key:has:a:long:prefix:3",
+            "this:key:has:a:long:common:prefix:2",
+            "this:key:has:a:long:common:prefix:1",
+        ];
+        for (i, key) in keys.iter().enumerate() {
+            assert_eq!(art.insert(key.to_string(), i), None);
+        }
+        keys.sort();
+
+        let forward: Vec<&str> = art.keys().map(|key| key.as_str()).collect();
+        assert_eq!(forward, keys);
+        let mut backward: Vec<&str> = art.keys().rev().map(|key| key.as_str()).collect();
+        backward.reverse();
+        assert_eq!(backward, keys);
+        assert_eq!(art.iter().len(), keys.len());
+    }
+
+    #[test]
+    fn iter_double_ended_meet() {
+        let mut art = Art::<u8, u8, 8>::new();
+        for i in 0..=u8::MAX {
+            art.insert(i, i);
+        }
+
+        let mut iter = art.iter();
+        let mut expected = 0..=u8::MAX;
+        loop {
+            let front = iter.next();
+            assert_eq!(front.map(|(k, _)| *k), expected.next());
+            let back = iter.next_back();
+            assert_eq!(back.map(|(k, _)| *k), expected.next_back());
+            assert_eq!(iter.len(), expected.len());
+            if front.is_none() {
+                break;
+            }
+        }
+    }
+
+    #[test]
+    fn iter_mut_values_mut() {
+        let mut art = Art::<u8, u16, 8>::new();
+        assert_eq!(art.iter_mut().next(), None);
+        for i in 0..100 {
+            art.insert(i, i as u16);
+        }
+
+        for (key, val) in art.iter_mut() {
+            *val += *key as u16;
+        }
+        for val in art.values_mut().rev() {
+            *val += 1;
+        }
+        let values: Vec<u16> = art.values().copied().collect();
+        let expected: Vec<u16> = (0..100).map(|i| i * 2 + 1).collect();
+        assert_eq!(values, expected);
+    }
+
+    fn words(alphabet: &[char], max_len: usize) -> Vec<String> {
+        let mut words = vec![String::new()];
+        let mut last = vec![String::new()];
+        for _ in 0..max_len {
+            last = last
+                .iter()
+                .flat_map(|word| alphabet.iter().map(move |c| format!("{}{}", word, c)))
+                .collect();
+            words.extend(last.iter().cloned());
+        }
+        words
+    }
+
+    fn range_against_btree<const MAX_PARTIAL_LEN: usize>() {
+        use std::collections::BTreeMap;
+        use std::ops::Bound;
+
+        let mut art = Art::<String, usize, MAX_PARTIAL_LEN>::new();
+        let mut btree = BTreeMap::new();
+        let keys = words(&['a', 'b', 'c'], 4)
+            .into_iter()
+            .enumerate()
+            .filter(|(i, _)| i % 3 != 1)
+            .map(|(_, key)| key)
+            .chain(["bbbbbbbbx", "bbbbbbbby", "cabcabcabz"].map(String::from));
+        for (i, key) in keys.enumerate() {
+            art.insert(key.clone(), i);
+            btree.insert(key, i);
+        }
+
+        let bounds = words(&['a', 'b', 'c', 'd'], 3)
+            .into_iter()
+            .chain(["bbbbbbbb", "bbbbbbbbz", "cabcabcab"].map(String::from))
+            .collect::<Vec<_>>();
+        let bound = |kind: usize, key: &String| match kind {
+            0 => Bound::Included(key.clone()),
+            1 => Bound::Excluded(key.clone()),
+            _ => Bound::Unbounded,
+        };
+        for start in bounds.iter() {
+            for end in bounds.iter().filter(|end| *end >= start) {
+                for kinds in 0..9 {
+                    let range = (bound(kinds / 3, start), bound(kinds % 3, end));
+                    if start == end && kinds == 4 {
+                        continue;
+                    }
+                    let expected: Vec<_> = btree.range(range.clone()).collect();
+                    assert_eq!(art.range(range.clone()).collect::<Vec<_>>(), expected);
+                    let mut backward: Vec<_> = art.range(range.clone()).rev().collect();
+                    backward.reverse();
+                    assert_eq!(backward, expected);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn range_matches_btree() {
+        range_against_btree::<8>();
+        range_against_btree::<1>();
+    }
+
+    fn prefix_against_filter<const MAX_PARTIAL_LEN: usize>() {
+        let mut art = Art::<String, usize, MAX_PARTIAL_LEN>::new();
+        let keys = words(&['a', 'b'], 5)
+            .into_iter()
+            .filter(|key| key.len() != 3)
+            .chain(["abababababx", "abababababy", "bbbbbbbbbbbbz"].map(String::from));
+        for (i, key) in keys.enumerate() {
+            art.insert(key, i);
+        }
+
+        let prefixes = words(&['a', 'b', 'c'], 4)
+            .into_iter()
+            .chain(["ababababab", "abababababx", "bbbbbbbbbb", "bbbbbbbbba"].map(String::from));
+        for prefix in prefixes {
+            let expected: Vec<_> = art
+                .iter()
+                .filter(|(key, _)| key.starts_with(prefix.as_str()))
+                .collect();
+            assert_eq!(
+                art.prefix_iter(prefix.as_bytes()).collect::<Vec<_>>(),
+                expected
+            );
+            let mut backward: Vec<_> = art.prefix_iter(prefix.as_bytes()).rev().collect();
+            backward.reverse();
+            assert_eq!(backward, expected);
+        }
+    }
+
+    #[test]
+    fn prefix_iter_matches_filter() {
+        prefix_against_filter::<8>();
+        prefix_against_filter::<2>();
+        prefix_against_filter::<0>();
+    }
+
+    #[test]
+    fn prefix_iter_mut_updates() {
+        let mut art = Art::<String, usize, 8>::new();
+        for key in ["tenant/a/1", "tenant/a/2", "tenant/ab", "tenant/b/1"] {
+            art.insert(key.to_string(), 0);
+        }
+
+        for (_, val) in art.prefix_iter_mut(b"tenant/a/") {
+            *val += 1;
+        }
+        let values: Vec<usize> = art.values().copied().collect();
+        assert_eq!(values, [1, 1, 0, 0]);
+        assert_eq!(art.prefix_iter_mut(b"tenant/c").next(), None);
+    }
+
+    #[test]
+    fn range_mut_and_panics() {
+        let mut art = Art::<String, usize, 8>::new();
+        for (i, key) in words(&['x', 'y'], 3).into_iter().enumerate() {
+            art.insert(key, i);
+        }
+
+        for (_, val) in art.range_mut("xy".to_string()..="y".to_string()) {
+            *val = 0;
+        }
+        let zeros: Vec<&String> = art
+            .iter()
+            .filter(|(_, v)| **v == 0)
+            .map(|(k, _)| k)
+            .collect();
+        assert_eq!(zeros, ["", "xy", "xyx", "xyy", "y"]);
+
+        let result = std::panic::catch_unwind(|| {
+            let art = Art::<String, usize, 8>::new();
+            art.range("b".to_string().."a".to_string()).count()
+        });
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn unsigned_keys_in_numeric_order() {
+        let mut art = Art::<u32, u32, 8>::new();
+        for key in [256, 1, 65536, 0, u32::MAX, 255] {
+            art.insert(key, key);
+        }
+        let keys: Vec<u32> = art.keys().copied().collect();
+        assert_eq!(keys, [0, 1, 255, 256, 65536, u32::MAX]);
+    }
+
+    #[test]
+    fn signed_keys_in_numeric_order() {
+        let mut art = Art::<i64, i64, 8>::new();
+        let mut expected = vec![i64::MIN, -256, -1, 0, 1, 255, i64::MAX];
+        for key in expected.iter().rev() {
+            art.insert(*key, *key);
+        }
+        let keys: Vec<i64> = art.keys().copied().collect();
+        assert_eq!(keys, expected);
+
+        let keys: Vec<i64> = art.range(-1..=255).map(|(k, _)| *k).collect();
+        assert_eq!(keys, [-1, 0, 1, 255]);
+
+        assert_eq!(art.remove(&-256), Some(-256));
+        expected.retain(|key| *key != -256);
+        let keys: Vec<i64> = art.keys().copied().collect();
+        assert_eq!(keys, expected);
+
+        let mut art = Art::<i8, i8, 8>::new();
+        for key in i8::MIN..=i8::MAX {
+            art.insert(key, key);
+        }
+        assert!(art.keys().copied().eq(i8::MIN..=i8::MAX));
+    }
+
+    #[test]
+    fn float_keys_in_total_order() {
+        let expected = [
+            f64::NEG_INFINITY,
+            -1.5e300,
+            -1.0,
+            -f64::MIN_POSITIVE,
+            -0.0,
+            0.0,
+            f64::MIN_POSITIVE,
+            0.5,
+            1.0,
+            f64::INFINITY,
+        ];
+        let mut art = Art::<f64, usize, 8>::new();
+        for (i, key) in expected.iter().enumerate().rev() {
+            art.insert(*key, i);
+        }
+        let values: Vec<usize> = art.values().copied().collect();
+        assert_eq!(values, (0..expected.len()).collect::<Vec<_>>());
+        assert_eq!(art.get(&-0.0), Some(&4));
+        assert_eq!(art.get(&0.0), Some(&5));
+
+        let mut art = Art::<f32, f32, 8>::new();
+        for key in [2.5f32, -2.5, 0.25, -0.25] {
+            art.insert(key, key);
+        }
+        let keys: Vec<f32> = art.keys().copied().collect();
+        assert_eq!(keys, [-2.5, -0.25, 0.25, 2.5]);
+    }
+
+    #[test]
+    fn composite_keys() {
+        let mut art = Art::<(u32, String), usize, 8>::new();
+        let mut expected = vec![];
+        for tenant in [256u32, 1, 0] {
+            for name in ["", "a", "a\0", "ab", "b"] {
+                art.insert((tenant, name.to_string()), expected.len());
+                expected.push((tenant, name.to_string()));
+            }
+        }
+        expected.sort();
+        let keys: Vec<(u32, String)> = art.keys().cloned().collect();
+        assert_eq!(keys, expected);
+        assert_eq!(art.get(&(1, "a\0".to_string())), Some(&7));
+        assert_eq!(art.get(&(1, "a\0\0".to_string())), None);
+
+        let mut art = Art::<&str, usize, 8>::new();
+        art.insert("b", 1);
+        art.insert("a", 0);
+        assert_eq!(art.get(&"a"), Some(&0));
+
+        let mut art = Art::<Vec<u8>, usize, 8>::new();
+        art.insert(vec![1, 2], 1);
+        art.insert(vec![1], 0);
+        let values: Vec<usize> = art.values().copied().collect();
+        assert_eq!(values, [0, 1]);
+    }
+
+    #[test]
+    fn borrowed_lookups() {
+        use std::ops::Bound;
+
+        let mut art = Art::<String, usize, 8>::new();
+        for (i, key) in ["a", "ab", "b"].iter().enumerate() {
+            art.insert(key.to_string(), i);
+        }
+        assert_eq!(art.get("ab"), Some(&1));
+        *art.get_mut("b").unwrap() += 10;
+        assert_eq!(art.get("b"), Some(&12));
+        let keys: Vec<&String> = art
+            .range::<str, _>((Bound::Included("a"), Bound::Excluded("b")))
+            .map(|(k, _)| k)
+            .collect();
+        assert_eq!(keys, ["a", "ab"]);
+        assert_eq!(art.remove("a"), Some(0));
+        assert_eq!(art.get("a"), None);
+
+        let mut art = Art::<Vec<u8>, usize, 8>::new();
+        art.insert(vec![1, 2, 3], 0);
+        assert_eq!(art.get(&[1, 2, 3][..]), Some(&0));
+        assert_eq!(art.remove(&[1, 2, 3][..]), Some(0));
+    }
+
+    #[test]
+    fn get_key_value_returns_stored_key() {
+        #[derive(Debug, PartialEq)]
+        struct Tagged {
+            name: &'static str,
+            tag: usize,
+        }
+
+        impl ArtKey for Tagged {
+            type Bytes<'a> = &'a [u8];
+
+            fn get_bytes(&self) -> &[u8] {
+                self.name.as_bytes()
+            }
+        }
+
+        let mut art = Art::<Tagged, usize, 8>::new();
+        art.insert(Tagged { name: "a", tag: 7 }, 1);
+        let probe = Tagged { name: "a", tag: 0 };
+        let (key, val) = art.get_key_value(&probe).unwrap();
+        assert_eq!((key.tag, *val), (7, 1));
+        assert!(art.get_key_value(&Tagged { name: "b", tag: 7 }).is_none());
+    }
+
+    #[test]
+    fn entry_counts() {
+        use super::Entry;
+        use std::collections::BTreeMap;
+
+        let mut art = Art::<String, usize, 2>::new();
+        let mut btree = BTreeMap::new();
+        let keys = words(&['a', 'b', 'c'], 4);
+        // every key is counted a different number of times, spread over several rounds.
+        for round in 0..3 {
+            for (i, key) in keys.iter().enumerate().filter(|(i, _)| i % 3 >= round) {
+                *art.entry(key.clone()).or_insert(0) += i;
+                *btree.entry(key.clone()).or_insert(0) += i;
+            }
+        }
+        assert_eq!(art.size(), btree.len());
+        assert!(art.iter().eq(btree.iter()));
+
+        // and_modify only touches occupied entries.
+        art.entry("abc".to_string()).and_modify(|v| *v = 0);
+        art.entry("abcd_".to_string()).and_modify(|v| *v = 0);
+        assert_eq!(art.get("abc"), Some(&0));
+        assert_eq!(art.get("abcd_"), None);
+
+        match art.entry("x".to_string()) {
+            Entry::Vacant(entry) => {
+                assert_eq!(entry.key(), "x");
+                *entry.insert(1) += 1;
+            }
+            Entry::Occupied(_) => unreachable!(),
+        }
+        match art.entry("x".to_string()) {
+            Entry::Occupied(mut entry) => {
+                assert_eq!(entry.insert(5), 2);
+                assert_eq!(entry.get(), &5);
+            }
+            Entry::Vacant(_) => unreachable!(),
+        }
+        assert_eq!(art.size(), btree.len() + 1);
+    }
+
+    #[test]
+    fn pop_first_last_against_btree() {
+        use std::collections::BTreeMap;
+
+        let mut art = Art::<u64, u64, 8>::new();
+        let mut btree = BTreeMap::new();
+        // dense low bytes grow full Node256, the sparse keys add deeper paths.
+        let keys = (0..1000u64).chain((0..64).map(|i| i << 40 | i));
+        for key in keys {
+            art.insert(key, key * 2);
+            btree.insert(key, key * 2);
+        }
+
+        while !btree.is_empty() {
+            assert_eq!(art.first_key_value(), btree.first_key_value());
+            assert_eq!(art.last_key_value(), btree.last_key_value());
+            assert_eq!(art.pop_first(), btree.pop_first());
+            assert_eq!(art.pop_last(), btree.pop_last());
+            assert_eq!(art.size(), btree.len());
+        }
+        assert_eq!(art.first_key_value(), None);
+        assert_eq!(art.pop_last(), None);
+        assert_eq!(art.iter().next(), None);
+    }
+
+    #[test]
+    fn remove_against_btree() {
+        use std::collections::BTreeMap;
+
+        let mut art = Art::<String, usize, 2>::new();
+        let mut btree = BTreeMap::new();
+        let keys = words(&['a', 'b', 'c', 'd', 'e'], 3);
+        for (i, key) in keys.iter().enumerate() {
+            art.insert(key.clone(), i);
+            btree.insert(key.clone(), i);
+        }
+
+        for (i, key) in keys.iter().enumerate().filter(|(i, _)| i % 3 != 1) {
+            assert_eq!(art.remove(key), btree.remove(key));
+            assert_eq!(art.remove(key), None, "removed {} twice", i);
+        }
+        assert_eq!(art.size(), btree.len());
+        assert!(art.iter().eq(btree.iter()));
+        for key in &keys {
+            assert_eq!(art.get(key), btree.get(key));
+        }
+    }
+
+    #[test]
+    fn std_traits() {
+        use std::collections::hash_map::DefaultHasher;
+        use std::hash::{Hash, Hasher};
+
+        fn hash<T: Hash>(value: &T) -> u64 {
+            let mut hasher = DefaultHasher::new();
+            value.hash(&mut hasher);
+            hasher.finish()
+        }
+
+        let mut art = Art::<String, usize, 2>::default();
+        for (i, key) in words(&['a', 'b', 'c', 'd', 'e', 'f'], 3)
+            .into_iter()
+            .enumerate()
+        {
+            art.insert(key, i);
+        }
+        for i in 0..300u32 {
+            art.insert(format!("n{}", char::from_u32(i).unwrap()), i as usize);
+        }
+
+        // the clone is deep, changing it leaves the original untouched.
+        let mut cloned = art.clone();
+        assert!(cloned.root == art.root);
+        assert_eq!(cloned, art);
+        assert_eq!(hash(&cloned), hash(&art));
+        *cloned.get_mut("abc").unwrap() += 1;
+        assert_ne!(cloned, art);
+        assert!(cloned > art);
+        assert_eq!(art.get("abc").map(|v| v + 1), cloned.get("abc").copied());
+
+        // the same entries inserted in another order are equal, whatever the tree shape.
+        let mut reversed = Art::<String, usize, 2>::new();
+        for (key, val) in art.iter().rev() {
+            reversed.insert(key.clone(), *val);
+        }
+        assert_eq!(reversed, art);
+        assert_eq!(reversed.cmp(&art), std::cmp::Ordering::Equal);
+
+        let mut small = Art::<u8, &str, 8>::new();
+        small.insert(2, "b");
+        small.insert(1, "a");
+        assert_eq!(format!("{:?}", small), r#"{1: "a", 2: "b"}"#);
+    }
+
+    #[test]
+    fn collect_extend_index() {
+        use std::collections::BTreeMap;
+
+        let pairs: Vec<(String, usize)> = words(&['a', 'b', 'c', 'd'], 3)
+            .into_iter()
+            .enumerate()
+            .map(|(i, key)| (key, i))
+            .collect();
+        let mut art: Art<String, usize, 2> = pairs[..40].iter().cloned().collect();
+        art.extend(pairs[40..].iter().cloned());
+        let btree: BTreeMap<String, usize> = pairs.iter().cloned().collect();
+        assert_eq!(art.size(), btree.len());
+        assert_eq!(art["abc"], btree["abc"]);
+
+        for (_, val) in &mut art {
+            *val += 1;
+        }
+        let mut total = 0;
+        for (_, val) in &art {
+            total += val;
+        }
+        assert_eq!(total, btree.values().map(|v| v + 1).sum::<usize>());
+
+        let mut ints = Art::<u32, u32, 8>::new();
+        ints.extend([(&1, &10), (&2, &20)]);
+        assert_eq!(ints[&2], 20);
+        let result = std::panic::catch_unwind(|| ints[&3]);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn into_iter_double_ended() {
+        use std::rc::Rc;
+
+        let keys = words(&['a', 'b', 'c'], 4);
+        let art: Art<String, usize, 8> = keys.iter().cloned().zip(0..).collect();
+        let mut sorted = keys.clone();
+        sorted.sort();
+
+        let mut iter = art.clone().into_iter();
+        assert_eq!(iter.len(), sorted.len());
+        let (mut front, mut back) = (0, sorted.len());
+        // alternate the ends at an uneven pace to meet in the middle of inner nodes.
+        while front < back {
+            for _ in 0..2 {
+                if front < back {
+                    assert_eq!(iter.next().map(|(k, _)| k).as_ref(), Some(&sorted[front]));
+                    front += 1;
+                }
+            }
+            if front < back {
+                back -= 1;
+                assert_eq!(
+                    iter.next_back().map(|(k, _)| k).as_ref(),
+                    Some(&sorted[back])
+                );
+            }
+            assert_eq!(iter.len(), back - front);
+        }
+        assert_eq!(iter.next(), None);
+        assert_eq!(iter.next_back(), None);
+
+        let owned: Vec<(String, usize)> = art.into_iter().collect();
+        assert!(owned.iter().map(|(k, _)| k).eq(sorted.iter()));
+
+        // the entries left in a dropped iterator are dropped as well.
+        let counter = Rc::new(());
+        let art: Art<u32, Rc<()>, 8> = (0..1000).map(|i| (i, counter.clone())).collect();
+        let mut iter = art.into_iter();
+        iter.next();
+        iter.next_back();
+        drop(iter);
+        assert_eq!(Rc::strong_count(&counter), 1);
+    }
+
+    fn bulk_load_same_shape<const MAX_PARTIAL_LEN: usize>() {
+        let mut keys = words(&['a', 'b', 'c'], 4);
+        keys.extend((0..300u32).map(|i| format!("bb{}", char::from_u32(i).unwrap())));
+        keys.extend((0..20).map(|i| format!("c{}", "x".repeat(i))));
+        keys.extend((0..40).map(|i| format!("long/common/prefix/{}", i)));
+        keys.sort();
+        keys.dedup();
+
+        let mut inserted = Art::<String, usize, MAX_PARTIAL_LEN>::new();
+        for (i, key) in keys.iter().enumerate() {
+            inserted.insert(key.clone(), i);
+        }
+        let pairs = keys.iter().cloned().zip(0..);
+        let loaded = Art::<String, usize, MAX_PARTIAL_LEN>::from_sorted_iter(pairs).unwrap();
+        assert_eq!(loaded.size(), keys.len());
+        assert!(loaded.root == inserted.root);
+        assert_eq!(
+            loaded.get("long/common/prefix/17"),
+            Some(&inserted["long/common/prefix/17"])
+        );
+    }
+
+    #[test]
+    fn from_sorted_iter_matches_insert() {
+        bulk_load_same_shape::<8>();
+        bulk_load_same_shape::<2>();
+        bulk_load_same_shape::<0>();
+
+        let loaded = Art::<u64, u64, 8>::from_sorted_iter((0..70000).map(|i| (i, i))).unwrap();
+        let mut inserted = Art::<u64, u64, 8>::new();
+        inserted.extend((0..70000).map(|i| (i, i)));
+        assert!(loaded.root == inserted.root);
+
+        let empty = Art::<u64, u64, 8>::from_sorted_iter(None).unwrap();
+        assert!(empty.root.is_none());
+        let single = Art::<String, u64, 8>::from_sorted_iter(Some((String::new(), 1))).unwrap();
+        assert_eq!(single.get(""), Some(&1));
+    }
+
+    #[test]
+    fn from_sorted_iter_errors() {
+        use super::BulkLoadError;
+
+        let pairs = [("a", 0), ("ab", 1), ("ab", 2)].map(|(k, v)| (k.to_string(), v));
+        let result = Art::<String, usize, 8>::from_sorted_iter(pairs);
+        assert_eq!(result.err(), Some(BulkLoadError::Duplicate { index: 2 }));
+
+        let pairs = [("a", 0), ("ab", 1), ("a", 2)].map(|(k, v)| (k.to_string(), v));
+        let result = Art::<String, usize, 8>::from_sorted_iter(pairs);
+        assert_eq!(result.err(), Some(BulkLoadError::OutOfOrder { index: 2 }));
+
+        let result = Art::<i32, i32, 8>::from_sorted_iter([(-1, 0), (-2, 0)]);
+        assert_eq!(result.err(), Some(BulkLoadError::OutOfOrder { index: 1 }));
+    }
+
+    #[test]
+    fn stats_counts_nodes() {
+        use super::ArtStats;
+
+        assert_eq!(Art::<u16, u16, 8>::new().stats(), ArtStats::default());
+
+        let mut art = Art::<u16, u16, 8>::new();
+        for key in 0..256 {
+            art.insert(key, key);
+        }
+        let stats = art.stats();
+        assert_eq!(stats.node256.count, 1);
+        assert_eq!(stats.leaves.count, 256);
+        assert_eq!((stats.max_depth, stats.avg_depth), (1, 1.0));
+        assert_eq!(
+            stats.total_bytes(),
+            stats.node256.bytes + stats.leaves.bytes
+        );
+
+        let mut art = Art::<String, usize, 2>::new();
+        for (i, key) in ["abcdef1", "abcdef2", "abcdef", "b"].iter().enumerate() {
+            art.insert(key.to_string(), i);
+        }
+        let stats = art.stats();
+        assert_eq!((stats.node4.count, stats.node16.count), (2, 0));
+        assert_eq!(stats.leaves.count, 4);
+        assert_eq!(stats.optimistic_paths, 1);
+        assert_eq!(stats.prefixed_children, 1);
+        assert_eq!((stats.max_depth, stats.avg_depth), (2, 1.75));
+
+        // the smallest leaf, of a bool and a unit, still takes a block of 8 bytes.
+        let mut art = Art::<bool, (), 8>::new();
+        art.insert(false, ());
+        art.insert(true, ());
+        let stats = art.stats();
+        assert_eq!(stats.leaves.count, 2);
+        assert_eq!(stats.leaves.bytes, 16);
+    }
+
+    #[test]
+    fn validate_reports_violations() {
+        use super::{ValidationError, Violation};
+
+        let mut art = Art::<String, usize, 2>::new();
+        for (i, key) in ["abcdef1", "abcdef2", "abcdef", "b"].iter().enumerate() {
+            art.insert(key.to_string(), i);
+        }
+        assert_eq!(art.validate(), Ok(()));
+
+        art.size += 1;
+        let error = art.validate().unwrap_err();
+        assert_eq!(error.violation, Violation::Size { size: 5, leaves: 4 });
+        assert_eq!(error.to_string(), "size is 5 but 4 leaves found at path []");
+        art.size -= 1;
+
+        // the optimistic path "bcdef" below `a` only stores "bc".
+        let node = art.root.get_mut_child((b'a', true)).unwrap();
+        node.header_mut().partial.data[1] = b'x';
+        let expected = ValidationError {
+            path: b"a".to_vec(),
+            violation: Violation::PartialMismatch,
+        };
+        assert_eq!(art.validate(), Err(expected));
+        let node = art.root.get_mut_child((b'a', true)).unwrap();
+        node.header_mut().partial.data[1] = b'c';
+
+        art.root.header_mut().non_null_children = 3;
+        let expected = ValidationError {
+            path: vec![],
+            violation: Violation::ChildCount {
+                header: 3,
+                found: 2,
+            },
+        };
+        assert_eq!(art.validate(), Err(expected));
+        art.root.header_mut().non_null_children = 2;
+    }
+
+    #[test]
+    fn check_reports_corrupt_counts_and_indexes() {
+        use super::Violation;
+        use crate::node16::Node16;
+        use crate::node4::Node4;
+        use crate::node48::Node48;
+
+        let mut n4 = Node4::<u8, u8, 8>::default();
+        n4.header.non_null_children = 200;
+        let expected = Violation::ChildCount {
+            header: 200,
+            found: 0,
+        };
+        assert_eq!(n4.check(), Err(expected));
+        let mut n16 = Node16::<u8, u8, 8>::default();
+        n16.header.non_null_children = 200;
+        assert_eq!(n16.check(), Err(expected));
+
+        // 48 is the empty index, every index above it is out of bounds.
+        let mut n48 = Node48::<u8, u8, 8>::default();
+        n48.child_index[b'x' as usize] = 48;
+        assert_eq!(n48.check(), Ok(()));
+        for index in [49, 200, 255] {
+            n48.child_index[b'x' as usize] = index;
+            assert_eq!(n48.check(), Err(Violation::BadIndex { byte: b'x' }));
+        }
+    }
+
+    #[test]
+    fn validate_after_updates() {
+        let alphabet: Vec<char> = ('a'..='t').collect();
+        let words = words(&alphabet, 3);
+        let mut art = Art::<String, usize, 4>::new();
+        for (i, word) in words.iter().enumerate() {
+            art.insert(word.clone(), i);
+            if i % 97 == 0 {
+                assert_eq!(art.validate(), Ok(()));
+            }
+        }
+        assert_eq!(art.validate(), Ok(()));
+
+        for (i, word) in words.iter().enumerate().filter(|(i, _)| i % 3 != 0) {
+            assert_eq!(art.remove(word), Some(i));
+            if i % 97 == 0 {
+                assert_eq!(art.validate(), Ok(()));
+            }
+        }
+        assert_eq!(art.validate(), Ok(()));
+
+        let mut art = Art::<u32, u32, 8>::new();
+        for key in (0..70_000).step_by(7) {
+            art.insert(key, key);
+        }
+        for key in (0..70_000).step_by(14) {
+            art.remove(&key);
+        }
+        assert_eq!(art.validate(), Ok(()));
+    }
+
+    #[test]
+    fn node16_orders_bytes_across_0x80() {
+        let mut art = Art::<Vec<u8>, u8, 8>::new();
+        // the bytes 0x78 to 0x87 in a shuffled order.
+        for i in 0..16 {
+            let byte = 0x78 + i * 7 % 16;
+            art.insert(vec![b'k', byte], byte);
+        }
+        assert_eq!(art.stats().node16.count, 1);
+        assert_eq!(art.validate(), Ok(()));
+        assert!(art.values().copied().eq(0x78..0x88));
+    }
+
+    #[test]
+    fn prefixed_child_of_full_node() {
+        use super::ArtStats;
+
+        for count in [4, 16, 48, 256] {
+            let mut art = Art::<Vec<u8>, usize, 8>::new();
+            for byte in 0..count {
+                art.insert(vec![7, 7, byte as u8], byte);
+            }
+            let before: ArtStats = art.stats();
+            assert_eq!(art.insert(vec![7, 7], count), None);
+            assert_eq!(art.validate(), Ok(()));
+
+            let after = art.stats();
+            assert_eq!(after.prefixed_children, 1);
+            assert_eq!(
+                (after.node4, after.node16, after.node48, after.node256),
+                (before.node4, before.node16, before.node48, before.node256)
+            );
+            assert_eq!(art.get(&vec![7, 7]), Some(&count));
+            assert_eq!(art.remove(&vec![7, 7, 0]), Some(0));
+            assert_eq!(art.validate(), Ok(()));
+        }
+    }
+
+    #[test]
+    fn deep_tree_on_small_stack() {
+        // every key `a..ab` branches off the previous one a byte further down, so the
+        // tree is a chain of one Node4 per key.
+        const DEPTH: usize = 3000;
+        let worker = std::thread::Builder::new()
+            .stack_size(64 << 10)
+            .spawn(|| {
+                let mut art = Art::<Vec<u8>, usize, 8>::new();
+                let key = |i: usize| {
+                    let mut key = vec![b'a'; i];
+                    key.push(b'b');
+                    key
+                };
+                for i in 0..DEPTH {
+                    assert_eq!(art.insert(key(i), i), None);
+                }
+                assert_eq!(art.get(&key(DEPTH - 1)), Some(&(DEPTH - 1)));
+                let copy = art.clone();
+                assert_eq!(copy.get(&key(DEPTH - 1)), Some(&(DEPTH - 1)));
+                drop(copy);
+                for i in (0..DEPTH).step_by(2) {
+                    assert_eq!(art.remove(&key(i)), Some(i));
+                }
+                assert_eq!(art.size(), DEPTH / 2);
+                assert_eq!(art.get(&key(DEPTH - 1)), Some(&(DEPTH - 1)));
+            })
+            .unwrap();
+        worker.join().unwrap();
+    }
+
+    #[test]
+    fn clone_frees_copy_on_panic() {
+        // every value shares the counter, the value of key 500 panics when cloned.
+        struct Value(std::rc::Rc<()>, bool);
+        impl Clone for Value {
+            fn clone(&self) -> Value {
+                assert!(!self.1, "clone failed");
+                Value(self.0.clone(), false)
+            }
+        }
+
+        let counter = std::rc::Rc::new(());
+        let mut art = Art::<u32, Value, 8>::new();
+        for key in 0..1000 {
+            art.insert(key, Value(counter.clone(), key == 500));
+        }
+        let copy = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| art.clone()));
+        assert!(copy.is_err());
+        assert_eq!(std::rc::Rc::strong_count(&counter), 1001);
+    }
+
+    #[test]
+    fn remove_restores_fresh_shape() {
+        fn check<const MAX_PARTIAL_LEN: usize>(keys: &[Vec<u8>], removed: impl Fn(usize) -> bool) {
+            let mut art = Art::<Vec<u8>, usize, MAX_PARTIAL_LEN>::new();
+            for (i, key) in keys.iter().enumerate() {
+                art.insert(key.clone(), i);
+            }
+            let mut remaining = Vec::new();
+            for (i, key) in keys.iter().enumerate() {
+                if removed(i) {
+                    assert_eq!(art.remove(key), Some(i));
+                } else {
+                    remaining.push((key.clone(), i));
+                }
+            }
+            assert_eq!(art.validate(), Ok(()));
+
+            let inserted: Art<Vec<u8>, usize, MAX_PARTIAL_LEN> =
+                remaining.iter().cloned().collect();
+            assert!(art.root == inserted.root);
+            remaining.sort();
+            let built =
+                Art::<Vec<u8>, usize, MAX_PARTIAL_LEN>::from_sorted_iter(remaining).unwrap();
+            assert!(art.root == built.root);
+        }
+
+        // one node shrinking through every node type, from either end.
+        let bytes: Vec<Vec<u8>> = (0..=255u8).map(|byte| vec![b'k', byte]).collect();
+        for kept in [0, 1, 2, 4, 5, 16, 17, 47, 48, 49, 255] {
+            check::<8>(&bytes, |i| i >= kept);
+            check::<8>(&bytes, |i| i < 256 - kept);
+        }
+
+        // paths that collapse when the keys branching off them are removed.
+        let mut keys = Vec::new();
+        for i in 0..2000usize {
+            let stem = ["", "a", "a/long/shared/stem/", "a/long/shared/stem/x"][i % 4];
+            keys.push(format!("{}{}", stem, i * 7919 % 2000).into_bytes());
+            keys.push(format!("{}{}/", stem, i).into_bytes());
+        }
+        keys.sort();
+        keys.dedup();
+        for step in [2, 3, 7, 64] {
+            check::<0>(&keys, |i| i % step != 0);
+            check::<4>(&keys, |i| i % step != 0);
+            check::<8>(&keys, |i| i % step == 0);
+        }
+    }
 }
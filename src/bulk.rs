@@ -0,0 +1,200 @@
+use std::cmp::min;
+use std::fmt;
+
+use crate::alloc::NodeAllocator;
+use crate::node::ArtNode;
+use crate::ArtKey;
+use crate::Partial;
+
+/// The error returned by [Art::from_sorted_iter](crate::Art::from_sorted_iter) when the keys
+/// are not strictly increasing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BulkLoadError {
+    /// The key at `index` is less than the key before it.
+    OutOfOrder { index: usize },
+    /// The key at `index` is equal to the key before it.
+    Duplicate { index: usize },
+}
+
+impl fmt::Display for BulkLoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BulkLoadError::OutOfOrder { index } => {
+                write!(f, "key at index {} is less than the previous key", index)
+            }
+            BulkLoadError::Duplicate { index } => {
+                write!(f, "key at index {} is equal to the previous key", index)
+            }
+        }
+    }
+}
+
+impl std::error::Error for BulkLoadError {}
+
+/// An inner node on the rightmost path of the tree, still open for more children.
+struct Frame<K: ArtKey, V, const MAX_PARTIAL_LEN: usize> {
+    /// The depth of the key byte that selects a child.
+    depth: usize,
+    prefixed_child: ArtNode<K, V, MAX_PARTIAL_LEN>,
+    children: Vec<(u8, ArtNode<K, V, MAX_PARTIAL_LEN>)>,
+}
+
+/// Builds a tree bottom-up from strictly increasing keys.
+///
+/// Only the rightmost path is open: a new key shares a prefix of length `lcp` with the
+/// previous key, so every open node deeper than `lcp` is complete and is built at its final
+/// size. The new leaf then joins the open node at depth `lcp`, or a new node is opened
+/// there when the path was compressed over it.
+pub(crate) struct BulkBuilder<K: ArtKey, V, const MAX_PARTIAL_LEN: usize, A: NodeAllocator> {
+    alloc: A,
+    frames: Vec<Frame<K, V, MAX_PARTIAL_LEN>>,
+    /// The leaf of the previous key, not linked into the open nodes yet.
+    last: ArtNode<K, V, MAX_PARTIAL_LEN>,
+    last_key: Vec<u8>,
+    size: usize,
+}
+
+impl<K: ArtKey, V, const MAX_PARTIAL_LEN: usize, A: NodeAllocator>
+    BulkBuilder<K, V, MAX_PARTIAL_LEN, A>
+{
+    pub(crate) fn new_in(alloc: A) -> BulkBuilder<K, V, MAX_PARTIAL_LEN, A> {
+        BulkBuilder {
+            alloc,
+            frames: Vec::new(),
+            last: ArtNode::none(),
+            last_key: Vec::new(),
+            size: 0,
+        }
+    }
+
+    /// Checks that the key can follow the previous one and returns the length of their
+    /// common prefix.
+    pub(crate) fn check(&self, key: &[u8]) -> Result<usize, BulkLoadError> {
+        let index = self.size;
+        let lcp = self
+            .last_key
+            .iter()
+            .zip(key)
+            .take_while(|(a, b)| a == b)
+            .count();
+        if self.last.is_none() {
+            return Ok(lcp);
+        }
+
+        match self.last_key[lcp..].cmp(&key[lcp..]) {
+            std::cmp::Ordering::Less => Ok(lcp),
+            std::cmp::Ordering::Equal => Err(BulkLoadError::Duplicate { index }),
+            std::cmp::Ordering::Greater => Err(BulkLoadError::OutOfOrder { index }),
+        }
+    }
+
+    pub(crate) fn push(&mut self, key: K, val: V) -> Result<(), BulkLoadError> {
+        {
+            let bytes = key.get_bytes();
+            let bytes = bytes.as_ref();
+            let lcp = self.check(bytes)?;
+            if !self.last.is_none() {
+                self.close(lcp);
+            }
+            self.last_key.clear();
+            self.last_key.extend_from_slice(bytes);
+        }
+
+        self.last = ArtNode::leaf(&mut self.alloc, key, val);
+        self.size += 1;
+        Ok(())
+    }
+
+    /// Links the previous leaf and builds every open node deeper than `lcp`, leaving an
+    /// open node at depth `lcp` for the next leaf.
+    fn close(&mut self, lcp: usize) {
+        let mut node = std::mem::take(&mut self.last);
+        while self.frames.last().is_some_and(|frame| frame.depth > lcp) {
+            let mut frame = self.frames.pop().unwrap();
+            self.attach(&mut frame, node);
+            // the parent is the next open node, or the one opened at `lcp` below.
+            let parent_depth = match self.frames.last() {
+                Some(parent) if parent.depth >= lcp => parent.depth,
+                _ => lcp,
+            };
+            node = self.build(frame, Some(parent_depth));
+        }
+
+        if self.frames.last().map_or(true, |frame| frame.depth < lcp) {
+            self.frames.push(Frame {
+                depth: lcp,
+                prefixed_child: ArtNode::none(),
+                children: Vec::new(),
+            });
+        }
+        let mut frame = self.frames.pop().unwrap();
+        self.attach(&mut frame, node);
+        self.frames.push(frame);
+    }
+
+    /// Adds the rightmost subtree, which holds the previous key, to an open node.
+    fn attach(
+        &self,
+        frame: &mut Frame<K, V, MAX_PARTIAL_LEN>,
+        node: ArtNode<K, V, MAX_PARTIAL_LEN>,
+    ) {
+        match self.last_key.get(frame.depth) {
+            Some(byte) => frame.children.push((*byte, node)),
+            None => frame.prefixed_child = node,
+        }
+    }
+
+    /// Builds an open node whose compressed path starts right after the parent's key byte.
+    fn build(
+        &mut self,
+        frame: Frame<K, V, MAX_PARTIAL_LEN>,
+        parent_depth: Option<usize>,
+    ) -> ArtNode<K, V, MAX_PARTIAL_LEN> {
+        let start = parent_depth.map_or(0, |depth| depth + 1);
+        let mut partial = Partial::<MAX_PARTIAL_LEN> {
+            len: (frame.depth - start) as u32,
+            ..Default::default()
+        };
+        let stored = min(MAX_PARTIAL_LEN, frame.depth - start);
+        partial.data[..stored].copy_from_slice(&self.last_key[start..start + stored]);
+        ArtNode::from_sorted_children(
+            &mut self.alloc,
+            partial,
+            frame.prefixed_child,
+            frame.children,
+        )
+    }
+
+    /// Builds the remaining open nodes and returns the root with the number of keys and
+    /// the allocator holding the nodes.
+    pub(crate) fn finish(mut self) -> (ArtNode<K, V, MAX_PARTIAL_LEN>, usize, A) {
+        let mut node = std::mem::take(&mut self.last);
+        while let Some(mut frame) = self.frames.pop() {
+            self.attach(&mut frame, node);
+            let parent_depth = self.frames.last().map(|parent| parent.depth);
+            node = self.build(frame, parent_depth);
+        }
+        // every open node is built, the allocator moves out with the root instead of
+        // being dropped with the builder.
+        let mut builder = std::mem::ManuallyDrop::new(self);
+        drop(std::mem::take(&mut builder.frames));
+        drop(std::mem::take(&mut builder.last_key));
+        let alloc = unsafe { std::ptr::read(&builder.alloc) };
+        (node, builder.size, alloc)
+    }
+}
+
+/// Frees the nodes built so far when the input turns out not to be sorted.
+impl<K: ArtKey, V, const MAX_PARTIAL_LEN: usize, A: NodeAllocator> Drop
+    for BulkBuilder<K, V, MAX_PARTIAL_LEN, A>
+{
+    fn drop(&mut self) {
+        std::mem::take(&mut self.last).free(&mut self.alloc);
+        for frame in self.frames.drain(..) {
+            frame.prefixed_child.free(&mut self.alloc);
+            for (_, child) in frame.children {
+                child.free(&mut self.alloc);
+            }
+        }
+    }
+}
@@ -0,0 +1,847 @@
+//! A compact binary format for an [Art](crate::Art), which is queried in place.
+//!
+//! [Art::write_to](crate::Art::write_to) writes the tree node by node, and [ArtView] reads
+//! it back from a byte slice, typically a memory-mapped file, without deserializing it.
+//! Keys are the bytes of [ArtKey], and values are written as their bytes.
+//!
+//! All integers are little-endian. The file is laid out as:
+//!
+//! - the header: the magic `b"ARTFUL"` and the format version as a `u16`.
+//! - the body: the node records, every node written after its children. A node refers to
+//!   its children by their `u64` offsets from the start of the body, where `u64::MAX` is an
+//!   empty child.
+//! - the trailer: the number of keys, the offset of the root and the FNV-1a hash of the
+//!   body, each a `u64`.
+//!
+//! A leaf is the tag `5`, the key and value lengths as `u32`, then the key and value bytes.
+//! An inner node is its tag, `1` to `4` for Node4 to Node256, the length of its compressed
+//! path as `u32` and the whole path, the offset of the prefixed child, the number of
+//! children as `u16`, then the children:
+//!
+//! - Node4 and Node16: the key bytes of the children in order, then their offsets.
+//! - Node48: the 256 slots indexed by key byte, `0xFF` when empty, then the offsets.
+//! - Node256: 256 offsets indexed by key byte.
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::io::{self, Write};
+use std::ops::{Bound, RangeBounds};
+
+use crate::node::{
+    ArtNode, NODE_TYPE_LEAF, NODE_TYPE_N16, NODE_TYPE_N256, NODE_TYPE_N4, NODE_TYPE_N48,
+};
+use crate::ArtKey;
+
+const MAGIC: &[u8; 6] = b"ARTFUL";
+const VERSION: u16 = 1;
+const HEADER_LEN: usize = 8;
+const TRAILER_LEN: usize = 24;
+const NONE: u64 = u64::MAX;
+const EMPTY_INDEX: u8 = 0xFF;
+
+const FNV_OFFSET: u64 = 0xcbf29ce484222325;
+const FNV_PRIME: u64 = 0x100000001b3;
+
+fn fnv1a(hash: u64, bytes: &[u8]) -> u64 {
+    bytes.iter().fold(hash, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(FNV_PRIME)
+    })
+}
+
+/// The error returned by [ArtView::open] for bytes that are not a valid tree.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FormatError {
+    /// The bytes do not start with the magic of the format.
+    BadMagic,
+    /// The format version is not supported by this version of the crate.
+    UnsupportedVersion(u16),
+    /// The bytes are too short to hold the header and the trailer.
+    Truncated,
+    /// The body does not match its checksum.
+    ChecksumMismatch,
+    /// The node at `offset` of the body is malformed.
+    Corrupt { offset: u64 },
+}
+
+impl fmt::Display for FormatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FormatError::BadMagic => write!(f, "not an artful file"),
+            FormatError::UnsupportedVersion(version) => {
+                write!(f, "unsupported format version {}", version)
+            }
+            FormatError::Truncated => write!(f, "file is truncated"),
+            FormatError::ChecksumMismatch => write!(f, "checksum mismatch"),
+            FormatError::Corrupt { offset } => write!(f, "corrupt node at offset {}", offset),
+        }
+    }
+}
+
+impl std::error::Error for FormatError {}
+
+/// Writes the body while counting its length and hashing it.
+struct BodyWriter<W: Write> {
+    inner: W,
+    offset: u64,
+    hash: u64,
+}
+
+impl<W: Write> BodyWriter<W> {
+    fn write(&mut self, bytes: &[u8]) -> io::Result<()> {
+        self.offset += bytes.len() as u64;
+        self.hash = fnv1a(self.hash, bytes);
+        self.inner.write_all(bytes)
+    }
+
+    /// Writes the subtree, children first, and returns the offset of its root.
+    ///
+    /// The tree is walked in post-order with an explicit stack of the inner nodes whose
+    /// children are being written, so deep trees do not overflow the call stack.
+    fn write_tree<K: ArtKey, V: AsRef<[u8]>, const MAX_PARTIAL_LEN: usize>(
+        &mut self,
+        root: &ArtNode<K, V, MAX_PARTIAL_LEN>,
+    ) -> io::Result<u64> {
+        let mut stack: Vec<Frame<'_, K, V, MAX_PARTIAL_LEN>> = Vec::new();
+        let mut visit = Some((root, 0));
+        loop {
+            let mut written = None;
+            if let Some((node, depth)) = visit.take() {
+                match node.node_type() == NODE_TYPE_LEAF {
+                    true => written = Some(self.write_leaf(node)?),
+                    false => stack.push(Frame::new(node, depth)),
+                }
+            }
+
+            let frame = match stack.last_mut() {
+                Some(frame) => frame,
+                None => return Ok(written.expect("the root is a leaf")),
+            };
+            frame.offsets.extend(written);
+            match frame.children.get(frame.offsets.len()) {
+                Some((_, child)) => visit = Some((*child, frame.child_depth)),
+                None => {
+                    let frame = stack.pop().expect("a node is being written");
+                    let offset = self.write_inner(frame)?;
+                    match stack.last_mut() {
+                        Some(parent) => parent.offsets.push(offset),
+                        None => return Ok(offset),
+                    }
+                }
+            }
+        }
+    }
+
+    fn write_leaf<K: ArtKey, V: AsRef<[u8]>, const MAX_PARTIAL_LEN: usize>(
+        &mut self,
+        node: &ArtNode<K, V, MAX_PARTIAL_LEN>,
+    ) -> io::Result<u64> {
+        let leaf = node.static_cast_ref_leaf();
+        let key = leaf.key.get_bytes();
+        let (key, val) = (key.as_ref(), leaf.val.as_ref());
+        let offset = self.offset;
+        self.write(&[NODE_TYPE_LEAF as u8])?;
+        self.write(&(key.len() as u32).to_le_bytes())?;
+        self.write(&(val.len() as u32).to_le_bytes())?;
+        self.write(key)?;
+        self.write(val)?;
+        Ok(offset)
+    }
+
+    /// Writes an inner node whose children are all written.
+    fn write_inner<K: ArtKey, V, const MAX_PARTIAL_LEN: usize>(
+        &mut self,
+        frame: Frame<'_, K, V, MAX_PARTIAL_LEN>,
+    ) -> io::Result<u64> {
+        let mut prefixed_child = NONE;
+        let mut children = Vec::with_capacity(frame.children.len());
+        for ((byte, _), child) in frame.children.iter().zip(frame.offsets) {
+            match byte {
+                Some(byte) => children.push((*byte, child)),
+                None => prefixed_child = child,
+            }
+        }
+
+        let offset = self.offset;
+        let node_type = frame.node.node_type();
+        self.write(&[node_type as u8])?;
+        self.write(&(frame.prefix.len() as u32).to_le_bytes())?;
+        self.write(&frame.prefix)?;
+        self.write(&prefixed_child.to_le_bytes())?;
+        self.write(&(children.len() as u16).to_le_bytes())?;
+        match node_type {
+            NODE_TYPE_N4 | NODE_TYPE_N16 => {
+                let keys: Vec<u8> = children.iter().map(|c| c.0).collect();
+                self.write(&keys)?;
+                for (_, child) in &children {
+                    self.write(&child.to_le_bytes())?;
+                }
+            }
+            NODE_TYPE_N48 => {
+                let mut index = [EMPTY_INDEX; 256];
+                for (slot, (byte, _)) in children.iter().enumerate() {
+                    index[*byte as usize] = slot as u8;
+                }
+                self.write(&index)?;
+                for (_, child) in &children {
+                    self.write(&child.to_le_bytes())?;
+                }
+            }
+            _ => {
+                let mut slots = [NONE; 256];
+                for (byte, child) in &children {
+                    slots[*byte as usize] = *child;
+                }
+                for child in slots {
+                    self.write(&child.to_le_bytes())?;
+                }
+            }
+        }
+        Ok(offset)
+    }
+}
+
+/// An inner node of [BodyWriter::write_tree] whose children are being written.
+struct Frame<'a, K: ArtKey, V, const MAX_PARTIAL_LEN: usize> {
+    node: &'a ArtNode<K, V, MAX_PARTIAL_LEN>,
+    /// The whole compressed path of the node.
+    prefix: Vec<u8>,
+    /// The children in the order they are written, the prefixed child first without a key
+    /// byte.
+    children: Vec<(Option<u8>, &'a ArtNode<K, V, MAX_PARTIAL_LEN>)>,
+    /// The offsets of the children written so far.
+    offsets: Vec<u64>,
+    child_depth: usize,
+}
+
+impl<'a, K: ArtKey, V, const MAX_PARTIAL_LEN: usize> Frame<'a, K, V, MAX_PARTIAL_LEN> {
+    fn new(node: &'a ArtNode<K, V, MAX_PARTIAL_LEN>, depth: usize) -> Self {
+        // an optimistic path is only partly stored, the rest is read from any leaf below.
+        let partial = node.header().partial;
+        let prefix_len = partial.len as usize;
+        let prefix = if prefix_len <= MAX_PARTIAL_LEN {
+            partial.data[..prefix_len].to_vec()
+        } else {
+            let leaf = ArtNode::minimum_child(node).expect("an inner node has a leaf");
+            leaf.key.get_bytes().as_ref()[depth..depth + prefix_len].to_vec()
+        };
+
+        let prefixed_child = node.prefixed_child().map(|child| (None, child));
+        let children = prefixed_child
+            .into_iter()
+            .chain(
+                node.children()
+                    .into_iter()
+                    .map(|(byte, child)| (Some(byte), child)),
+            )
+            .collect();
+        Frame {
+            node,
+            prefix,
+            children,
+            offsets: Vec::new(),
+            child_depth: depth + prefix_len + 1,
+        }
+    }
+}
+
+pub(crate) fn write<K, V, W, const MAX_PARTIAL_LEN: usize>(
+    root: &ArtNode<K, V, MAX_PARTIAL_LEN>,
+    size: usize,
+    writer: W,
+) -> io::Result<()>
+where
+    K: ArtKey,
+    V: AsRef<[u8]>,
+    W: Write,
+{
+    let mut writer = BodyWriter {
+        inner: writer,
+        offset: 0,
+        hash: FNV_OFFSET,
+    };
+    writer.inner.write_all(MAGIC)?;
+    writer.inner.write_all(&VERSION.to_le_bytes())?;
+    let root = match root.is_none() {
+        true => NONE,
+        false => writer.write_tree(root)?,
+    };
+
+    let mut trailer = [0u8; TRAILER_LEN];
+    trailer[..8].copy_from_slice(&(size as u64).to_le_bytes());
+    trailer[8..16].copy_from_slice(&root.to_le_bytes());
+    trailer[16..].copy_from_slice(&writer.hash.to_le_bytes());
+    writer.inner.write_all(&trailer)?;
+    writer.inner.flush()
+}
+
+enum Record<'a> {
+    Leaf { key: &'a [u8], val: &'a [u8] },
+    Inner(InnerRecord<'a>),
+}
+
+struct InnerRecord<'a> {
+    node_type: usize,
+    prefix: &'a [u8],
+    prefixed_child: u64,
+    count: usize,
+    /// The key bytes or slots, followed by the offsets of the children.
+    table: &'a [u8],
+}
+
+fn read_u16(bytes: &[u8], pos: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(
+        bytes.get(pos..pos + 2)?.try_into().ok()?,
+    ))
+}
+
+fn read_u32(bytes: &[u8], pos: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(
+        bytes.get(pos..pos + 4)?.try_into().ok()?,
+    ))
+}
+
+fn read_u64(bytes: &[u8], pos: usize) -> Option<u64> {
+    Some(u64::from_le_bytes(
+        bytes.get(pos..pos + 8)?.try_into().ok()?,
+    ))
+}
+
+impl InnerRecord<'_> {
+    fn offset_at(&self, pos: usize) -> u64 {
+        read_u64(self.table, pos).expect("the table is checked on open")
+    }
+
+    fn child(&self, valid_key: (u8, bool)) -> Option<u64> {
+        let (byte, valid) = valid_key;
+        let child = match (valid, self.node_type) {
+            (false, _) => self.prefixed_child,
+            (true, NODE_TYPE_N4 | NODE_TYPE_N16) => {
+                let slot = self.table[..self.count].iter().position(|&k| k == byte)?;
+                self.offset_at(self.count + slot * 8)
+            }
+            (true, NODE_TYPE_N48) => match self.table[byte as usize] {
+                EMPTY_INDEX => NONE,
+                slot => self.offset_at(256 + slot as usize * 8),
+            },
+            (true, _) => self.offset_at(byte as usize * 8),
+        };
+        (child != NONE).then_some(child)
+    }
+
+    /// Returns the children in key order, without the prefixed child.
+    fn children(&self) -> Vec<(u8, u64)> {
+        match self.node_type {
+            NODE_TYPE_N4 | NODE_TYPE_N16 => (0..self.count)
+                .map(|slot| (self.table[slot], self.offset_at(self.count + slot * 8)))
+                .collect(),
+            _ => (0..=u8::MAX)
+                .filter_map(|byte| Some((byte, self.child((byte, true))?)))
+                .collect(),
+        }
+    }
+}
+
+/// A read-only tree over bytes written by [Art::write_to](crate::Art::write_to).
+///
+/// The bytes are checked once by [open](ArtView::open), after which lookups and iteration
+/// read the nodes in place.
+///
+/// # Examples
+/// ```rust
+/// use artful::disk::ArtView;
+/// use artful::Art;
+///
+/// let mut art = Art::<String, String, 8>::new();
+/// art.insert("apple".to_string(), "red".to_string());
+/// art.insert("banana".to_string(), "yellow".to_string());
+///
+/// let mut bytes = Vec::new();
+/// art.write_to(&mut bytes).unwrap();
+///
+/// let view = ArtView::open(&bytes).unwrap();
+/// assert_eq!(view.get("apple"), Some(&b"red"[..]));
+/// assert_eq!(view.get("cherry"), None);
+/// ```
+#[derive(Clone, Copy)]
+pub struct ArtView<'a> {
+    body: &'a [u8],
+    root: u64,
+    len: usize,
+}
+
+impl<'a> ArtView<'a> {
+    /// Checks the header, the checksum and every node of the bytes.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [FormatError] if the bytes are not a tree of a supported version, or are
+    /// corrupt.
+    pub fn open(bytes: &'a [u8]) -> Result<ArtView<'a>, FormatError> {
+        if bytes.len() < HEADER_LEN + TRAILER_LEN {
+            return Err(match bytes.starts_with(MAGIC) {
+                true => FormatError::Truncated,
+                false => FormatError::BadMagic,
+            });
+        }
+        if &bytes[..MAGIC.len()] != MAGIC {
+            return Err(FormatError::BadMagic);
+        }
+        let version = read_u16(bytes, MAGIC.len()).unwrap();
+        if version != VERSION {
+            return Err(FormatError::UnsupportedVersion(version));
+        }
+
+        let (body, trailer) = bytes[HEADER_LEN..].split_at(bytes.len() - HEADER_LEN - TRAILER_LEN);
+        let len = read_u64(trailer, 0).unwrap();
+        let root = read_u64(trailer, 8).unwrap();
+        if fnv1a(FNV_OFFSET, body) != read_u64(trailer, 16).unwrap() {
+            return Err(FormatError::ChecksumMismatch);
+        }
+
+        let view = ArtView {
+            body,
+            root,
+            len: len as usize,
+        };
+        view.check()?;
+        Ok(view)
+    }
+
+    /// Checks that every node can be read and that the nodes form a tree holding `len`
+    /// leaves, so that queries never read out of bounds.
+    fn check(&self) -> Result<(), FormatError> {
+        let mut leaves = 0;
+        let mut visited = 0;
+        let mut stack = Vec::new();
+        stack.extend((self.root != NONE).then_some(self.root));
+        while let Some(offset) = stack.pop() {
+            let corrupt = FormatError::Corrupt { offset };
+            // a tree has fewer nodes than bytes, more visits mean shared nodes.
+            visited += 1;
+            if visited > self.body.len() {
+                return Err(corrupt);
+            }
+            match self.parse(offset).ok_or(corrupt)? {
+                Record::Leaf { .. } => leaves += 1,
+                Record::Inner(inner) => {
+                    let mut children = inner.children();
+                    let sorted = children.windows(2).all(|pair| pair[0].0 < pair[1].0);
+                    if !sorted || children.len() != inner.count {
+                        return Err(corrupt);
+                    }
+                    if inner.prefixed_child != NONE {
+                        // a key ends at the node, so the prefixed child must be a leaf.
+                        match self.parse(inner.prefixed_child) {
+                            Some(Record::Leaf { .. }) if inner.prefixed_child < offset => {}
+                            _ => return Err(corrupt),
+                        }
+                        children.push((0, inner.prefixed_child));
+                    }
+                    // children are written first, which also rules out cycles.
+                    if children.iter().any(|(_, child)| *child >= offset) {
+                        return Err(corrupt);
+                    }
+                    stack.extend(children.iter().map(|(_, child)| *child));
+                }
+            }
+        }
+
+        match leaves == self.len {
+            true => Ok(()),
+            false => Err(FormatError::Corrupt { offset: self.root }),
+        }
+    }
+
+    fn parse(&self, offset: u64) -> Option<Record<'a>> {
+        let body = self.body;
+        let pos = usize::try_from(offset).ok()?;
+        let node_type = *body.get(pos)? as usize;
+        if node_type == NODE_TYPE_LEAF {
+            let key_len = read_u32(body, pos + 1)? as usize;
+            let val_len = read_u32(body, pos + 5)? as usize;
+            let key_start = pos + 9;
+            let key = body.get(key_start..key_start + key_len)?;
+            let val = body.get(key_start + key_len..key_start + key_len + val_len)?;
+            return Some(Record::Leaf { key, val });
+        }
+
+        let prefix_len = read_u32(body, pos + 1)? as usize;
+        let prefix = body.get(pos + 5..pos + 5 + prefix_len)?;
+        let pos = pos + 5 + prefix_len;
+        let prefixed_child = read_u64(body, pos)?;
+        let count = read_u16(body, pos + 8)? as usize;
+        let (max_count, table_len) = match node_type {
+            NODE_TYPE_N4 => (4, count * 9),
+            NODE_TYPE_N16 => (16, count * 9),
+            NODE_TYPE_N48 => (48, 256 + count * 8),
+            NODE_TYPE_N256 => (256, 256 * 8),
+            _ => return None,
+        };
+        if count > max_count {
+            return None;
+        }
+        let table = body.get(pos + 10..pos + 10 + table_len)?;
+        if node_type == NODE_TYPE_N48
+            && table[..256]
+                .iter()
+                .any(|&s| s != EMPTY_INDEX && s as usize >= count)
+        {
+            return None;
+        }
+        Some(Record::Inner(InnerRecord {
+            node_type,
+            prefix,
+            prefixed_child,
+            count,
+            table,
+        }))
+    }
+
+    fn record(&self, offset: u64) -> Record<'a> {
+        self.parse(offset).expect("the nodes are checked on open")
+    }
+
+    /// Returns the number of keys.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the value bytes corresponding to the key.
+    pub fn get<Q: ArtKey + ?Sized>(&self, key: &Q) -> Option<&'a [u8]> {
+        let key = key.get_bytes();
+        let key = key.as_ref();
+        let mut offset = (self.root != NONE).then_some(self.root)?;
+        let mut depth = 0;
+        loop {
+            match self.record(offset) {
+                Record::Leaf { key: leaf_key, val } => return (leaf_key == key).then_some(val),
+                Record::Inner(inner) => {
+                    if !key[depth..].starts_with(inner.prefix) {
+                        return None;
+                    }
+                    depth += inner.prefix.len();
+                    let valid_key = match key.get(depth) {
+                        Some(byte) => (*byte, true),
+                        None => (0, false),
+                    };
+                    offset = inner.child(valid_key)?;
+                    depth += 1;
+                }
+            }
+        }
+    }
+
+    /// Gets an iterator over the key and value bytes, sorted by key.
+    pub fn iter(&self) -> Iter<'a> {
+        Iter::new(*self, Bound::Unbounded, Bound::Unbounded)
+    }
+
+    /// Gets an iterator over the key and value bytes of the keys in the range, sorted by
+    /// key.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use std::ops::Bound::{Excluded, Included};
+    ///
+    /// use artful::disk::ArtView;
+    /// use artful::Art;
+    ///
+    /// let art: Art<u32, Vec<u8>, 8> = (0..100u32).map(|i| (i, vec![i as u8])).collect();
+    /// let mut bytes = Vec::new();
+    /// art.write_to(&mut bytes).unwrap();
+    ///
+    /// let view = ArtView::open(&bytes).unwrap();
+    /// let values: Vec<&[u8]> = view.range::<u32, _>(10..13).map(|(_, v)| v).collect();
+    /// assert_eq!(values, [[10], [11], [12]]);
+    /// assert_eq!(view.range::<u32, _>((Excluded(98), Included(200))).count(), 1);
+    /// ```
+    pub fn range<Q, R>(&self, range: R) -> Iter<'a>
+    where
+        Q: ArtKey + ?Sized,
+        R: RangeBounds<Q>,
+    {
+        let bytes = |bound: Bound<&Q>| match bound {
+            Bound::Included(key) => Bound::Included(key.get_bytes().as_ref().to_vec()),
+            Bound::Excluded(key) => Bound::Excluded(key.get_bytes().as_ref().to_vec()),
+            Bound::Unbounded => Bound::Unbounded,
+        };
+        Iter::new(*self, bytes(range.start_bound()), bytes(range.end_bound()))
+    }
+
+    /// Gets an iterator over the key and value bytes of the keys starting with the prefix,
+    /// sorted by key.
+    pub fn prefix_iter(&self, prefix: &[u8]) -> Iter<'a> {
+        let prefix = prefix.to_vec();
+        // the first byte string after every key with the prefix.
+        let mut end = prefix.clone();
+        while end.last() == Some(&u8::MAX) {
+            end.pop();
+        }
+        let upper = match end.last_mut() {
+            Some(byte) => {
+                *byte += 1;
+                Bound::Excluded(end)
+            }
+            None => Bound::Unbounded,
+        };
+        Iter::new(*self, Bound::Included(prefix), upper)
+    }
+}
+
+/// An iterator over the key and value bytes of an [ArtView], sorted by key.
+pub struct Iter<'a> {
+    view: ArtView<'a>,
+    /// The nodes still to visit, the next one last, each with the length of its parent's
+    /// path and its key byte.
+    stack: Vec<(u64, usize, Option<u8>)>,
+    /// The bytes leading to the last visited inner node.
+    path: Vec<u8>,
+    lower: Bound<Vec<u8>>,
+    upper: Bound<Vec<u8>>,
+}
+
+impl<'a> Iter<'a> {
+    fn new(view: ArtView<'a>, lower: Bound<Vec<u8>>, upper: Bound<Vec<u8>>) -> Iter<'a> {
+        let mut stack = Vec::new();
+        stack.extend((view.root != NONE).then_some((view.root, 0, None)));
+        Iter {
+            view,
+            stack,
+            path: Vec::new(),
+            lower,
+            upper,
+        }
+    }
+}
+
+/// Compares the bytes a subtree's keys start with against a bound key, `Equal` when
+/// either is a prefix of the other.
+fn compare_path(path: &[u8], key: &[u8]) -> Ordering {
+    let len = path.len().min(key.len());
+    path[..len].cmp(&key[..len])
+}
+
+impl<'a> Iterator for Iter<'a> {
+    type Item = (&'a [u8], &'a [u8]);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some((offset, parent_len, byte)) = self.stack.pop() {
+            match self.view.record(offset) {
+                Record::Leaf { key, val } => {
+                    let after_lower = match &self.lower {
+                        Bound::Included(lower) => key >= &lower[..],
+                        Bound::Excluded(lower) => key > &lower[..],
+                        Bound::Unbounded => true,
+                    };
+                    let before_upper = match &self.upper {
+                        Bound::Included(upper) => key <= &upper[..],
+                        Bound::Excluded(upper) => key < &upper[..],
+                        Bound::Unbounded => true,
+                    };
+                    if !before_upper {
+                        self.stack.clear();
+                        return None;
+                    }
+                    if after_lower {
+                        return Some((key, val));
+                    }
+                }
+                Record::Inner(inner) => {
+                    self.path.truncate(parent_len);
+                    self.path.extend(byte);
+                    self.path.extend_from_slice(inner.prefix);
+                    if let Bound::Included(lower) | Bound::Excluded(lower) = &self.lower {
+                        if compare_path(&self.path, lower) == Ordering::Less {
+                            continue;
+                        }
+                    }
+                    if let Bound::Included(upper) | Bound::Excluded(upper) = &self.upper {
+                        if compare_path(&self.path, upper) == Ordering::Greater {
+                            self.stack.clear();
+                            return None;
+                        }
+                    }
+
+                    let len = self.path.len();
+                    let children = inner.children().into_iter().rev();
+                    self.stack
+                        .extend(children.map(|(byte, child)| (child, len, Some(byte))));
+                    if inner.prefixed_child != NONE {
+                        self.stack.push((inner.prefixed_child, len, None));
+                    }
+                }
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{fnv1a, ArtView, FormatError, FNV_OFFSET, HEADER_LEN, TRAILER_LEN};
+    use crate::Art;
+    use std::ops::Bound;
+
+    fn words() -> Vec<String> {
+        let mut words = Vec::new();
+        for a in ["", "a", "ab", "abcdefghijkl"] {
+            for b in ["", "x", "xyz"] {
+                for c in 0..20 {
+                    words.push(format!("{}{}{}", a, b, c));
+                }
+            }
+        }
+        words
+    }
+
+    #[test]
+    fn queries_match_art() {
+        let mut art = Art::<String, Vec<u8>, 2>::new();
+        for (i, word) in words().into_iter().enumerate() {
+            art.insert(word, i.to_le_bytes().to_vec());
+        }
+        for i in 0..300u32 {
+            art.insert(format!("n{}", i), vec![i as u8; i as usize % 5]);
+        }
+        let mut bytes = Vec::new();
+        art.write_to(&mut bytes).unwrap();
+        let view = ArtView::open(&bytes).unwrap();
+
+        assert_eq!(view.len(), art.size());
+        let expected: Vec<(&[u8], &[u8])> =
+            art.iter().map(|(k, v)| (k.as_bytes(), &v[..])).collect();
+        assert!(view.iter().eq(expected.iter().copied()));
+        for (key, val) in art.iter() {
+            assert_eq!(view.get(key), Some(&val[..]));
+        }
+        assert_eq!(view.get("abcdefghijk"), None);
+        assert_eq!(view.get("n300"), None);
+
+        for prefix in ["", "a", "abc", "abcdefghijklx", "n1", "z"] {
+            let from_art: Vec<&[u8]> = art
+                .prefix_iter(prefix.as_bytes())
+                .map(|(k, _)| k.as_bytes())
+                .collect();
+            let from_view: Vec<&[u8]> = view
+                .prefix_iter(prefix.as_bytes())
+                .map(|(k, _)| k)
+                .collect();
+            assert_eq!(from_view, from_art, "prefix {:?}", prefix);
+        }
+        let bounds = [
+            (Bound::Included("ab"), Bound::Excluded("abcdefghijkl5")),
+            (Bound::Excluded("a"), Bound::Included("n2")),
+            (Bound::Unbounded, Bound::Excluded("")),
+            (Bound::Included("n99"), Bound::Unbounded),
+        ];
+        for range in bounds {
+            let from_art: Vec<&[u8]> = art
+                .range::<str, _>(range)
+                .map(|(k, _)| k.as_bytes())
+                .collect();
+            let from_view: Vec<&[u8]> = view.range::<str, _>(range).map(|(k, _)| k).collect();
+            assert_eq!(from_view, from_art, "range {:?}", range);
+        }
+    }
+
+    #[test]
+    fn empty_tree() {
+        let mut bytes = Vec::new();
+        Art::<u32, Vec<u8>, 8>::new().write_to(&mut bytes).unwrap();
+        let view = ArtView::open(&bytes).unwrap();
+        assert!(view.is_empty());
+        assert_eq!(view.get(&1u32), None);
+        assert_eq!(view.iter().count(), 0);
+    }
+
+    #[test]
+    fn prefix_of_max_bytes() {
+        let keys: [&[u8]; 5] = [&[1], &[0xFF], &[0xFF, 0], &[0xFF, 0xFF, 1], &[0xFF, 0xFF]];
+        let art: Art<Vec<u8>, Vec<u8>, 8> = keys.iter().map(|k| (k.to_vec(), vec![])).collect();
+        let mut bytes = Vec::new();
+        art.write_to(&mut bytes).unwrap();
+        let view = ArtView::open(&bytes).unwrap();
+
+        assert_eq!(view.prefix_iter(&[0xFF][..]).count(), 4);
+        assert_eq!(view.prefix_iter(&[0xFF, 0xFF][..]).count(), 2);
+        assert_eq!(view.prefix_iter(&[0xFE][..]).count(), 0);
+    }
+
+    #[test]
+    fn deep_tree_on_small_stack() {
+        // as in the Art test of the same name, the tree is a chain of one Node4 per key.
+        const DEPTH: usize = 3000;
+        let worker = std::thread::Builder::new()
+            .stack_size(64 << 10)
+            .spawn(|| {
+                let key = |i: usize| {
+                    let mut key = vec![b'a'; i];
+                    key.push(b'b');
+                    key
+                };
+                let mut art = Art::<Vec<u8>, Vec<u8>, 8>::new();
+                for i in 0..DEPTH {
+                    art.insert(key(i), i.to_le_bytes().to_vec());
+                }
+                let mut bytes = Vec::new();
+                art.write_to(&mut bytes).unwrap();
+                let view = ArtView::open(&bytes).unwrap();
+
+                assert_eq!(view.len(), DEPTH);
+                assert_eq!(
+                    view.get(&key(DEPTH - 1)),
+                    Some(&(DEPTH - 1).to_le_bytes()[..])
+                );
+                assert!(view.iter().map(|(k, _)| k).eq(art.keys().map(|k| &k[..])));
+            })
+            .unwrap();
+        worker.join().unwrap();
+    }
+
+    #[test]
+    fn rejects_bad_bytes() {
+        let art: Art<u16, Vec<u8>, 8> = (0..500u16).map(|i| (i, vec![1, 2])).collect();
+        let mut bytes = Vec::new();
+        art.write_to(&mut bytes).unwrap();
+        let body = HEADER_LEN..bytes.len() - TRAILER_LEN;
+
+        assert_eq!(ArtView::open(b"ARTFU").err(), Some(FormatError::BadMagic));
+        assert_eq!(
+            ArtView::open(&bytes[..20]).err(),
+            Some(FormatError::Truncated)
+        );
+        let mut wrong = bytes.clone();
+        wrong[0] = b'X';
+        assert_eq!(ArtView::open(&wrong).err(), Some(FormatError::BadMagic));
+        let mut wrong = bytes.clone();
+        wrong[6] = 2;
+        assert_eq!(
+            ArtView::open(&wrong).err(),
+            Some(FormatError::UnsupportedVersion(2))
+        );
+        let mut wrong = bytes.clone();
+        wrong[body.start + 3] ^= 1;
+        assert_eq!(
+            ArtView::open(&wrong).err(),
+            Some(FormatError::ChecksumMismatch)
+        );
+
+        // a corrupt node with a matching checksum: the root is its own prefixed child.
+        let mut wrong = bytes.clone();
+        let root = u64::from_le_bytes(wrong[body.end + 8..body.end + 16].try_into().unwrap());
+        let pos = body.start + root as usize;
+        let prefix_len = u32::from_le_bytes(wrong[pos + 1..pos + 5].try_into().unwrap());
+        let prefixed_pos = pos + 5 + prefix_len as usize;
+        wrong[prefixed_pos..prefixed_pos + 8].copy_from_slice(&root.to_le_bytes());
+        let hash = fnv1a(FNV_OFFSET, &wrong[body.clone()]);
+        wrong[body.end + 16..].copy_from_slice(&hash.to_le_bytes());
+        assert_eq!(
+            ArtView::open(&wrong).err(),
+            Some(FormatError::Corrupt { offset: root })
+        );
+    }
+}
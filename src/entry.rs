@@ -0,0 +1,200 @@
+use crate::alloc::{Global, NodeAllocator};
+use crate::leaf::Leaf;
+use crate::node::VacantSite;
+use crate::ArtKey;
+
+/// A view into a single entry in a map, which may either be vacant or occupied.
+///
+/// This `enum` is constructed from the [entry](crate::Art::entry) method on [Art](crate::Art).
+pub enum Entry<'a, K: ArtKey, V, const MAX_PARTIAL_LEN: usize, A: NodeAllocator = Global> {
+    /// A vacant entry.
+    Vacant(VacantEntry<'a, K, V, MAX_PARTIAL_LEN, A>),
+    /// An occupied entry.
+    Occupied(OccupiedEntry<'a, K, V>),
+}
+
+/// A view into a vacant entry in an [Art](crate::Art). It is part of the [Entry] enum.
+///
+/// The entry remembers where the key belongs in the tree, so inserting into it does not
+/// descend from the root again.
+pub struct VacantEntry<'a, K: ArtKey, V, const MAX_PARTIAL_LEN: usize, A: NodeAllocator = Global> {
+    pub(crate) key: K,
+    pub(crate) site: VacantSite<'a, K, V, MAX_PARTIAL_LEN>,
+    pub(crate) size: &'a mut usize,
+    pub(crate) alloc: &'a mut A,
+}
+
+/// A view into an occupied entry in an [Art](crate::Art). It is part of the [Entry] enum.
+pub struct OccupiedEntry<'a, K: ArtKey, V> {
+    pub(crate) leaf: &'a mut Leaf<K, V>,
+}
+
+impl<'a, K: ArtKey, V, const MAX_PARTIAL_LEN: usize, A: NodeAllocator>
+    Entry<'a, K, V, MAX_PARTIAL_LEN, A>
+{
+    /// Ensures a value is in the entry by inserting the default if empty, and returns
+    /// a mutable reference to the value in the entry.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use artful::Art;
+    ///
+    /// let mut art = Art::<String, usize, 8>::new();
+    /// for word in ["a", "b", "a"] {
+    ///     *art.entry(word.to_string()).or_insert(0) += 1;
+    /// }
+    /// assert_eq!(art.get("a"), Some(&2));
+    /// assert_eq!(art.get("b"), Some(&1));
+    /// ```
+    pub fn or_insert(self, default: V) -> &'a mut V {
+        match self {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(default),
+        }
+    }
+
+    /// Ensures a value is in the entry by inserting the result of the default function if
+    /// empty, and returns a mutable reference to the value in the entry.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use artful::Art;
+    ///
+    /// let mut art = Art::<i32, String, 8>::new();
+    /// art.entry(1).or_insert_with(|| "a".to_string()).push('b');
+    /// assert_eq!(art.get(&1), Some(&"ab".to_string()));
+    /// ```
+    pub fn or_insert_with<F: FnOnce() -> V>(self, default: F) -> &'a mut V {
+        match self {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(default()),
+        }
+    }
+
+    /// Ensures a value is in the entry by inserting, if empty, the result of the default
+    /// function called with a reference to the key, and returns a mutable reference to
+    /// the value in the entry.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use artful::Art;
+    ///
+    /// let mut art = Art::<String, usize, 8>::new();
+    /// art.entry("abc".to_string()).or_insert_with_key(|key| key.len());
+    /// assert_eq!(art.get("abc"), Some(&3));
+    /// ```
+    pub fn or_insert_with_key<F: FnOnce(&K) -> V>(self, default: F) -> &'a mut V {
+        match self {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => {
+                let val = default(&entry.key);
+                entry.insert(val)
+            }
+        }
+    }
+
+    /// Returns a reference to this entry's key.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use artful::Art;
+    ///
+    /// let mut art = Art::<i32, &str, 8>::new();
+    /// assert_eq!(art.entry(1).key(), &1);
+    /// ```
+    pub fn key(&self) -> &K {
+        match self {
+            Entry::Occupied(entry) => entry.key(),
+            Entry::Vacant(entry) => entry.key(),
+        }
+    }
+
+    /// Provides in-place mutable access to an occupied entry before any potential inserts
+    /// into the map.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use artful::Art;
+    ///
+    /// let mut art = Art::<i32, usize, 8>::new();
+    /// art.entry(1).and_modify(|v| *v += 1).or_insert(10);
+    /// assert_eq!(art.get(&1), Some(&10));
+    /// art.entry(1).and_modify(|v| *v += 1).or_insert(10);
+    /// assert_eq!(art.get(&1), Some(&11));
+    /// ```
+    pub fn and_modify<F: FnOnce(&mut V)>(self, f: F) -> Self {
+        match self {
+            Entry::Occupied(mut entry) => {
+                f(entry.get_mut());
+                Entry::Occupied(entry)
+            }
+            Entry::Vacant(entry) => Entry::Vacant(entry),
+        }
+    }
+}
+
+impl<'a, K: ArtKey, V: Default, const MAX_PARTIAL_LEN: usize, A: NodeAllocator>
+    Entry<'a, K, V, MAX_PARTIAL_LEN, A>
+{
+    /// Ensures a value is in the entry by inserting the default value if empty, and returns
+    /// a mutable reference to the value in the entry.
+    pub fn or_default(self) -> &'a mut V {
+        match self {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(V::default()),
+        }
+    }
+}
+
+impl<'a, K: ArtKey, V, const MAX_PARTIAL_LEN: usize, A: NodeAllocator>
+    VacantEntry<'a, K, V, MAX_PARTIAL_LEN, A>
+{
+    /// Gets a reference to the key that would be used when inserting a value through the
+    /// VacantEntry.
+    pub fn key(&self) -> &K {
+        &self.key
+    }
+
+    /// Take ownership of the key.
+    pub fn into_key(self) -> K {
+        self.key
+    }
+
+    /// Sets the value of the entry with the VacantEntry's key, and returns a mutable
+    /// reference to it.
+    pub fn insert(self, value: V) -> &'a mut V {
+        *self.size += 1;
+        self.site.insert(self.alloc, self.key, value)
+    }
+}
+
+impl<'a, K: ArtKey, V> OccupiedEntry<'a, K, V> {
+    /// Gets a reference to the key stored in the map.
+    pub fn key(&self) -> &K {
+        &self.leaf.key
+    }
+
+    /// Gets a reference to the value in the entry.
+    pub fn get(&self) -> &V {
+        &self.leaf.val
+    }
+
+    /// Gets a mutable reference to the value in the entry.
+    ///
+    /// If you need a reference to the `OccupiedEntry` that may outlive the destruction of
+    /// the `Entry` value, see [into_mut](OccupiedEntry::into_mut).
+    pub fn get_mut(&mut self) -> &mut V {
+        &mut self.leaf.val
+    }
+
+    /// Converts the entry into a mutable reference to its value.
+    pub fn into_mut(self) -> &'a mut V {
+        &mut self.leaf.val
+    }
+
+    /// Sets the value of the entry with the `OccupiedEntry`'s key, and returns the entry's
+    /// old value.
+    pub fn insert(&mut self, value: V) -> V {
+        std::mem::replace(&mut self.leaf.val, value)
+    }
+}
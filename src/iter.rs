@@ -0,0 +1,832 @@
+use std::cmp::Ordering;
+use std::collections::VecDeque;
+use std::iter::FusedIterator;
+use std::marker::PhantomData;
+use std::ops::Bound;
+
+use crate::alloc::{Global, NodeAllocator};
+use crate::node::ArtNode;
+use crate::ArtKey;
+
+type Cursor<'a, K, V, const MAX_PARTIAL_LEN: usize> = (&'a ArtNode<K, V, MAX_PARTIAL_LEN>, usize);
+
+/// A double-ended walk over the leaves of a subtree in byte-lexicographic key order.
+///
+/// The front and back walks are independent stacks of `(node, slot)` cursors. `head`
+/// and `tail` hold the next leaf of each end, the walk is finished once they meet.
+pub(crate) struct RawIter<'a, K: ArtKey, V, const MAX_PARTIAL_LEN: usize> {
+    front: Vec<Cursor<'a, K, V, MAX_PARTIAL_LEN>>,
+    back: Vec<Cursor<'a, K, V, MAX_PARTIAL_LEN>>,
+    head: Option<&'a ArtNode<K, V, MAX_PARTIAL_LEN>>,
+    tail: Option<&'a ArtNode<K, V, MAX_PARTIAL_LEN>>,
+}
+
+impl<'a, K: ArtKey, V, const MAX_PARTIAL_LEN: usize> RawIter<'a, K, V, MAX_PARTIAL_LEN> {
+    pub(crate) fn new(
+        root: &'a ArtNode<K, V, MAX_PARTIAL_LEN>,
+    ) -> RawIter<'a, K, V, MAX_PARTIAL_LEN> {
+        let mut raw = RawIter {
+            front: vec![(root, 0)],
+            back: vec![(root, root.slots())],
+            head: None,
+            tail: None,
+        };
+        raw.head = raw.walk_front();
+        raw.tail = raw.walk_back();
+        raw
+    }
+
+    pub(crate) fn empty() -> RawIter<'a, K, V, MAX_PARTIAL_LEN> {
+        RawIter {
+            front: Vec::new(),
+            back: Vec::new(),
+            head: None,
+            tail: None,
+        }
+    }
+
+    /// Creates a walk over the leaves between `lower` and `upper`.
+    ///
+    /// Both ends descend along the bound key once and prune every subtree outside of the
+    /// bound, only the leaf on the path of the bound key needs a full key comparison.
+    pub(crate) fn range(
+        root: &'a ArtNode<K, V, MAX_PARTIAL_LEN>,
+        lower: Bound<&[u8]>,
+        upper: Bound<&[u8]>,
+    ) -> RawIter<'a, K, V, MAX_PARTIAL_LEN> {
+        let mut raw = RawIter::empty();
+        match lower {
+            Bound::Unbounded => raw.front.push((root, 0)),
+            Bound::Included(key) | Bound::Excluded(key) => raw.seek_front(root, key),
+        }
+        match upper {
+            Bound::Unbounded => raw.back.push((root, root.slots())),
+            Bound::Included(key) | Bound::Excluded(key) => raw.seek_back(root, key),
+        }
+
+        raw.head = raw.walk_front();
+        if raw
+            .head
+            .is_some_and(|head| !after_lower(leaf_key(head).as_ref(), lower))
+        {
+            raw.head = raw.walk_front();
+        }
+        raw.tail = raw.walk_back();
+        if raw
+            .tail
+            .is_some_and(|tail| !before_upper(leaf_key(tail).as_ref(), upper))
+        {
+            raw.tail = raw.walk_back();
+        }
+
+        match (raw.head, raw.tail) {
+            (Some(head), Some(tail)) if leaf_key(head).as_ref() <= leaf_key(tail).as_ref() => {}
+            _ => {
+                raw.head = None;
+                raw.tail = None;
+            }
+        }
+        raw
+    }
+
+    /// Positions the front stack so that the first leaf walked is the smallest one
+    /// greater than or equal to `key`, or the leaf on the path of `key` just below it.
+    fn seek_front(&mut self, root: &'a ArtNode<K, V, MAX_PARTIAL_LEN>, key: &[u8]) {
+        let mut node = root;
+        let mut depth = 0;
+        loop {
+            if node.is_none() {
+                return;
+            }
+            if node.is_leaf() {
+                self.front.push((node, 0));
+                return;
+            }
+
+            match node.compare_prefix(key, depth) {
+                // every key below the node is greater.
+                Ordering::Less => {
+                    self.front.push((node, 0));
+                    return;
+                }
+                // every key below the node is smaller.
+                Ordering::Greater => return,
+                Ordering::Equal => depth += node.header().partial.len as usize,
+            }
+
+            if depth == key.len() {
+                self.front.push((node, 0));
+                return;
+            }
+
+            let slot = node.slot_of(key[depth]);
+            match node.child_of(key[depth]) {
+                Some(child) if !child.is_leaf() => {
+                    self.front.push((node, slot + 1));
+                    node = child;
+                    depth += 1;
+                }
+                _ => {
+                    self.front.push((node, slot));
+                    return;
+                }
+            }
+        }
+    }
+
+    /// Positions the back stack so that the first leaf walked is the greatest one less
+    /// than or equal to `key`, or the leaf on the path of `key` just above it.
+    fn seek_back(&mut self, root: &'a ArtNode<K, V, MAX_PARTIAL_LEN>, key: &[u8]) {
+        let mut node = root;
+        let mut depth = 0;
+        loop {
+            if node.is_none() {
+                return;
+            }
+            if node.is_leaf() {
+                self.back.push((node, 1));
+                return;
+            }
+
+            match node.compare_prefix(key, depth) {
+                Ordering::Less => return,
+                Ordering::Greater => {
+                    self.back.push((node, node.slots()));
+                    return;
+                }
+                Ordering::Equal => depth += node.header().partial.len as usize,
+            }
+
+            // only the prefixed child is not greater than the key.
+            if depth == key.len() {
+                self.back.push((node, 1));
+                return;
+            }
+
+            let slot = node.slot_of(key[depth]);
+            match node.child_of(key[depth]) {
+                Some(child) if !child.is_leaf() => {
+                    self.back.push((node, slot));
+                    node = child;
+                    depth += 1;
+                }
+                Some(_) => {
+                    self.back.push((node, slot + 1));
+                    return;
+                }
+                None => {
+                    self.back.push((node, slot));
+                    return;
+                }
+            }
+        }
+    }
+
+    /// Advances the front stack to the next leaf.
+    fn walk_front(&mut self) -> Option<&'a ArtNode<K, V, MAX_PARTIAL_LEN>> {
+        while let Some((node, slot)) = self.front.last_mut() {
+            let node: &'a ArtNode<K, V, MAX_PARTIAL_LEN> = node;
+            if *slot == node.slots() {
+                self.front.pop();
+                continue;
+            }
+
+            let child = node.child_at(*slot);
+            *slot += 1;
+            match child {
+                None => continue,
+                Some(child) if child.is_leaf() => return Some(child),
+                Some(child) => self.front.push((child, 0)),
+            }
+        }
+
+        None
+    }
+
+    /// Advances the back stack to the previous leaf.
+    fn walk_back(&mut self) -> Option<&'a ArtNode<K, V, MAX_PARTIAL_LEN>> {
+        while let Some((node, slot)) = self.back.last_mut() {
+            let node: &'a ArtNode<K, V, MAX_PARTIAL_LEN> = node;
+            if *slot == 0 {
+                self.back.pop();
+                continue;
+            }
+
+            *slot -= 1;
+            match node.child_at(*slot) {
+                None => continue,
+                Some(child) if child.is_leaf() => return Some(child),
+                Some(child) => self.back.push((child, child.slots())),
+            }
+        }
+
+        None
+    }
+
+    pub(crate) fn next(&mut self) -> Option<&'a ArtNode<K, V, MAX_PARTIAL_LEN>> {
+        let head = self.head?;
+        if std::ptr::eq(head, self.tail?) {
+            self.head = None;
+            self.tail = None;
+        } else {
+            self.head = self.walk_front();
+        }
+
+        Some(head)
+    }
+
+    pub(crate) fn next_back(&mut self) -> Option<&'a ArtNode<K, V, MAX_PARTIAL_LEN>> {
+        let tail = self.tail?;
+        if std::ptr::eq(self.head?, tail) {
+            self.head = None;
+            self.tail = None;
+        } else {
+            self.tail = self.walk_back();
+        }
+
+        Some(tail)
+    }
+}
+
+#[inline]
+fn leaf_key<K: ArtKey, V, const MAX_PARTIAL_LEN: usize>(
+    node: &ArtNode<K, V, MAX_PARTIAL_LEN>,
+) -> K::Bytes<'_> {
+    node.static_cast_ref_leaf().key.get_bytes()
+}
+
+#[inline]
+fn after_lower(key: &[u8], lower: Bound<&[u8]>) -> bool {
+    match lower {
+        Bound::Included(bound) => key >= bound,
+        Bound::Excluded(bound) => key > bound,
+        Bound::Unbounded => true,
+    }
+}
+
+#[inline]
+fn before_upper(key: &[u8], upper: Bound<&[u8]>) -> bool {
+    match upper {
+        Bound::Included(bound) => key <= bound,
+        Bound::Excluded(bound) => key < bound,
+        Bound::Unbounded => true,
+    }
+}
+
+impl<K: ArtKey, V, const MAX_PARTIAL_LEN: usize> Clone for RawIter<'_, K, V, MAX_PARTIAL_LEN> {
+    fn clone(&self) -> Self {
+        RawIter {
+            front: self.front.clone(),
+            back: self.back.clone(),
+            head: self.head,
+            tail: self.tail,
+        }
+    }
+}
+
+/// An iterator over the entries of an [Art](crate::Art), sorted by key.
+///
+/// This `struct` is created by the [`iter`](crate::Art::iter) method on `Art`.
+pub struct Iter<'a, K: ArtKey, V, const MAX_PARTIAL_LEN: usize> {
+    raw: RawIter<'a, K, V, MAX_PARTIAL_LEN>,
+    length: usize,
+}
+
+impl<'a, K: ArtKey, V, const MAX_PARTIAL_LEN: usize> Iter<'a, K, V, MAX_PARTIAL_LEN> {
+    pub(crate) fn new(
+        root: &'a ArtNode<K, V, MAX_PARTIAL_LEN>,
+        length: usize,
+    ) -> Iter<'a, K, V, MAX_PARTIAL_LEN> {
+        Iter {
+            raw: RawIter::new(root),
+            length,
+        }
+    }
+}
+
+impl<'a, K: ArtKey, V, const MAX_PARTIAL_LEN: usize> Iterator for Iter<'a, K, V, MAX_PARTIAL_LEN> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<(&'a K, &'a V)> {
+        let leaf = self.raw.next()?.static_cast_ref_leaf();
+        self.length -= 1;
+        Some((&leaf.key, &leaf.val))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.length, Some(self.length))
+    }
+}
+
+impl<'a, K: ArtKey, V, const MAX_PARTIAL_LEN: usize> DoubleEndedIterator
+    for Iter<'a, K, V, MAX_PARTIAL_LEN>
+{
+    fn next_back(&mut self) -> Option<(&'a K, &'a V)> {
+        let leaf = self.raw.next_back()?.static_cast_ref_leaf();
+        self.length -= 1;
+        Some((&leaf.key, &leaf.val))
+    }
+}
+
+impl<K: ArtKey, V, const MAX_PARTIAL_LEN: usize> ExactSizeIterator
+    for Iter<'_, K, V, MAX_PARTIAL_LEN>
+{
+}
+
+impl<K: ArtKey, V, const MAX_PARTIAL_LEN: usize> FusedIterator for Iter<'_, K, V, MAX_PARTIAL_LEN> {}
+
+impl<K: ArtKey, V, const MAX_PARTIAL_LEN: usize> Clone for Iter<'_, K, V, MAX_PARTIAL_LEN> {
+    fn clone(&self) -> Self {
+        Iter {
+            raw: self.raw.clone(),
+            length: self.length,
+        }
+    }
+}
+
+/// A mutable iterator over the entries of an [Art](crate::Art), sorted by key.
+///
+/// This `struct` is created by the [`iter_mut`](crate::Art::iter_mut) method on `Art`.
+pub struct IterMut<'a, K: ArtKey, V, const MAX_PARTIAL_LEN: usize> {
+    raw: RawIter<'a, K, V, MAX_PARTIAL_LEN>,
+    length: usize,
+    _marker: PhantomData<&'a mut V>,
+}
+
+impl<'a, K: ArtKey, V, const MAX_PARTIAL_LEN: usize> IterMut<'a, K, V, MAX_PARTIAL_LEN> {
+    /// The root is taken mutably so that the yielded values are exclusive for `'a`.
+    pub(crate) fn new(
+        root: &'a mut ArtNode<K, V, MAX_PARTIAL_LEN>,
+        length: usize,
+    ) -> IterMut<'a, K, V, MAX_PARTIAL_LEN> {
+        IterMut {
+            raw: RawIter::new(root),
+            length,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<'a, K: ArtKey, V, const MAX_PARTIAL_LEN: usize> Iterator
+    for IterMut<'a, K, V, MAX_PARTIAL_LEN>
+{
+    type Item = (&'a K, &'a mut V);
+
+    fn next(&mut self) -> Option<(&'a K, &'a mut V)> {
+        // Safety: the tree is mutably borrowed for 'a and every leaf is yielded once.
+        let leaf = unsafe { &mut *self.raw.next()?.leaf_ptr() };
+        self.length -= 1;
+        Some((&leaf.key, &mut leaf.val))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.length, Some(self.length))
+    }
+}
+
+impl<'a, K: ArtKey, V, const MAX_PARTIAL_LEN: usize> DoubleEndedIterator
+    for IterMut<'a, K, V, MAX_PARTIAL_LEN>
+{
+    fn next_back(&mut self) -> Option<(&'a K, &'a mut V)> {
+        // Safety: the tree is mutably borrowed for 'a and every leaf is yielded once.
+        let leaf = unsafe { &mut *self.raw.next_back()?.leaf_ptr() };
+        self.length -= 1;
+        Some((&leaf.key, &mut leaf.val))
+    }
+}
+
+impl<K: ArtKey, V, const MAX_PARTIAL_LEN: usize> ExactSizeIterator
+    for IterMut<'_, K, V, MAX_PARTIAL_LEN>
+{
+}
+
+impl<K: ArtKey, V, const MAX_PARTIAL_LEN: usize> FusedIterator
+    for IterMut<'_, K, V, MAX_PARTIAL_LEN>
+{
+}
+
+/// An iterator over the keys of an [Art](crate::Art), sorted by key.
+///
+/// This `struct` is created by the [`keys`](crate::Art::keys) method on `Art`.
+pub struct Keys<'a, K: ArtKey, V, const MAX_PARTIAL_LEN: usize> {
+    pub(crate) inner: Iter<'a, K, V, MAX_PARTIAL_LEN>,
+}
+
+impl<'a, K: ArtKey, V, const MAX_PARTIAL_LEN: usize> Iterator for Keys<'a, K, V, MAX_PARTIAL_LEN> {
+    type Item = &'a K;
+
+    fn next(&mut self) -> Option<&'a K> {
+        self.inner.next().map(|(key, _)| key)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<'a, K: ArtKey, V, const MAX_PARTIAL_LEN: usize> DoubleEndedIterator
+    for Keys<'a, K, V, MAX_PARTIAL_LEN>
+{
+    fn next_back(&mut self) -> Option<&'a K> {
+        self.inner.next_back().map(|(key, _)| key)
+    }
+}
+
+impl<K: ArtKey, V, const MAX_PARTIAL_LEN: usize> ExactSizeIterator
+    for Keys<'_, K, V, MAX_PARTIAL_LEN>
+{
+}
+
+impl<K: ArtKey, V, const MAX_PARTIAL_LEN: usize> FusedIterator for Keys<'_, K, V, MAX_PARTIAL_LEN> {}
+
+impl<K: ArtKey, V, const MAX_PARTIAL_LEN: usize> Clone for Keys<'_, K, V, MAX_PARTIAL_LEN> {
+    fn clone(&self) -> Self {
+        Keys {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+/// An iterator over the values of an [Art](crate::Art), sorted by key.
+///
+/// This `struct` is created by the [`values`](crate::Art::values) method on `Art`.
+pub struct Values<'a, K: ArtKey, V, const MAX_PARTIAL_LEN: usize> {
+    pub(crate) inner: Iter<'a, K, V, MAX_PARTIAL_LEN>,
+}
+
+impl<'a, K: ArtKey, V, const MAX_PARTIAL_LEN: usize> Iterator
+    for Values<'a, K, V, MAX_PARTIAL_LEN>
+{
+    type Item = &'a V;
+
+    fn next(&mut self) -> Option<&'a V> {
+        self.inner.next().map(|(_, val)| val)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<'a, K: ArtKey, V, const MAX_PARTIAL_LEN: usize> DoubleEndedIterator
+    for Values<'a, K, V, MAX_PARTIAL_LEN>
+{
+    fn next_back(&mut self) -> Option<&'a V> {
+        self.inner.next_back().map(|(_, val)| val)
+    }
+}
+
+impl<K: ArtKey, V, const MAX_PARTIAL_LEN: usize> ExactSizeIterator
+    for Values<'_, K, V, MAX_PARTIAL_LEN>
+{
+}
+
+impl<K: ArtKey, V, const MAX_PARTIAL_LEN: usize> FusedIterator
+    for Values<'_, K, V, MAX_PARTIAL_LEN>
+{
+}
+
+impl<K: ArtKey, V, const MAX_PARTIAL_LEN: usize> Clone for Values<'_, K, V, MAX_PARTIAL_LEN> {
+    fn clone(&self) -> Self {
+        Values {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+/// A mutable iterator over the values of an [Art](crate::Art), sorted by key.
+///
+/// This `struct` is created by the [`values_mut`](crate::Art::values_mut) method on `Art`.
+pub struct ValuesMut<'a, K: ArtKey, V, const MAX_PARTIAL_LEN: usize> {
+    pub(crate) inner: IterMut<'a, K, V, MAX_PARTIAL_LEN>,
+}
+
+impl<'a, K: ArtKey, V, const MAX_PARTIAL_LEN: usize> Iterator
+    for ValuesMut<'a, K, V, MAX_PARTIAL_LEN>
+{
+    type Item = &'a mut V;
+
+    fn next(&mut self) -> Option<&'a mut V> {
+        self.inner.next().map(|(_, val)| val)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<'a, K: ArtKey, V, const MAX_PARTIAL_LEN: usize> DoubleEndedIterator
+    for ValuesMut<'a, K, V, MAX_PARTIAL_LEN>
+{
+    fn next_back(&mut self) -> Option<&'a mut V> {
+        self.inner.next_back().map(|(_, val)| val)
+    }
+}
+
+impl<K: ArtKey, V, const MAX_PARTIAL_LEN: usize> ExactSizeIterator
+    for ValuesMut<'_, K, V, MAX_PARTIAL_LEN>
+{
+}
+
+impl<K: ArtKey, V, const MAX_PARTIAL_LEN: usize> FusedIterator
+    for ValuesMut<'_, K, V, MAX_PARTIAL_LEN>
+{
+}
+
+/// An iterator over a sub-range of entries in an [Art](crate::Art), sorted by key.
+///
+/// This `struct` is created by the [`range`](crate::Art::range) method on `Art`.
+pub struct Range<'a, K: ArtKey, V, const MAX_PARTIAL_LEN: usize> {
+    raw: RawIter<'a, K, V, MAX_PARTIAL_LEN>,
+}
+
+impl<'a, K: ArtKey, V, const MAX_PARTIAL_LEN: usize> Range<'a, K, V, MAX_PARTIAL_LEN> {
+    pub(crate) fn new(
+        root: &'a ArtNode<K, V, MAX_PARTIAL_LEN>,
+        lower: Bound<&[u8]>,
+        upper: Bound<&[u8]>,
+    ) -> Range<'a, K, V, MAX_PARTIAL_LEN> {
+        Range {
+            raw: RawIter::range(root, lower, upper),
+        }
+    }
+}
+
+impl<'a, K: ArtKey, V, const MAX_PARTIAL_LEN: usize> Iterator for Range<'a, K, V, MAX_PARTIAL_LEN> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<(&'a K, &'a V)> {
+        let leaf = self.raw.next()?.static_cast_ref_leaf();
+        Some((&leaf.key, &leaf.val))
+    }
+}
+
+impl<'a, K: ArtKey, V, const MAX_PARTIAL_LEN: usize> DoubleEndedIterator
+    for Range<'a, K, V, MAX_PARTIAL_LEN>
+{
+    fn next_back(&mut self) -> Option<(&'a K, &'a V)> {
+        let leaf = self.raw.next_back()?.static_cast_ref_leaf();
+        Some((&leaf.key, &leaf.val))
+    }
+}
+
+impl<K: ArtKey, V, const MAX_PARTIAL_LEN: usize> FusedIterator
+    for Range<'_, K, V, MAX_PARTIAL_LEN>
+{
+}
+
+impl<K: ArtKey, V, const MAX_PARTIAL_LEN: usize> Clone for Range<'_, K, V, MAX_PARTIAL_LEN> {
+    fn clone(&self) -> Self {
+        Range {
+            raw: self.raw.clone(),
+        }
+    }
+}
+
+/// A mutable iterator over a sub-range of entries in an [Art](crate::Art), sorted by key.
+///
+/// This `struct` is created by the [`range_mut`](crate::Art::range_mut) method on `Art`.
+pub struct RangeMut<'a, K: ArtKey, V, const MAX_PARTIAL_LEN: usize> {
+    raw: RawIter<'a, K, V, MAX_PARTIAL_LEN>,
+    _marker: PhantomData<&'a mut V>,
+}
+
+impl<'a, K: ArtKey, V, const MAX_PARTIAL_LEN: usize> RangeMut<'a, K, V, MAX_PARTIAL_LEN> {
+    pub(crate) fn new(
+        root: &'a mut ArtNode<K, V, MAX_PARTIAL_LEN>,
+        lower: Bound<&[u8]>,
+        upper: Bound<&[u8]>,
+    ) -> RangeMut<'a, K, V, MAX_PARTIAL_LEN> {
+        RangeMut {
+            raw: RawIter::range(root, lower, upper),
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<'a, K: ArtKey, V, const MAX_PARTIAL_LEN: usize> Iterator
+    for RangeMut<'a, K, V, MAX_PARTIAL_LEN>
+{
+    type Item = (&'a K, &'a mut V);
+
+    fn next(&mut self) -> Option<(&'a K, &'a mut V)> {
+        // Safety: the tree is mutably borrowed for 'a and every leaf is yielded once.
+        let leaf = unsafe { &mut *self.raw.next()?.leaf_ptr() };
+        Some((&leaf.key, &mut leaf.val))
+    }
+}
+
+impl<'a, K: ArtKey, V, const MAX_PARTIAL_LEN: usize> DoubleEndedIterator
+    for RangeMut<'a, K, V, MAX_PARTIAL_LEN>
+{
+    fn next_back(&mut self) -> Option<(&'a K, &'a mut V)> {
+        // Safety: the tree is mutably borrowed for 'a and every leaf is yielded once.
+        let leaf = unsafe { &mut *self.raw.next_back()?.leaf_ptr() };
+        Some((&leaf.key, &mut leaf.val))
+    }
+}
+
+impl<K: ArtKey, V, const MAX_PARTIAL_LEN: usize> FusedIterator
+    for RangeMut<'_, K, V, MAX_PARTIAL_LEN>
+{
+}
+
+/// An iterator over the entries of an [Art](crate::Art) whose key starts with a prefix,
+/// sorted by key.
+///
+/// This `struct` is created by the [`prefix_iter`](crate::Art::prefix_iter) method on `Art`.
+pub struct PrefixIter<'a, K: ArtKey, V, const MAX_PARTIAL_LEN: usize> {
+    raw: RawIter<'a, K, V, MAX_PARTIAL_LEN>,
+}
+
+impl<'a, K: ArtKey, V, const MAX_PARTIAL_LEN: usize> PrefixIter<'a, K, V, MAX_PARTIAL_LEN> {
+    pub(crate) fn new(
+        root: &'a ArtNode<K, V, MAX_PARTIAL_LEN>,
+        prefix: &[u8],
+    ) -> PrefixIter<'a, K, V, MAX_PARTIAL_LEN> {
+        let raw = match ArtNode::seek_prefix(root, prefix) {
+            Some(node) => RawIter::new(node),
+            None => RawIter::empty(),
+        };
+        PrefixIter { raw }
+    }
+}
+
+impl<'a, K: ArtKey, V, const MAX_PARTIAL_LEN: usize> Iterator
+    for PrefixIter<'a, K, V, MAX_PARTIAL_LEN>
+{
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<(&'a K, &'a V)> {
+        let leaf = self.raw.next()?.static_cast_ref_leaf();
+        Some((&leaf.key, &leaf.val))
+    }
+}
+
+impl<'a, K: ArtKey, V, const MAX_PARTIAL_LEN: usize> DoubleEndedIterator
+    for PrefixIter<'a, K, V, MAX_PARTIAL_LEN>
+{
+    fn next_back(&mut self) -> Option<(&'a K, &'a V)> {
+        let leaf = self.raw.next_back()?.static_cast_ref_leaf();
+        Some((&leaf.key, &leaf.val))
+    }
+}
+
+impl<K: ArtKey, V, const MAX_PARTIAL_LEN: usize> FusedIterator
+    for PrefixIter<'_, K, V, MAX_PARTIAL_LEN>
+{
+}
+
+impl<K: ArtKey, V, const MAX_PARTIAL_LEN: usize> Clone for PrefixIter<'_, K, V, MAX_PARTIAL_LEN> {
+    fn clone(&self) -> Self {
+        PrefixIter {
+            raw: self.raw.clone(),
+        }
+    }
+}
+
+/// A mutable iterator over the entries of an [Art](crate::Art) whose key starts with a
+/// prefix, sorted by key.
+///
+/// This `struct` is created by the [`prefix_iter_mut`](crate::Art::prefix_iter_mut) method
+/// on `Art`.
+pub struct PrefixIterMut<'a, K: ArtKey, V, const MAX_PARTIAL_LEN: usize> {
+    raw: RawIter<'a, K, V, MAX_PARTIAL_LEN>,
+    _marker: PhantomData<&'a mut V>,
+}
+
+impl<'a, K: ArtKey, V, const MAX_PARTIAL_LEN: usize> PrefixIterMut<'a, K, V, MAX_PARTIAL_LEN> {
+    pub(crate) fn new(
+        root: &'a mut ArtNode<K, V, MAX_PARTIAL_LEN>,
+        prefix: &[u8],
+    ) -> PrefixIterMut<'a, K, V, MAX_PARTIAL_LEN> {
+        let root: &'a ArtNode<K, V, MAX_PARTIAL_LEN> = root;
+        let raw = match ArtNode::seek_prefix(root, prefix) {
+            Some(node) => RawIter::new(node),
+            None => RawIter::empty(),
+        };
+        PrefixIterMut {
+            raw,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<'a, K: ArtKey, V, const MAX_PARTIAL_LEN: usize> Iterator
+    for PrefixIterMut<'a, K, V, MAX_PARTIAL_LEN>
+{
+    type Item = (&'a K, &'a mut V);
+
+    fn next(&mut self) -> Option<(&'a K, &'a mut V)> {
+        // Safety: the tree is mutably borrowed for 'a and every leaf is yielded once.
+        let leaf = unsafe { &mut *self.raw.next()?.leaf_ptr() };
+        Some((&leaf.key, &mut leaf.val))
+    }
+}
+
+impl<'a, K: ArtKey, V, const MAX_PARTIAL_LEN: usize> DoubleEndedIterator
+    for PrefixIterMut<'a, K, V, MAX_PARTIAL_LEN>
+{
+    fn next_back(&mut self) -> Option<(&'a K, &'a mut V)> {
+        // Safety: the tree is mutably borrowed for 'a and every leaf is yielded once.
+        let leaf = unsafe { &mut *self.raw.next_back()?.leaf_ptr() };
+        Some((&leaf.key, &mut leaf.val))
+    }
+}
+
+impl<K: ArtKey, V, const MAX_PARTIAL_LEN: usize> FusedIterator
+    for PrefixIterMut<'_, K, V, MAX_PARTIAL_LEN>
+{
+}
+
+/// An owning iterator over the entries of an [Art](crate::Art), sorted by key.
+///
+/// This `struct` is created by the `into_iter` method on `Art`. The tree is taken apart
+/// while iterating: the subtrees not visited yet are kept in key order, and an inner node
+/// at either end is replaced by its children until a leaf comes out. The iterator owns the
+/// allocator of the tree and frees the nodes as they are taken apart.
+pub struct IntoIter<K: ArtKey, V, const MAX_PARTIAL_LEN: usize, A: NodeAllocator = Global> {
+    nodes: VecDeque<ArtNode<K, V, MAX_PARTIAL_LEN>>,
+    length: usize,
+    alloc: A,
+}
+
+impl<K: ArtKey, V, const MAX_PARTIAL_LEN: usize, A: NodeAllocator>
+    IntoIter<K, V, MAX_PARTIAL_LEN, A>
+{
+    pub(crate) fn new(
+        root: ArtNode<K, V, MAX_PARTIAL_LEN>,
+        length: usize,
+        alloc: A,
+    ) -> IntoIter<K, V, MAX_PARTIAL_LEN, A> {
+        let mut nodes = VecDeque::new();
+        if !root.is_none() {
+            nodes.push_back(root);
+        }
+        IntoIter {
+            nodes,
+            length,
+            alloc,
+        }
+    }
+}
+
+impl<K: ArtKey, V, const MAX_PARTIAL_LEN: usize, A: NodeAllocator> Iterator
+    for IntoIter<K, V, MAX_PARTIAL_LEN, A>
+{
+    type Item = (K, V);
+
+    fn next(&mut self) -> Option<(K, V)> {
+        loop {
+            let mut node = self.nodes.pop_front()?;
+            if node.is_leaf() {
+                self.length -= 1;
+                return node.take_leaf(&mut self.alloc);
+            }
+            for child in node.take_children().into_iter().rev() {
+                self.nodes.push_front(child);
+            }
+            node.free(&mut self.alloc);
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.length, Some(self.length))
+    }
+}
+
+impl<K: ArtKey, V, const MAX_PARTIAL_LEN: usize, A: NodeAllocator> DoubleEndedIterator
+    for IntoIter<K, V, MAX_PARTIAL_LEN, A>
+{
+    fn next_back(&mut self) -> Option<(K, V)> {
+        loop {
+            let mut node = self.nodes.pop_back()?;
+            if node.is_leaf() {
+                self.length -= 1;
+                return node.take_leaf(&mut self.alloc);
+            }
+            self.nodes.extend(node.take_children());
+            node.free(&mut self.alloc);
+        }
+    }
+}
+
+impl<K: ArtKey, V, const MAX_PARTIAL_LEN: usize, A: NodeAllocator> ExactSizeIterator
+    for IntoIter<K, V, MAX_PARTIAL_LEN, A>
+{
+}
+
+impl<K: ArtKey, V, const MAX_PARTIAL_LEN: usize, A: NodeAllocator> FusedIterator
+    for IntoIter<K, V, MAX_PARTIAL_LEN, A>
+{
+}
+
+impl<K: ArtKey, V, const MAX_PARTIAL_LEN: usize, A: NodeAllocator> Drop
+    for IntoIter<K, V, MAX_PARTIAL_LEN, A>
+{
+    fn drop(&mut self) {
+        for node in self.nodes.drain(..) {
+            node.free(&mut self.alloc);
+        }
+    }
+}
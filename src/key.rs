@@ -0,0 +1,274 @@
+/// A trait some constraints on the key of art.
+///
+/// Artful implements this trait for most of the built-in types. If you want to
+/// customize the type as an artful key, you will need to implement the trait.
+///
+/// The tree is ordered by comparing the bytes of keys with `memcmp`, so the bytes must be
+/// an order-preserving encoding: `a < b` if and only if `a.get_bytes() < b.get_bytes()`.
+/// Integers are encoded in big-endian with the sign bit flipped for signed types, floats
+/// are encoded in the IEEE-754 total order, see [f64::total_cmp].
+///
+/// Lookups accept any borrowed form `Q` of the key type `K`, so when `K: Borrow<Q>` both must
+/// produce the same bytes, as `String` and `str` do.
+///
+/// Tuples are encoded by concatenating their fields, where each field except the last one is
+/// written by [ArtKey::encode_delimited] so that no field can run into the next one.
+///
+/// # Examples
+///
+/// A custom key only needs to describe its encoding, usually by reusing the encoding of
+/// its fields:
+///
+/// ```
+/// use artful::{Art, ArtKey};
+///
+/// struct Version {
+///     major: u16,
+///     minor: u16,
+/// }
+///
+/// impl ArtKey for Version {
+///     type Bytes<'a> = Vec<u8>;
+///
+///     fn get_bytes(&self) -> Vec<u8> {
+///         (self.major, self.minor).get_bytes()
+///     }
+/// }
+///
+/// let mut art = Art::<Version, &str, 8>::new();
+/// art.insert(Version { major: 1, minor: 10 }, "b");
+/// art.insert(Version { major: 1, minor: 2 }, "a");
+/// let values: Vec<&str> = art.values().copied().collect();
+/// assert_eq!(values, ["a", "b"]);
+/// ```
+pub trait ArtKey {
+    /// The encoded bytes, either borrowed from the key or owned.
+    type Bytes<'a>: AsRef<[u8]>
+    where
+        Self: 'a;
+
+    /// Returns the order-preserving encoding of the key.
+    fn get_bytes(&self) -> Self::Bytes<'_>;
+
+    /// Appends the encoding of the key to `buf` so that it is never a prefix of the encoding
+    /// of another key, which is needed when the key is followed by other fields.
+    ///
+    /// The default implementation escapes every `0x00` as `0x00 0xFF` and terminates the
+    /// bytes with `0x00 0x00`, which keeps the order. Keys with a fixed encoded width may
+    /// override it to append the bytes as they are.
+    fn encode_delimited(&self, buf: &mut Vec<u8>) {
+        for &byte in self.get_bytes().as_ref() {
+            buf.push(byte);
+            if byte == 0 {
+                buf.push(0xFF);
+            }
+        }
+        buf.extend_from_slice(&[0, 0]);
+    }
+}
+
+macro_rules! unsigned_key {
+    ($($ty:ty),*) => {$(
+        impl ArtKey for $ty {
+            type Bytes<'a> = [u8; std::mem::size_of::<$ty>()];
+
+            fn get_bytes(&self) -> Self::Bytes<'_> {
+                self.to_be_bytes()
+            }
+
+            fn encode_delimited(&self, buf: &mut Vec<u8>) {
+                buf.extend_from_slice(&self.get_bytes());
+            }
+        }
+    )*};
+}
+
+macro_rules! signed_key {
+    ($($ty:ty => $unsigned:ty),*) => {$(
+        impl ArtKey for $ty {
+            type Bytes<'a> = [u8; std::mem::size_of::<$ty>()];
+
+            fn get_bytes(&self) -> Self::Bytes<'_> {
+                (*self as $unsigned ^ 1 << (<$unsigned>::BITS - 1)).to_be_bytes()
+            }
+
+            fn encode_delimited(&self, buf: &mut Vec<u8>) {
+                buf.extend_from_slice(&self.get_bytes());
+            }
+        }
+    )*};
+}
+
+macro_rules! float_key {
+    ($($ty:ty => $signed:ty, $unsigned:ty),*) => {$(
+        impl ArtKey for $ty {
+            type Bytes<'a> = [u8; std::mem::size_of::<$ty>()];
+
+            fn get_bytes(&self) -> Self::Bytes<'_> {
+                let bits = self.to_bits();
+                // flip all bits of a negative number and only the sign bit of a positive one.
+                let shift = <$unsigned>::BITS - 1;
+                let mask = ((bits as $signed >> shift) as $unsigned) | 1 << shift;
+                (bits ^ mask).to_be_bytes()
+            }
+
+            fn encode_delimited(&self, buf: &mut Vec<u8>) {
+                buf.extend_from_slice(&self.get_bytes());
+            }
+        }
+    )*};
+}
+
+unsigned_key!(u8, u16, u32, u64, u128);
+signed_key!(i8 => u8, i16 => u16, i32 => u32, i64 => u64, i128 => u128);
+float_key!(f32 => i32, u32, f64 => i64, u64);
+
+impl ArtKey for bool {
+    type Bytes<'a> = [u8; 1];
+
+    fn get_bytes(&self) -> [u8; 1] {
+        [*self as u8]
+    }
+
+    fn encode_delimited(&self, buf: &mut Vec<u8>) {
+        buf.push(*self as u8);
+    }
+}
+
+impl ArtKey for char {
+    type Bytes<'a> = [u8; 4];
+
+    fn get_bytes(&self) -> [u8; 4] {
+        (*self as u32).to_be_bytes()
+    }
+
+    fn encode_delimited(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(&self.get_bytes());
+    }
+}
+
+impl<const N: usize> ArtKey for [u8; N] {
+    type Bytes<'a> = &'a [u8];
+
+    fn get_bytes(&self) -> &[u8] {
+        self
+    }
+
+    fn encode_delimited(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(self);
+    }
+}
+
+impl ArtKey for [u8] {
+    type Bytes<'a> = &'a [u8];
+
+    fn get_bytes(&self) -> &[u8] {
+        self
+    }
+}
+
+impl ArtKey for Vec<u8> {
+    type Bytes<'a> = &'a [u8];
+
+    fn get_bytes(&self) -> &[u8] {
+        self
+    }
+}
+
+impl ArtKey for str {
+    type Bytes<'a> = &'a [u8];
+
+    fn get_bytes(&self) -> &[u8] {
+        self.as_bytes()
+    }
+}
+
+impl ArtKey for String {
+    type Bytes<'a> = &'a [u8];
+
+    fn get_bytes(&self) -> &[u8] {
+        self.as_bytes()
+    }
+}
+
+impl<T: ArtKey + ?Sized> ArtKey for &T {
+    type Bytes<'a>
+        = T::Bytes<'a>
+    where
+        Self: 'a;
+
+    fn get_bytes(&self) -> Self::Bytes<'_> {
+        (**self).get_bytes()
+    }
+
+    fn encode_delimited(&self, buf: &mut Vec<u8>) {
+        (**self).encode_delimited(buf)
+    }
+}
+
+macro_rules! tuple_key {
+    ($(($($init:ident),*; $last:ident)),*) => {$(
+        #[allow(non_snake_case)]
+        impl<$($init: ArtKey,)* $last: ArtKey> ArtKey for ($($init,)* $last,) {
+            type Bytes<'a>
+                = Vec<u8>
+            where
+                Self: 'a;
+
+            fn get_bytes(&self) -> Vec<u8> {
+                let ($($init,)* $last,) = self;
+                let mut buf = Vec::new();
+                $($init.encode_delimited(&mut buf);)*
+                buf.extend_from_slice($last.get_bytes().as_ref());
+                buf
+            }
+
+            fn encode_delimited(&self, buf: &mut Vec<u8>) {
+                let ($($init,)* $last,) = self;
+                $($init.encode_delimited(buf);)*
+                $last.encode_delimited(buf);
+            }
+        }
+    )*};
+}
+
+tuple_key!((A; B), (A, B; C), (A, B, C; D));
+
+#[cfg(test)]
+mod test {
+    use super::ArtKey;
+
+    fn assert_ordered<K: ArtKey>(keys: &[K]) {
+        for pair in keys.windows(2) {
+            assert!(pair[0].get_bytes().as_ref() < pair[1].get_bytes().as_ref());
+        }
+    }
+
+    #[test]
+    fn delimited_bytes_keep_order() {
+        assert_ordered(&[
+            (String::new(), 9u32),
+            ("\0".to_string(), 0),
+            ("\0\0".to_string(), 0),
+            ("\0a".to_string(), 0),
+            ("a".to_string(), u32::MAX),
+            ("a\0".to_string(), 0),
+            ("ab".to_string(), 0),
+        ]);
+        assert_ordered(&[(vec![1u8], 2u8, -1i8), (vec![1], 2, 0), (vec![1, 0], 0, 0)]);
+        assert_ordered(&[((1u8, "a"), "b"), ((1, "a\0"), "a"), ((1, "b"), "")]);
+    }
+
+    #[test]
+    fn fixed_width_keys_keep_order() {
+        assert_ordered(&[i128::MIN, -1, 0, 1, i128::MAX]);
+        assert_ordered(&[0u128, 1 << 64, u128::MAX]);
+        assert_ordered(&[false, true]);
+        assert_ordered(&['\0', 'a', 'é', '\u{10FFFF}']);
+        assert_ordered(&[
+            [0u8; 16],
+            [0, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+            [1; 16],
+        ]);
+    }
+}
@@ -13,10 +13,11 @@ impl<K: ArtKey, V> Leaf<K, V> {
 
     pub(crate) fn matches(&self, key: &[u8]) -> bool {
         let leaf_key = self.key.get_bytes();
+        let leaf_key = leaf_key.as_ref();
         if leaf_key.len() != key.len() {
             return false;
         }
 
-        return leaf_key == key;
+        leaf_key == key
     }
 }
@@ -1,154 +1,29 @@
+pub mod alloc;
 pub mod art;
+mod bulk;
+pub mod disk;
+mod entry;
+mod iter;
+mod key;
 mod leaf;
 mod node;
 mod node16;
 mod node256;
 mod node4;
 mod node48;
+pub mod persistent;
+#[cfg(feature = "serde")]
+mod serde;
 mod simd;
+mod stats;
+#[cfg(feature = "sync")]
+pub mod sync;
+mod validate;
 pub use art::Art;
-
-/// A trait some constraints on the key of art.
-///
-/// Artful implements this trait for most of the built-in types. If you want to
-/// customize the type as an artful key, you will need to implement the trait.
-pub trait ArtKey: Default {
-    /// Returns a reference to a byte slice from a particular type.
-    fn get_bytes(&self) -> &[u8];
-
-    /// Returns a mutable reference to a byte slice from a particular type.
-    fn get_mut_bytes(&mut self) -> &mut [u8];
-}
-
-impl ArtKey for i8 {
-    fn get_bytes(&self) -> &[u8] {
-        let ptr = self as *const i8 as *const u8;
-        unsafe { std::slice::from_raw_parts(ptr, 1) }
-    }
-
-    fn get_mut_bytes(&mut self) -> &mut [u8] {
-        let ptr = self as *mut i8 as *mut u8;
-        unsafe { std::slice::from_raw_parts_mut(ptr, 1) }
-    }
-}
-
-impl ArtKey for i16 {
-    fn get_bytes(&self) -> &[u8] {
-        let ptr = self as *const i16 as *const u8;
-        unsafe { std::slice::from_raw_parts(ptr, 2) }
-    }
-
-    fn get_mut_bytes(&mut self) -> &mut [u8] {
-        let ptr = self as *mut i16 as *mut u8;
-        unsafe { std::slice::from_raw_parts_mut(ptr, 2) }
-    }
-}
-
-impl ArtKey for i32 {
-    fn get_bytes(&self) -> &[u8] {
-        let ptr = self as *const i32 as *const u8;
-        unsafe { std::slice::from_raw_parts(ptr, 4) }
-    }
-
-    fn get_mut_bytes(&mut self) -> &mut [u8] {
-        let ptr = self as *mut i32 as *mut u8;
-        unsafe { std::slice::from_raw_parts_mut(ptr, 4) }
-    }
-}
-
-impl ArtKey for i64 {
-    fn get_bytes(&self) -> &[u8] {
-        let ptr = self as *const i64 as *const u8;
-        unsafe { std::slice::from_raw_parts(ptr, 8) }
-    }
-
-    fn get_mut_bytes(&mut self) -> &mut [u8] {
-        let ptr = self as *mut i64 as *mut u8;
-        unsafe { std::slice::from_raw_parts_mut(ptr, 8) }
-    }
-}
-
-impl ArtKey for u8 {
-    fn get_bytes(&self) -> &[u8] {
-        let ptr = self as *const u8;
-        unsafe { std::slice::from_raw_parts(ptr, 1) }
-    }
-
-    fn get_mut_bytes(&mut self) -> &mut [u8] {
-        let ptr = self as *mut u8;
-        unsafe { std::slice::from_raw_parts_mut(ptr, 1) }
-    }
-}
-
-impl ArtKey for u16 {
-    fn get_bytes(&self) -> &[u8] {
-        let ptr = self as *const u16 as *const u8;
-        unsafe { std::slice::from_raw_parts(ptr, 2) }
-    }
-
-    fn get_mut_bytes(&mut self) -> &mut [u8] {
-        let ptr = self as *mut u16 as *mut u8;
-        unsafe { std::slice::from_raw_parts_mut(ptr, 2) }
-    }
-}
-
-impl ArtKey for u32 {
-    fn get_bytes(&self) -> &[u8] {
-        let ptr = self as *const u32 as *const u8;
-        unsafe { std::slice::from_raw_parts(ptr, 4) }
-    }
-
-    fn get_mut_bytes(&mut self) -> &mut [u8] {
-        let ptr = self as *mut u32 as *mut u8;
-        unsafe { std::slice::from_raw_parts_mut(ptr, 4) }
-    }
-}
-
-impl ArtKey for u64 {
-    fn get_bytes(&self) -> &[u8] {
-        let ptr = self as *const u64 as *const u8;
-        unsafe { std::slice::from_raw_parts(ptr, 8) }
-    }
-
-    fn get_mut_bytes(&mut self) -> &mut [u8] {
-        let ptr = self as *mut u64 as *mut u8;
-        unsafe { std::slice::from_raw_parts_mut(ptr, 8) }
-    }
-}
-
-impl ArtKey for f32 {
-    fn get_bytes(&self) -> &[u8] {
-        let ptr = self as *const f32 as *const u8;
-        unsafe { std::slice::from_raw_parts(ptr, 4) }
-    }
-
-    fn get_mut_bytes(&mut self) -> &mut [u8] {
-        let ptr = self as *mut f32 as *mut u8;
-        unsafe { std::slice::from_raw_parts_mut(ptr, 4) }
-    }
-}
-
-impl ArtKey for f64 {
-    fn get_bytes(&self) -> &[u8] {
-        let ptr = self as *const f64 as *const u8;
-        unsafe { std::slice::from_raw_parts(ptr, 8) }
-    }
-
-    fn get_mut_bytes(&mut self) -> &mut [u8] {
-        let ptr = self as *mut f64 as *mut u8;
-        unsafe { std::slice::from_raw_parts_mut(ptr, 8) }
-    }
-}
-
-impl ArtKey for String {
-    fn get_bytes(&self) -> &[u8] {
-        self.as_bytes()
-    }
-
-    fn get_mut_bytes(&mut self) -> &mut [u8] {
-        unsafe { self.as_bytes_mut() }
-    }
-}
+pub use key::ArtKey;
+pub use persistent::PersistentArt;
+#[cfg(feature = "sync")]
+pub use sync::ConcurrentArt;
 
 #[derive(Debug, Clone, Copy)]
 pub(crate) struct Partial<const MAX_PARTIAL_LEN: usize> {
@@ -165,7 +40,18 @@ impl<const MAX_PARTIAL_LEN: usize> Default for Partial<MAX_PARTIAL_LEN> {
     }
 }
 
-#[derive(Default, Debug, Clone, Copy)]
+/// Two partials are equal when they have the same length and the same stored bytes, the
+/// bytes beyond `MAX_PARTIAL_LEN` of an optimistic path are not stored.
+impl<const MAX_PARTIAL_LEN: usize> PartialEq for Partial<MAX_PARTIAL_LEN> {
+    fn eq(&self, other: &Partial<MAX_PARTIAL_LEN>) -> bool {
+        let stored = std::cmp::min(self.len as usize, MAX_PARTIAL_LEN);
+        self.len == other.len && self.data[..stored] == other.data[..stored]
+    }
+}
+
+impl<const MAX_PARTIAL_LEN: usize> Eq for Partial<MAX_PARTIAL_LEN> {}
+
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
 pub(crate) struct Header<const MAX_PARTIAL_LEN: usize> {
     pub(crate) partial: Partial<MAX_PARTIAL_LEN>,
     pub(crate) non_null_children: u16,
@@ -1,24 +1,48 @@
-use std::cmp::min;
+use std::alloc::Layout;
+use std::cmp::{min, Ordering};
 use std::marker::PhantomData;
+use std::ptr::NonNull;
 
+use crate::alloc::NodeAllocator;
 use crate::leaf::Leaf;
 use crate::node16::Node16;
 use crate::node256::Node256;
 use crate::node4::Node4;
 use crate::node48::Node48;
+use crate::validate::Violation;
 use crate::ArtKey;
 use crate::Header;
+use crate::Partial;
 
 const NODE_TYPE_NONE: usize = 0;
-const NODE_TYPE_N4: usize = 1;
-const NODE_TYPE_N16: usize = 2;
-const NODE_TYPE_N48: usize = 3;
-const NODE_TYPE_N256: usize = 4;
-const NODE_TYPE_LEAF: usize = 5;
+pub(crate) const NODE_TYPE_N4: usize = 1;
+pub(crate) const NODE_TYPE_N16: usize = 2;
+pub(crate) const NODE_TYPE_N48: usize = 3;
+pub(crate) const NODE_TYPE_N256: usize = 4;
+pub(crate) const NODE_TYPE_LEAF: usize = 5;
 const NODE_TYPE_MASK: usize = 7;
 const NODE_PTR_MASK: usize = usize::MAX - NODE_TYPE_MASK;
 
-// TODO: impl PartialEq for ArtNode
+/// Returns the layout a node or leaf of type `T` is allocated with, aligned so the low bits
+/// of the address are free for the node type and never zero-sized.
+pub(crate) fn layout<T>() -> Layout {
+    let layout = Layout::new::<T>()
+        .align_to(NODE_TYPE_MASK + 1)
+        .expect("node layout");
+    Layout::from_size_align(layout.size().max(layout.align()), layout.align()).expect("node layout")
+}
+
+/// A tagged pointer to a leaf or an inner node, none when zero.
+///
+/// A node does not know its allocator, so every node that is not none has exactly one owner
+/// that frees it with [free](ArtNode::free): the root or a child slot of a tree, the nodes
+/// left in an owning iterator, the open nodes of a bulk builder or the copy being cloned.
+/// Each of them frees its nodes when dropped, also while unwinding. Dropping a node in any
+/// other way is a bug, which [Drop] turns into a panic in every build.
+///
+/// A panic in the middle of an operation, such as from `ArtKey::get_bytes` while an insert
+/// reshapes the tree, leaks the nodes held in locals at that point, which is safe.
+#[repr(transparent)]
 pub struct ArtNode<K: ArtKey, V, const MAX_PARTIAL_LEN: usize>(
     pub(crate) usize,
     PhantomData<K>,
@@ -43,6 +67,51 @@ pub(crate) enum ArtNodeMut<'a, K: ArtKey, V, const MAX_PARTIAL_LEN: usize> {
     Node256(&'a mut Node256<K, V, MAX_PARTIAL_LEN>),
 }
 
+/// The place found by [ArtNode::seek_insert_site], either the leaf already storing the key or
+/// the node where a new leaf goes.
+pub(crate) enum InsertSite<'a, K: ArtKey, V, const MAX_PARTIAL_LEN: usize> {
+    Occupied(&'a mut Leaf<K, V>),
+    Vacant(VacantSite<'a, K, V, MAX_PARTIAL_LEN>),
+}
+
+pub(crate) struct VacantSite<'a, K: ArtKey, V, const MAX_PARTIAL_LEN: usize> {
+    node: &'a mut ArtNode<K, V, MAX_PARTIAL_LEN>,
+    depth: usize,
+    kind: VacantKind,
+}
+
+enum VacantKind {
+    /// The node is none and is replaced by the new leaf.
+    Empty,
+    /// The node is a leaf of another key, see [LazyExpand::expand].
+    Expand,
+    /// The compressed path of the node mismatches the key at the position.
+    Compression(usize),
+    /// The node has no child for the key byte.
+    Child((u8, bool)),
+}
+
+impl<'a, K: ArtKey, V, const MAX_PARTIAL_LEN: usize> VacantSite<'a, K, V, MAX_PARTIAL_LEN> {
+    /// Links a new leaf into the tree and returns a reference to its value.
+    pub(crate) fn insert<A: NodeAllocator>(self, alloc: &mut A, key: K, val: V) -> &'a mut V {
+        let new_leaf = ArtNode::leaf(alloc, key, val);
+        // the leaf has a block of its own, so it stays in place when the tree is reshaped
+        // around it.
+        let leaf_ptr = new_leaf.leaf_ptr();
+        let node = self.node;
+        match self.kind {
+            VacantKind::Empty => *node = new_leaf,
+            VacantKind::Expand => {
+                *node = LazyExpand::expand(alloc, std::mem::take(node), new_leaf, self.depth)
+            }
+            VacantKind::Compression(pos) => node.compression(alloc, pos, new_leaf, self.depth),
+            VacantKind::Child(valid_key) => node.insert_child(alloc, valid_key, new_leaf),
+        }
+
+        unsafe { &mut (*leaf_ptr).val }
+    }
+}
+
 struct ArtKeyVerifier;
 
 impl ArtKeyVerifier {
@@ -68,7 +137,7 @@ impl LazyExpand {
             }
         }
 
-        return max_len;
+        max_len
     }
 
     /// Lazy expansion to remove path to single leaf: an existing leaf is encountered,
@@ -76,30 +145,38 @@ impl LazyExpand {
     ///
     /// **Safety**: the existing_leaf must be a leaf node.
     #[inline]
-    fn expand<K: ArtKey, V, const MAX_PARTIAL_LEN: usize>(
+    fn expand<K: ArtKey, V, const MAX_PARTIAL_LEN: usize, A: NodeAllocator>(
+        alloc: &mut A,
         node: ArtNode<K, V, MAX_PARTIAL_LEN>,
-        key: K,
-        val: V,
+        new_leaf: ArtNode<K, V, MAX_PARTIAL_LEN>,
         depth: usize,
     ) -> ArtNode<K, V, MAX_PARTIAL_LEN> {
-        let new_leaf_key = key.get_bytes();
-        let leaf_key = node.static_cast_ref_leaf().key.get_bytes();
-
-        let longest_partial_len = LazyExpand::longest_common_prefix(leaf_key, new_leaf_key, depth);
-        // copy matched longest prefix to node4
-        let mut node4: Box<Node4<K, V, MAX_PARTIAL_LEN>> = Box::default();
-        node4.header.partial.len = longest_partial_len as u32;
-        let max_copy_len = min(MAX_PARTIAL_LEN, longest_partial_len);
-        node4.header.partial.data[..max_copy_len]
-            .copy_from_slice(&new_leaf_key[depth..depth + max_copy_len]);
-
-        let mut new_node = ArtNode::node4(node4);
-        let depth = depth + longest_partial_len;
-        new_node.insert_child(ArtKeyVerifier::valid(leaf_key, depth), node);
-        new_node.insert_child(
-            ArtKeyVerifier::valid(new_leaf_key, depth),
-            ArtNode::leaf(key, val),
-        );
+        let mut node4: Node4<K, V, MAX_PARTIAL_LEN> = Node4::default();
+        // the encoded keys may borrow both leaves, so they are dropped before moving.
+        let (leaf_valid_key, new_leaf_valid_key) = {
+            let new_leaf_key = new_leaf.static_cast_ref_leaf().key.get_bytes();
+            let new_leaf_key = new_leaf_key.as_ref();
+            let leaf_key = node.static_cast_ref_leaf().key.get_bytes();
+            let leaf_key = leaf_key.as_ref();
+
+            let longest_partial_len =
+                LazyExpand::longest_common_prefix(leaf_key, new_leaf_key, depth);
+            // copy matched longest prefix to node4
+            node4.header.partial.len = longest_partial_len as u32;
+            let max_copy_len = min(MAX_PARTIAL_LEN, longest_partial_len);
+            node4.header.partial.data[..max_copy_len]
+                .copy_from_slice(&new_leaf_key[depth..depth + max_copy_len]);
+
+            let depth = depth + longest_partial_len;
+            (
+                ArtKeyVerifier::valid(leaf_key, depth),
+                ArtKeyVerifier::valid(new_leaf_key, depth),
+            )
+        };
+
+        let mut new_node = ArtNode::node4(alloc, node4);
+        new_node.insert_child(alloc, leaf_valid_key, node);
+        new_node.insert_child(alloc, new_leaf_valid_key, new_leaf);
         new_node
     }
 }
@@ -110,15 +187,25 @@ impl<K: ArtKey, V, const MAX_PARTIAL_LEN: usize> ArtNode<K, V, MAX_PARTIAL_LEN>
         key: &[u8],
         depth: usize,
     ) -> Option<&'a V> {
+        ArtNode::get_leaf(root, key, depth).map(|leaf| &leaf.val)
+    }
+
+    /// Returns the leaf that stores the key, which gives access to the stored key as well
+    /// as the value.
+    pub(crate) fn get_leaf<'a>(
+        root: &'a ArtNode<K, V, MAX_PARTIAL_LEN>,
+        key: &[u8],
+        depth: usize,
+    ) -> Option<&'a Leaf<K, V>> {
         let mut depth = depth;
-        let mut current: &ArtNode<K, V, MAX_PARTIAL_LEN> = &*root;
+        let mut current: &ArtNode<K, V, MAX_PARTIAL_LEN> = root;
         while !current.is_none() {
             if current.is_leaf() {
                 let leaf = current.static_cast_ref_leaf();
                 // handles lazy expansion by checking that the
                 // encountered leaf fully matches the key.
                 if leaf.matches(key) {
-                    return Some(&leaf.val);
+                    return Some(leaf);
                 }
                 return None;
             }
@@ -177,6 +264,52 @@ impl<K: ArtKey, V, const MAX_PARTIAL_LEN: usize> ArtNode<K, V, MAX_PARTIAL_LEN>
         None
     }
 
+    /// Returns the node covering every key that starts with `prefix`: the node is either an
+    /// inner node where the prefix ends inside or right after its compressed path, or a
+    /// single leaf starting with the prefix.
+    pub(crate) fn seek_prefix<'a>(
+        root: &'a ArtNode<K, V, MAX_PARTIAL_LEN>,
+        prefix: &[u8],
+    ) -> Option<&'a ArtNode<K, V, MAX_PARTIAL_LEN>> {
+        let mut depth = 0;
+        let mut current: &ArtNode<K, V, MAX_PARTIAL_LEN> = root;
+        while !current.is_none() {
+            if current.is_leaf() {
+                let leaf = current.static_cast_ref_leaf();
+                if leaf.key.get_bytes().as_ref().starts_with(prefix) {
+                    return Some(current);
+                }
+                return None;
+            }
+
+            if depth == prefix.len() {
+                return Some(current);
+            }
+
+            let header = current.header();
+            if header.partial.len > 0 {
+                // both pessimistic and optimistic path are matched, the latter falls back
+                // to the minimum leaf for the bytes not stored in the header.
+                let mismatch_pos = ArtNode::prefix_mismatch(current, header, prefix, depth);
+                if mismatch_pos < header.partial.len as usize {
+                    if depth + mismatch_pos == prefix.len() {
+                        return Some(current);
+                    }
+                    return None;
+                }
+                depth += header.partial.len as usize;
+                if depth == prefix.len() {
+                    return Some(current);
+                }
+            }
+
+            current = current.child_of(prefix[depth])?;
+            depth += 1;
+        }
+
+        None
+    }
+
     #[inline(always)]
     fn check_prefix_match(&self, key_byte: &[u8], depth: usize) -> usize {
         let header = self.header();
@@ -191,7 +324,7 @@ impl<K: ArtKey, V, const MAX_PARTIAL_LEN: usize> ArtNode<K, V, MAX_PARTIAL_LEN>
             }
         }
 
-        return max_compare_len;
+        max_compare_len
     }
 
     #[inline(always)]
@@ -206,72 +339,200 @@ impl<K: ArtKey, V, const MAX_PARTIAL_LEN: usize> ArtNode<K, V, MAX_PARTIAL_LEN>
         }
     }
 
-    pub(crate) fn insert(
+    /// Returns the number of child slots visited by ordered iteration.
+    ///
+    /// A leaf is treated as a node holding itself in its only slot, so that
+    /// iterators can start from any node of the tree.
+    #[inline]
+    pub(crate) fn slots(&self) -> usize {
+        match self.as_ref() {
+            ArtNodeRef::None => 0,
+            ArtNodeRef::Leaf(_) => 1,
+            ArtNodeRef::Node4(n4) => n4.slots(),
+            ArtNodeRef::Node16(n16) => n16.slots(),
+            ArtNodeRef::Node48(n48) => n48.slots(),
+            ArtNodeRef::Node256(n256) => n256.slots(),
+        }
+    }
+
+    /// Returns the non-empty child at `slot`, slots are ordered by key byte
+    /// with the prefixed child first.
+    #[inline]
+    pub(crate) fn child_at(&self, slot: usize) -> Option<&ArtNode<K, V, MAX_PARTIAL_LEN>> {
+        match self.as_ref() {
+            ArtNodeRef::None => None,
+            ArtNodeRef::Leaf(_) => Some(self),
+            ArtNodeRef::Node4(n4) => n4.child_at(slot),
+            ArtNodeRef::Node16(n16) => n16.child_at(slot),
+            ArtNodeRef::Node48(n48) => n48.child_at(slot),
+            ArtNodeRef::Node256(n256) => n256.child_at(slot),
+        }
+    }
+
+    /// Returns the first slot of an inner node whose key byte is greater than or
+    /// equal to `byte`.
+    #[inline]
+    pub(crate) fn slot_of(&self, byte: u8) -> usize {
+        match self.as_ref() {
+            ArtNodeRef::Node4(n4) => n4.slot_of(byte),
+            ArtNodeRef::Node16(n16) => n16.slot_of(byte),
+            ArtNodeRef::Node48(n48) => n48.slot_of(byte),
+            ArtNodeRef::Node256(n256) => n256.slot_of(byte),
+            _ => unreachable!(),
+        }
+    }
+
+    /// Returns the non-empty child of an inner node for the key byte.
+    #[inline]
+    pub(crate) fn child_of(&self, byte: u8) -> Option<&ArtNode<K, V, MAX_PARTIAL_LEN>> {
+        self.get_child((byte, true))
+            .filter(|child| !child.is_none())
+    }
+
+    /// Returns the prefixed child of an inner node if it is not empty.
+    pub(crate) fn prefixed_child(&self) -> Option<&ArtNode<K, V, MAX_PARTIAL_LEN>> {
+        self.get_child((0, false)).filter(|child| !child.is_none())
+    }
+
+    /// Returns the non-empty children of an inner node with their key bytes, in key order
+    /// and without the prefixed child.
+    pub(crate) fn children(&self) -> Vec<(u8, &ArtNode<K, V, MAX_PARTIAL_LEN>)> {
+        (0..=u8::MAX)
+            .filter_map(|byte| Some((byte, self.child_of(byte)?)))
+            .collect()
+    }
+
+    /// Returns the type of the node, one of the `NODE_TYPE_*` tags.
+    pub(crate) fn node_type(&self) -> usize {
+        self.0 & NODE_TYPE_MASK
+    }
+
+    /// Checks the children of an inner node against its header, see [Violation].
+    pub(crate) fn check(&self) -> Result<(), Violation> {
+        match self.as_ref() {
+            ArtNodeRef::Node4(n4) => n4.check(),
+            ArtNodeRef::Node16(n16) => n16.check(),
+            ArtNodeRef::Node48(n48) => n48.check(),
+            ArtNodeRef::Node256(n256) => n256.check(),
+            ArtNodeRef::Leaf(_) | ArtNodeRef::None => Ok(()),
+        }
+    }
+
+    /// Compares `key` from `depth` with the compressed path of an inner node.
+    ///
+    /// Returns `Equal` when the whole path matches, otherwise `Less` or `Greater` tells
+    /// whether the key sorts before or after every key stored below the node. An
+    /// optimistic path longer than `MAX_PARTIAL_LEN` is compared against the minimum leaf.
+    pub(crate) fn compare_prefix(&self, key: &[u8], depth: usize) -> Ordering {
+        let header = self.header();
+        let partial_len = header.partial.len as usize;
+        let mismatch_pos = ArtNode::prefix_mismatch(self, header, key, depth);
+        if mismatch_pos >= partial_len {
+            return Ordering::Equal;
+        }
+
+        // the key is exhausted inside the path, so it is a prefix of every key below.
+        if depth + mismatch_pos == key.len() {
+            return Ordering::Less;
+        }
+
+        let prefix_byte = if mismatch_pos < MAX_PARTIAL_LEN {
+            header.partial.data[mismatch_pos]
+        } else {
+            let leaf = ArtNode::minimum_child(self).expect("the inner node get minimum child fail");
+            leaf.key.get_bytes().as_ref()[depth + mismatch_pos]
+        };
+        key[depth + mismatch_pos].cmp(&prefix_byte)
+    }
+
+    pub(crate) fn insert<A: NodeAllocator>(
         node: &mut ArtNode<K, V, MAX_PARTIAL_LEN>,
+        alloc: &mut A,
         key: K,
         val: V,
         depth: usize,
     ) -> Option<V> {
-        let mut depth = depth;
-        match node.as_mut() {
-            ArtNodeMut::None => {
-                *node = ArtNode::leaf(key, val);
+        let site = ArtNode::seek_insert_site(node, key.get_bytes().as_ref(), depth);
+        match site {
+            // TODO: Can support leaf multi version?
+            InsertSite::Occupied(leaf) => Some(std::mem::replace(&mut leaf.val, val)),
+            InsertSite::Vacant(vacant) => {
+                vacant.insert(alloc, key, val);
                 None
             }
+        }
+    }
 
-            ArtNodeMut::Leaf(leaf) => {
-                if leaf.matches(key.get_bytes()) {
-                    // TODO: Can support leaf multi version?
-                    return Some(std::mem::replace(&mut leaf.val, val));
+    /// Descends to the place where the key lives or would be inserted, without changing
+    /// the tree, so that an insertion walks the path only once.
+    ///
+    /// The descent is a loop, the depth of the tree is only bounded by the key length.
+    pub(crate) fn seek_insert_site<'a>(
+        node: &'a mut ArtNode<K, V, MAX_PARTIAL_LEN>,
+        key: &[u8],
+        depth: usize,
+    ) -> InsertSite<'a, K, V, MAX_PARTIAL_LEN> {
+        let mut node = node;
+        let mut depth = depth;
+        loop {
+            let kind = if node.is_none() {
+                VacantKind::Empty
+            } else if node.is_leaf() {
+                if node.static_cast_ref_leaf().matches(key) {
+                    return InsertSite::Occupied(node.static_cast_mut_leaf());
                 }
                 // expand leaf
-                *node = LazyExpand::expand::<K, V, MAX_PARTIAL_LEN>(
-                    std::mem::take(node),
-                    key,
-                    val,
-                    depth,
-                );
-                None
-            }
-
-            _ => {
+                VacantKind::Expand
+            } else {
                 let header = node.header();
+                let mut mismatched_pos = None;
                 if header.partial.len > 0 {
-                    let mismatched_pos = ArtNode::<K, V, MAX_PARTIAL_LEN>::prefix_mismatch(
-                        node,
-                        header,
-                        key.get_bytes(),
-                        depth,
-                    );
-
-                    if mismatched_pos >= header.partial.len as usize {
+                    let pos = ArtNode::prefix_mismatch(node, header, key, depth);
+                    if pos >= header.partial.len as usize {
                         depth += header.partial.len as usize;
                     } else {
-                        node.compression(mismatched_pos, key, depth, val);
-                        return None;
+                        mismatched_pos = Some(pos);
                     }
                 }
 
-                if let Some(child) =
-                    node.get_mut_child(ArtKeyVerifier::valid(key.get_bytes(), depth))
-                {
-                    return ArtNode::insert(child, key, val, depth + 1);
+                match mismatched_pos {
+                    Some(pos) => VacantKind::Compression(pos),
+                    None => {
+                        let valid_key = ArtKeyVerifier::valid(key, depth);
+                        // the child is checked by a shared borrow first, returning the
+                        // mutable borrow from a conditional branch would keep `node`
+                        // borrowed. An empty slot is filled by `insert_child` to keep the
+                        // children count right.
+                        if node
+                            .get_child(valid_key)
+                            .is_some_and(|child| !child.is_none())
+                        {
+                            node = node.get_mut_child(valid_key).unwrap();
+                            depth += 1;
+                            continue;
+                        }
+                        VacantKind::Child(valid_key)
+                    }
                 }
+            };
 
-                // node.add_child_with_grow(false, key.get_bytes()key.get_bytes()[depth], ArtNode::leaf(key, val));
-                node.assert_size();
-                node.insert_child(
-                    ArtKeyVerifier::valid(key.get_bytes(), depth),
-                    ArtNode::leaf(key, val),
-                );
-                return None;
-            }
+            return InsertSite::Vacant(VacantSite { node, depth, kind });
         }
     }
 
     #[inline]
-    fn compression(&mut self, prefix_mismatch_pos: usize, key: K, depth: usize, val: V) {
-        let mut old_node = std::mem::replace(self, ArtNode::node4(Box::default()));
+    fn compression<A: NodeAllocator>(
+        &mut self,
+        alloc: &mut A,
+        prefix_mismatch_pos: usize,
+        new_leaf: ArtNode<K, V, MAX_PARTIAL_LEN>,
+        depth: usize,
+    ) {
+        let new_leaf_valid_key = ArtKeyVerifier::valid(
+            new_leaf.static_cast_ref_leaf().key.get_bytes().as_ref(),
+            depth + prefix_mismatch_pos,
+        );
+        let mut old_node = std::mem::replace(self, ArtNode::node4(alloc, Node4::default()));
         // self is already new node
 
         let old_node_header = old_node.header();
@@ -306,30 +567,32 @@ impl<K: ArtKey, V, const MAX_PARTIAL_LEN: usize> ArtNode<K, V, MAX_PARTIAL_LEN>
             //         min(MAX_PARTIAL_LEN, old_node_header.partial.len as usize),
             //     );
             // }
-            self.insert_child((old_node_byte, true), old_node);
-            self.insert_child(
-                ArtKeyVerifier::valid(key.get_bytes(), depth + prefix_mismatch_pos),
-                ArtNode::leaf(key, val),
-            );
+            self.insert_child(alloc, (old_node_byte, true), old_node);
+            self.insert_child(alloc, new_leaf_valid_key, new_leaf);
 
             return;
         }
 
         // optimistic compression
-        // TODO: optimization the Header::default() to zero size.
-        let mut old_node_header = std::mem::take(old_node.header_mut());
-        let leaf =
-            ArtNode::minimum_child(&old_node).expect("the inner node get minimum child fail");
+        // the header is copied, the minimum child is looked up with the children count in place.
+        let mut old_node_header = *old_node.header();
+        let valid_key = {
+            let leaf =
+                ArtNode::minimum_child(&old_node).expect("the inner node get minimum child fail");
 
-        let leaf_key_bytes = leaf.key.get_bytes();
-        let valid_key = ArtKeyVerifier::valid(leaf_key_bytes, depth + prefix_mismatch_pos);
+            let leaf_key_bytes = leaf.key.get_bytes();
+            let leaf_key_bytes = leaf_key_bytes.as_ref();
+            let valid_key = ArtKeyVerifier::valid(leaf_key_bytes, depth + prefix_mismatch_pos);
 
-        // TODO add proof.
-        old_node_header.partial.len -= (prefix_mismatch_pos + 1) as u32;
-        let max_copy_len = min(MAX_PARTIAL_LEN, old_node_header.partial.len as usize);
-        let start = depth + prefix_mismatch_pos + 1;
-        let end = start + max_copy_len;
-        old_node_header.partial.data[..max_copy_len].copy_from_slice(&leaf_key_bytes[start..end]);
+            // TODO add proof.
+            old_node_header.partial.len -= (prefix_mismatch_pos + 1) as u32;
+            let max_copy_len = min(MAX_PARTIAL_LEN, old_node_header.partial.len as usize);
+            let start = depth + prefix_mismatch_pos + 1;
+            let end = start + max_copy_len;
+            old_node_header.partial.data[..max_copy_len]
+                .copy_from_slice(&leaf_key_bytes[start..end]);
+            valid_key
+        };
 
         std::mem::swap(&mut old_node_header, old_node.header_mut());
         // unsafe {
@@ -341,11 +604,8 @@ impl<K: ArtKey, V, const MAX_PARTIAL_LEN: usize> ArtNode<K, V, MAX_PARTIAL_LEN>
         //         min(MAX_PARTIAL_LEN, old_node.header().partial.len as usize),
         //     )
         // }
-        self.insert_child(valid_key, old_node);
-        self.insert_child(
-            ArtKeyVerifier::valid(key.get_bytes(), depth + prefix_mismatch_pos),
-            ArtNode::leaf(key, val),
-        );
+        self.insert_child(alloc, valid_key, old_node);
+        self.insert_child(alloc, new_leaf_valid_key, new_leaf);
     }
 
     #[inline]
@@ -370,6 +630,7 @@ impl<K: ArtKey, V, const MAX_PARTIAL_LEN: usize> ArtNode<K, V, MAX_PARTIAL_LEN>
         if node_header.partial.len as usize > MAX_PARTIAL_LEN {
             let leaf = ArtNode::minimum_child(node).expect("the inner node get minimum child fail");
             let leaf_key = leaf.key.get_bytes();
+            let leaf_key = leaf_key.as_ref();
             let max_compare_len = min(leaf_key.len(), key.len()) - depth;
             while matched_index < max_compare_len {
                 if leaf_key[depth + matched_index] != key[depth + matched_index] {
@@ -380,47 +641,67 @@ impl<K: ArtKey, V, const MAX_PARTIAL_LEN: usize> ArtNode<K, V, MAX_PARTIAL_LEN>
             }
         }
 
-        return matched_index;
+        matched_index
     }
 
-    pub(crate) fn remove(
+    /// Removes the leaf of the key and returns the stored key-value pair.
+    ///
+    /// The leaf is detached from the node holding it, so that the node shrinks instead of
+    /// keeping an empty child around. That node is the only one on the path losing a child:
+    /// a Node4 left with a single child is merged into it in the slot of its parent, so the
+    /// children count of every ancestor stays the same. Shrinking at the thresholds of
+    /// [from_sorted_children](ArtNode::from_sorted_children) thus leaves the tree in the
+    /// shape of a fresh build of the remaining keys.
+    pub(crate) fn remove<A: NodeAllocator>(
         node: &mut ArtNode<K, V, MAX_PARTIAL_LEN>,
+        alloc: &mut A,
         key: &[u8],
         depth: usize,
-    ) -> Option<V> {
+    ) -> Option<(K, V)> {
+        let mut node = node;
         let mut depth = depth;
-        let mut current: &mut ArtNode<K, V, MAX_PARTIAL_LEN> = node;
-        while !current.is_none() && !current.is_leaf() {
-            let header = current.header();
+        loop {
+            match node.as_mut() {
+                ArtNodeMut::None => return None,
+                ArtNodeMut::Leaf(leaf) => {
+                    return match leaf.matches(key) {
+                        true => node.take_leaf(alloc),
+                        false => None,
+                    }
+                }
+                _ => {}
+            }
+
+            let header = node.header();
             if header.partial.len > 0 {
-                let prefix_matched = current.check_prefix_match(key, depth);
+                let prefix_matched = node.check_prefix_match(key, depth);
                 if prefix_matched != min(MAX_PARTIAL_LEN, header.partial.len as usize) {
                     return None;
                 }
                 depth += header.partial.len as usize
             }
-            let next = current.get_mut_child(ArtKeyVerifier::valid(key, depth))?;
-            current = next;
-            depth = depth + 1;
-            // ref mut child
-        }
 
-        match current.as_mut() {
-            ArtNodeMut::None => None,
-            ArtNodeMut::Leaf(leaf) => {
-                return match leaf.matches(key) {
-                    true => {
-                        let mut child = current.remove_child(ArtKeyVerifier::valid(key, depth))?;
-                        child.take_leaf()
-                    }
-                    false => None,
-                }
+            let valid_key = ArtKeyVerifier::valid(key, depth);
+            let child = node.get_child(valid_key)?;
+            if !child.is_leaf() {
+                node = node.get_mut_child(valid_key).unwrap();
+                depth += 1;
+                continue;
             }
-            _ => unreachable!(),
+            if !child.static_cast_ref_leaf().matches(key) {
+                return None;
+            }
+
+            let mut child = node.remove_child(alloc, valid_key)?;
+            return child.take_leaf(alloc);
         }
     }
 
-    fn remove_child(&mut self, valid_key: (u8, bool)) -> Option<ArtNode<K, V, MAX_PARTIAL_LEN>> {
+    fn remove_child<A: NodeAllocator>(
+        &mut self,
+        alloc: &mut A,
+        valid_key: (u8, bool),
+    ) -> Option<ArtNode<K, V, MAX_PARTIAL_LEN>> {
         let removed_child = match self.as_mut() {
             ArtNodeMut::Node4(n4) => n4.remove_child(valid_key),
             ArtNodeMut::Node16(n16) => n16.remove_child(valid_key),
@@ -430,26 +711,26 @@ impl<K: ArtKey, V, const MAX_PARTIAL_LEN: usize> ArtNode<K, V, MAX_PARTIAL_LEN>
             _ => unreachable!(),
         };
 
-        self.shrink_to_fit();
+        self.shrink_to_fit(alloc);
         removed_child
     }
 
     /// shrink_to_fit
-    fn shrink_to_fit(&mut self) {
+    fn shrink_to_fit<A: NodeAllocator>(&mut self, alloc: &mut A) {
         if !self.is_few() {
             return;
         }
 
-        // let mut taken_node = std::mem::take(self);
-        let mut shrink_node = match self.as_mut() {
+        let shrink_node = match self.as_mut() {
             ArtNodeMut::Node4(n4) => n4.shrink_to_fit(), // This fucking ugly.
-            ArtNodeMut::Node16(n16) => ArtNode::node4(n16.shrink_to_fit()),
-            ArtNodeMut::Node48(n48) => ArtNode::node16(n48.shrink_to_fit()),
-            ArtNodeMut::Node256(n256) => ArtNode::node48(n256.shrink_to_fit()),
+            ArtNodeMut::Node16(n16) => ArtNode::node4(alloc, n16.shrink_to_fit()),
+            ArtNodeMut::Node48(n48) => ArtNode::node16(alloc, n48.shrink_to_fit()),
+            ArtNodeMut::Node256(n256) => ArtNode::node48(alloc, n256.shrink_to_fit()),
             _ => unreachable!(),
         };
 
-        std::mem::swap(self, &mut shrink_node);
+        // the children moved to the new node, the old one is freed alone.
+        std::mem::replace(self, shrink_node).release(alloc);
     }
 
     fn is_few(&self) -> bool {
@@ -468,46 +749,245 @@ impl<K: ArtKey, V, const MAX_PARTIAL_LEN: usize> ArtNode<K, V, MAX_PARTIAL_LEN>
         ArtNode(NODE_TYPE_NONE, PhantomData, PhantomData)
     }
 
-    pub(crate) fn node4(n4: Box<Node4<K, V, MAX_PARTIAL_LEN>>) -> ArtNode<K, V, MAX_PARTIAL_LEN> {
-        let ptr: *mut Node4<K, V, MAX_PARTIAL_LEN> = Box::into_raw(n4);
-        let ptr_usize = ptr as usize;
-        ArtNode(ptr_usize | NODE_TYPE_N4, PhantomData, PhantomData)
+    /// Builds an inner node at its final size from children sorted by key byte, the node
+    /// type is the smallest one holding all of them, as repeated inserts would grow it.
+    pub(crate) fn from_sorted_children<A: NodeAllocator>(
+        alloc: &mut A,
+        partial: Partial<MAX_PARTIAL_LEN>,
+        prefixed_child: ArtNode<K, V, MAX_PARTIAL_LEN>,
+        children: Vec<(u8, ArtNode<K, V, MAX_PARTIAL_LEN>)>,
+    ) -> ArtNode<K, V, MAX_PARTIAL_LEN> {
+        let header = Header {
+            partial,
+            non_null_children: children.len() as u16,
+        };
+        match children.len() {
+            0..=4 => {
+                let mut n4: Node4<K, V, MAX_PARTIAL_LEN> = Node4::default();
+                for (index, (byte, child)) in children.into_iter().enumerate() {
+                    n4.key[index] = byte;
+                    n4.children[index] = child;
+                }
+                n4.header = header;
+                n4.prefixed_child = prefixed_child;
+                ArtNode::node4(alloc, n4)
+            }
+            5..=16 => {
+                let mut n16: Node16<K, V, MAX_PARTIAL_LEN> = Node16::default();
+                for (index, (byte, child)) in children.into_iter().enumerate() {
+                    n16.key[index] = byte;
+                    n16.children[index] = child;
+                }
+                n16.header = header;
+                n16.prefixed_child = prefixed_child;
+                ArtNode::node16(alloc, n16)
+            }
+            17..=48 => {
+                let mut n48: Node48<K, V, MAX_PARTIAL_LEN> = Node48::default();
+                for (index, (byte, child)) in children.into_iter().enumerate() {
+                    n48.child_index[byte as usize] = index as u8;
+                    n48.children[index] = child;
+                }
+                n48.header = header;
+                n48.prefixed_child = prefixed_child;
+                ArtNode::node48(alloc, n48)
+            }
+            _ => {
+                let mut n256: Node256<K, V, MAX_PARTIAL_LEN> = Node256::default();
+                for (byte, child) in children {
+                    n256.children[byte as usize] = child;
+                }
+                n256.header = header;
+                n256.prefixed_child = prefixed_child;
+                ArtNode::node256(alloc, n256)
+            }
+        }
+    }
+
+    pub(crate) fn node4<A: NodeAllocator>(
+        alloc: &mut A,
+        n4: Node4<K, V, MAX_PARTIAL_LEN>,
+    ) -> ArtNode<K, V, MAX_PARTIAL_LEN> {
+        ArtNode::alloc_in(alloc, n4, NODE_TYPE_N4)
     }
 
-    pub(crate) fn node16(
-        n16: Box<Node16<K, V, MAX_PARTIAL_LEN>>,
+    pub(crate) fn node16<A: NodeAllocator>(
+        alloc: &mut A,
+        n16: Node16<K, V, MAX_PARTIAL_LEN>,
     ) -> ArtNode<K, V, MAX_PARTIAL_LEN> {
-        let ptr: *mut Node16<K, V, MAX_PARTIAL_LEN> = Box::into_raw(n16);
-        let ptr_usize = ptr as usize;
-        ArtNode(ptr_usize | NODE_TYPE_N16, PhantomData, PhantomData)
+        ArtNode::alloc_in(alloc, n16, NODE_TYPE_N16)
     }
 
-    pub(crate) fn node48(
-        n48: Box<Node48<K, V, MAX_PARTIAL_LEN>>,
+    pub(crate) fn node48<A: NodeAllocator>(
+        alloc: &mut A,
+        n48: Node48<K, V, MAX_PARTIAL_LEN>,
     ) -> ArtNode<K, V, MAX_PARTIAL_LEN> {
-        let ptr: *mut Node48<K, V, MAX_PARTIAL_LEN> = Box::into_raw(n48);
-        let ptr_usize = ptr as usize;
-        ArtNode(ptr_usize | NODE_TYPE_N48, PhantomData, PhantomData)
+        ArtNode::alloc_in(alloc, n48, NODE_TYPE_N48)
     }
 
-    pub(crate) fn node256(
-        n256: Box<Node256<K, V, MAX_PARTIAL_LEN>>,
+    pub(crate) fn node256<A: NodeAllocator>(
+        alloc: &mut A,
+        n256: Node256<K, V, MAX_PARTIAL_LEN>,
     ) -> ArtNode<K, V, MAX_PARTIAL_LEN> {
-        let ptr: *mut Node256<K, V, MAX_PARTIAL_LEN> = Box::into_raw(n256);
-        let ptr_usize = ptr as usize;
-        ArtNode(ptr_usize | NODE_TYPE_N256, PhantomData, PhantomData)
+        ArtNode::alloc_in(alloc, n256, NODE_TYPE_N256)
     }
 
-    pub(crate) fn leaf(key: K, val: V) -> ArtNode<K, V, MAX_PARTIAL_LEN> {
-        let leaf_ptr: *mut Leaf<K, V> = Box::into_raw(Box::new(Leaf::new(key, val)));
-        let leaf_ptr_usize = leaf_ptr as usize;
-        ArtNode(leaf_ptr_usize | NODE_TYPE_LEAF, PhantomData, PhantomData)
+    pub(crate) fn leaf<A: NodeAllocator>(
+        alloc: &mut A,
+        key: K,
+        val: V,
+    ) -> ArtNode<K, V, MAX_PARTIAL_LEN> {
+        ArtNode::alloc_in(alloc, Leaf::new(key, val), NODE_TYPE_LEAF)
     }
 
-    /// Safety: node never is leaf and none
-    fn minimum_child(node: &ArtNode<K, V, MAX_PARTIAL_LEN>) -> Option<&Leaf<K, V>> {
-        assert!(!node.is_none() && !node.is_leaf());
-        let mut node = &*node;
+    /// Moves a node or a leaf into a block of the allocator and tags its address.
+    fn alloc_in<T, A: NodeAllocator>(
+        alloc: &mut A,
+        value: T,
+        node_type: usize,
+    ) -> ArtNode<K, V, MAX_PARTIAL_LEN> {
+        let ptr = alloc.allocate(layout::<T>()).cast::<T>();
+        unsafe { ptr.as_ptr().write(value) };
+        ArtNode(ptr.as_ptr() as usize | node_type, PhantomData, PhantomData)
+    }
+
+    /// Moves the node or leaf out of its block and gives the block back to the allocator.
+    ///
+    /// **Safety**: `T` must be the type tagged in the node.
+    unsafe fn dealloc_into<T, A: NodeAllocator>(self, alloc: &mut A) -> T {
+        let ptr = (self.0 & NODE_PTR_MASK) as *mut T;
+        std::mem::forget(self);
+        let value = ptr.read();
+        alloc.deallocate(NonNull::new_unchecked(ptr.cast::<u8>()), layout::<T>());
+        value
+    }
+
+    /// Frees a single node, an inner node must have no children left.
+    fn release<A: NodeAllocator>(self, alloc: &mut A) {
+        unsafe {
+            match self.0 & NODE_TYPE_MASK {
+                NODE_TYPE_NONE => std::mem::forget(self),
+                NODE_TYPE_N4 => drop(self.dealloc_into::<Node4<K, V, MAX_PARTIAL_LEN>, A>(alloc)),
+                NODE_TYPE_N16 => drop(self.dealloc_into::<Node16<K, V, MAX_PARTIAL_LEN>, A>(alloc)),
+                NODE_TYPE_N48 => drop(self.dealloc_into::<Node48<K, V, MAX_PARTIAL_LEN>, A>(alloc)),
+                NODE_TYPE_N256 => {
+                    drop(self.dealloc_into::<Node256<K, V, MAX_PARTIAL_LEN>, A>(alloc))
+                }
+                NODE_TYPE_LEAF => drop(self.dealloc_into::<Leaf<K, V>, A>(alloc)),
+                _ => unreachable!(),
+            }
+        }
+    }
+
+    /// Frees the subtree, dropping the keys and values of its leaves.
+    ///
+    /// The nodes not freed yet are kept on a work stack instead of the call stack, so a
+    /// deep tree is freed in constant stack space.
+    pub(crate) fn free<A: NodeAllocator>(self, alloc: &mut A) {
+        let mut stack = vec![self];
+        while let Some(mut node) = stack.pop() {
+            if !node.is_none() && !node.is_leaf() {
+                node.move_children(&mut stack);
+            }
+            node.release(alloc);
+        }
+    }
+
+    /// Deep copy of the subtree, every inner node and leaf is copied into the allocator.
+    ///
+    /// Nodes are copied top-down with an explicit stack: an inner node is copied without its
+    /// children, which are filled in as they are copied. The copy is a tree at every step, so
+    /// it is freed if cloning a key or a value panics.
+    pub(crate) fn clone_in<A: NodeAllocator>(&self, alloc: &mut A) -> ArtNode<K, V, MAX_PARTIAL_LEN>
+    where
+        K: Clone,
+        V: Clone,
+    {
+        let mut copy = PartialCopy {
+            root: ArtNode::none(),
+            alloc,
+        };
+        // the slots of the copy waiting for the copy of a node, a slot does not move as
+        // nodes are never moved once allocated.
+        let root: *mut ArtNode<K, V, MAX_PARTIAL_LEN> = &mut copy.root;
+        let mut stack = vec![(self, root)];
+        while let Some((node, slot)) = stack.pop() {
+            let copied = match node.as_ref() {
+                ArtNodeRef::None => continue,
+                ArtNodeRef::Leaf(leaf) => {
+                    ArtNode::leaf(copy.alloc, leaf.key.clone(), leaf.val.clone())
+                }
+                ArtNodeRef::Node4(n4) => ArtNode::node4(
+                    copy.alloc,
+                    Node4 {
+                        header: n4.header,
+                        key: n4.key,
+                        ..Default::default()
+                    },
+                ),
+                ArtNodeRef::Node16(n16) => ArtNode::node16(
+                    copy.alloc,
+                    Node16 {
+                        header: n16.header,
+                        key: n16.key,
+                        ..Default::default()
+                    },
+                ),
+                ArtNodeRef::Node48(n48) => ArtNode::node48(
+                    copy.alloc,
+                    Node48 {
+                        header: n48.header,
+                        child_index: n48.child_index,
+                        ..Default::default()
+                    },
+                ),
+                ArtNodeRef::Node256(n256) => ArtNode::node256(
+                    copy.alloc,
+                    Node256 {
+                        header: n256.header,
+                        ..Default::default()
+                    },
+                ),
+            };
+
+            // Safety: the slot is the root of the copy or a child slot of a copied node,
+            // and nothing else refers to it.
+            let copied = unsafe {
+                *slot = copied;
+                &mut *slot
+            };
+            let (children, copies) = match (node.as_ref(), copied.as_mut()) {
+                (ArtNodeRef::Node4(n4), ArtNodeMut::Node4(c4)) => {
+                    stack.push((&n4.prefixed_child, &mut c4.prefixed_child));
+                    (&n4.children[..], &mut c4.children[..])
+                }
+                (ArtNodeRef::Node16(n16), ArtNodeMut::Node16(c16)) => {
+                    stack.push((&n16.prefixed_child, &mut c16.prefixed_child));
+                    (&n16.children[..], &mut c16.children[..])
+                }
+                (ArtNodeRef::Node48(n48), ArtNodeMut::Node48(c48)) => {
+                    stack.push((&n48.prefixed_child, &mut c48.prefixed_child));
+                    (&n48.children[..], &mut c48.children[..])
+                }
+                (ArtNodeRef::Node256(n256), ArtNodeMut::Node256(c256)) => {
+                    stack.push((&n256.prefixed_child, &mut c256.prefixed_child));
+                    (&n256.children[..], &mut c256.children[..])
+                }
+                (ArtNodeRef::Leaf(_), _) => continue,
+                _ => unreachable!(),
+            };
+            stack.extend(
+                children
+                    .iter()
+                    .zip(copies.iter_mut().map(|copy| copy as *mut _)),
+            );
+        }
+        std::mem::take(&mut copy.root)
+    }
+
+    /// Returns the leaf with the smallest key below the node, the node itself if it is a leaf.
+    pub(crate) fn minimum_child(node: &ArtNode<K, V, MAX_PARTIAL_LEN>) -> Option<&Leaf<K, V>> {
+        let mut node = node;
         while !node.is_none() && !node.is_leaf() {
             let child = match node.as_ref() {
                 ArtNodeRef::Node4(n4) => n4.minimum_child(),
@@ -526,6 +1006,27 @@ impl<K: ArtKey, V, const MAX_PARTIAL_LEN: usize> ArtNode<K, V, MAX_PARTIAL_LEN>
         }
     }
 
+    /// Returns the leaf with the largest key below the node, the node itself if it is a leaf.
+    pub(crate) fn maximum_child(node: &ArtNode<K, V, MAX_PARTIAL_LEN>) -> Option<&Leaf<K, V>> {
+        let mut node = node;
+        while !node.is_none() && !node.is_leaf() {
+            let child = match node.as_ref() {
+                ArtNodeRef::Node4(n4) => n4.maximum_child(),
+                ArtNodeRef::Node16(n16) => n16.maximum_child(),
+                ArtNodeRef::Node48(n48) => n48.maximum_child(),
+                ArtNodeRef::Node256(n256) => n256.maximum_child(),
+                _ => unreachable!(),
+            }?;
+            node = child;
+        }
+
+        match node.as_ref() {
+            ArtNodeRef::None => None,
+            ArtNodeRef::Leaf(leaf) => Some(leaf),
+            _ => unreachable!(),
+        }
+    }
+
     pub(crate) fn get_mut_child(
         &mut self,
         valid_key: (u8, bool),
@@ -546,9 +1047,15 @@ impl<K: ArtKey, V, const MAX_PARTIAL_LEN: usize> ArtNode<K, V, MAX_PARTIAL_LEN>
         self.0 & NODE_TYPE_MASK == NODE_TYPE_LEAF
     }
 
-    fn insert_child(&mut self, valid_key: (u8, bool), new_child: ArtNode<K, V, MAX_PARTIAL_LEN>) {
-        if self.is_full() {
-            self.grow()
+    fn insert_child<A: NodeAllocator>(
+        &mut self,
+        alloc: &mut A,
+        valid_key: (u8, bool),
+        new_child: ArtNode<K, V, MAX_PARTIAL_LEN>,
+    ) {
+        // the prefixed child has a slot of its own, it never needs a larger node.
+        if valid_key.1 && self.is_full() {
+            self.grow(alloc)
         }
 
         match self.as_mut() {
@@ -560,33 +1067,6 @@ impl<K: ArtKey, V, const MAX_PARTIAL_LEN: usize> ArtNode<K, V, MAX_PARTIAL_LEN>
         }
     }
 
-    fn assert_size(&self) {
-        debug_assert_eq!(
-            {
-                let children: &[ArtNode<K, V, MAX_PARTIAL_LEN>] = match self.as_ref() {
-                    ArtNodeRef::Node4(n4) => &n4.children,
-                    ArtNodeRef::Node16(n16) => &n16.children,
-                    ArtNodeRef::Node48(n48) => &n48.children,
-                    ArtNodeRef::Node256(n256) => &n256.children,
-                    _ => &[],
-                };
-
-                children.iter().filter(|child| !child.is_none()).count()
-            },
-            self.len()
-        )
-    }
-
-    fn len(&self) -> usize {
-        match self.as_ref() {
-            ArtNodeRef::Node4(n4) => n4.header.non_null_children as usize,
-            ArtNodeRef::Node16(n16) => n16.header.non_null_children as usize,
-            ArtNodeRef::Node48(n48) => n48.header.non_null_children as usize,
-            ArtNodeRef::Node256(n256) => n256.header.non_null_children as usize,
-            _ => 0,
-        }
-    }
-
     #[inline(always)]
     pub(crate) fn is_full(&self) -> bool {
         match self.as_ref() {
@@ -599,17 +1079,15 @@ impl<K: ArtKey, V, const MAX_PARTIAL_LEN: usize> ArtNode<K, V, MAX_PARTIAL_LEN>
     }
 
     #[inline]
-    fn grow(&mut self) {
-        // save old node header and taken it.
-        let mut taken_node = std::mem::take(self);
-        // start growing.
-        *self = match taken_node.as_mut() {
-            ArtNodeMut::Node4(n4) => ArtNode::node16(n4.grow()),
-            ArtNodeMut::Node16(n16) => ArtNode::node48(n16.grow()),
-            ArtNodeMut::Node48(n48) => ArtNode::node256(n48.grow()),
+    fn grow<A: NodeAllocator>(&mut self, alloc: &mut A) {
+        let grown = match self.as_mut() {
+            ArtNodeMut::Node4(n4) => ArtNode::node16(alloc, n4.grow()),
+            ArtNodeMut::Node16(n16) => ArtNode::node48(alloc, n16.grow()),
+            ArtNodeMut::Node48(n48) => ArtNode::node256(alloc, n48.grow()),
             _ => unreachable!(),
         };
-        // Note: taken_node drop here.
+        // the children moved to the grown node, the old one is freed alone.
+        std::mem::replace(self, grown).release(alloc);
     }
 
     pub(crate) fn header(&self) -> &Header<MAX_PARTIAL_LEN> {
@@ -649,7 +1127,7 @@ impl<K: ArtKey, V, const MAX_PARTIAL_LEN: usize> ArtNode<K, V, MAX_PARTIAL_LEN>
     ///         ret
     /// ```
     #[inline]
-    const fn static_cast_ref_leaf(&self) -> &Leaf<K, V> {
+    pub(crate) const fn static_cast_ref_leaf(&self) -> &Leaf<K, V> {
         match self.0 & NODE_TYPE_MASK {
             NODE_TYPE_LEAF => {
                 let leaf_ptr: *const Leaf<K, V> = (self.0 & NODE_PTR_MASK) as *const Leaf<K, V>;
@@ -672,14 +1150,68 @@ impl<K: ArtKey, V, const MAX_PARTIAL_LEN: usize> ArtNode<K, V, MAX_PARTIAL_LEN>
         }
     }
 
-    fn take_leaf(&mut self) -> Option<V> {
-        let ptr = self.0;
-        self.0 = 0;
-        match ptr & NODE_TYPE_MASK {
+    /// Returns a raw pointer to the leaf, the pointer is rebuilt from the tagged
+    /// address so it is not derived from the `&self` borrow.
+    ///
+    /// **Safety**: the caller must have exclusive access to the tree before
+    /// writing through the pointer.
+    #[inline]
+    pub(crate) fn leaf_ptr(&self) -> *mut Leaf<K, V> {
+        match self.0 & NODE_TYPE_MASK {
+            NODE_TYPE_LEAF => (self.0 & NODE_PTR_MASK) as *mut Leaf<K, V>,
+            _ => unreachable!(),
+        }
+    }
+
+    /// Moves the non-empty children out of an inner node in key order, the prefixed child
+    /// first. The node is left without children.
+    pub(crate) fn take_children(&mut self) -> Vec<ArtNode<K, V, MAX_PARTIAL_LEN>> {
+        let mut taken = Vec::with_capacity(self.slots());
+        self.move_children(&mut taken);
+        taken
+    }
+
+    /// Appends the non-empty children of an inner node to `taken`, as
+    /// [take_children](ArtNode::take_children) returns them.
+    fn move_children(&mut self, taken: &mut Vec<ArtNode<K, V, MAX_PARTIAL_LEN>>) {
+        let mut push = |child: &mut ArtNode<K, V, MAX_PARTIAL_LEN>| {
+            if !child.is_none() {
+                taken.push(std::mem::take(child));
+            }
+        };
+        match self.as_mut() {
+            ArtNodeMut::Node4(n4) => {
+                let count = n4.header.non_null_children as usize;
+                push(&mut n4.prefixed_child);
+                n4.children[..count].iter_mut().for_each(push);
+            }
+            ArtNodeMut::Node16(n16) => {
+                let count = n16.header.non_null_children as usize;
+                push(&mut n16.prefixed_child);
+                n16.children[..count].iter_mut().for_each(push);
+            }
+            ArtNodeMut::Node48(n48) => {
+                push(&mut n48.prefixed_child);
+                for byte in 0..=u8::MAX {
+                    if let Some(child) = n48.get_mut_child((byte, true)) {
+                        push(child);
+                    }
+                }
+            }
+            ArtNodeMut::Node256(n256) => {
+                push(&mut n256.prefixed_child);
+                n256.children.iter_mut().for_each(push);
+            }
+            _ => unreachable!(),
+        }
+        self.header_mut().non_null_children = 0;
+    }
+
+    pub(crate) fn take_leaf<A: NodeAllocator>(&mut self, alloc: &mut A) -> Option<(K, V)> {
+        match self.0 & NODE_TYPE_MASK {
             NODE_TYPE_LEAF => {
-                let leaf_ptr = (ptr & NODE_PTR_MASK) as *mut Leaf<K, V>;
-                let boxed = unsafe { Box::from_raw(leaf_ptr) };
-                Some(boxed.val)
+                let leaf = unsafe { std::mem::take(self).dealloc_into::<Leaf<K, V>, A>(alloc) };
+                Some((leaf.key, leaf.val))
             }
             _ => unreachable!(),
         }
@@ -762,32 +1294,45 @@ impl<K: ArtKey, V, const MAX_PARTIAL_LEN: usize> ArtNode<K, V, MAX_PARTIAL_LEN>
     }
 }
 
+/// Structural equality: both subtrees have the same node types, compressed paths and
+/// leaves, which is stricter than holding the same entries.
+impl<K: ArtKey + PartialEq, V: PartialEq, const MAX_PARTIAL_LEN: usize> PartialEq
+    for ArtNode<K, V, MAX_PARTIAL_LEN>
+{
+    fn eq(&self, other: &ArtNode<K, V, MAX_PARTIAL_LEN>) -> bool {
+        match (self.as_ref(), other.as_ref()) {
+            (ArtNodeRef::None, ArtNodeRef::None) => true,
+            (ArtNodeRef::Leaf(a), ArtNodeRef::Leaf(b)) => a.key == b.key && a.val == b.val,
+            (ArtNodeRef::Node4(a), ArtNodeRef::Node4(b)) => a == b,
+            (ArtNodeRef::Node16(a), ArtNodeRef::Node16(b)) => a == b,
+            (ArtNodeRef::Node48(a), ArtNodeRef::Node48(b)) => a == b,
+            (ArtNodeRef::Node256(a), ArtNodeRef::Node256(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+/// The copy built by [ArtNode::clone_in], freed if the copy is not finished.
+struct PartialCopy<'a, K: ArtKey, V, const MAX_PARTIAL_LEN: usize, A: NodeAllocator> {
+    root: ArtNode<K, V, MAX_PARTIAL_LEN>,
+    alloc: &'a mut A,
+}
+
+impl<K: ArtKey, V, const MAX_PARTIAL_LEN: usize, A: NodeAllocator> Drop
+    for PartialCopy<'_, K, V, MAX_PARTIAL_LEN, A>
+{
+    fn drop(&mut self) {
+        std::mem::take(&mut self.root).free(self.alloc);
+    }
+}
+
+/// Checks that only none is dropped, see [ArtNode] for who frees the other nodes.
 impl<K: ArtKey, V, const MAX_PARTIAL_LEN: usize> Drop for ArtNode<K, V, MAX_PARTIAL_LEN> {
     fn drop(&mut self) {
-        match self.0 & NODE_TYPE_MASK {
-            NODE_TYPE_NONE => {}
-            NODE_TYPE_N4 => {
-                let ptr = (self.0 & NODE_PTR_MASK) as *mut Node4<K, V, MAX_PARTIAL_LEN>;
-                drop(unsafe { Box::from_raw(ptr) });
-            }
-            NODE_TYPE_N16 => {
-                let ptr = (self.0 & NODE_PTR_MASK) as *mut Node16<K, V, MAX_PARTIAL_LEN>;
-                drop(unsafe { Box::from_raw(ptr) });
-            }
-            NODE_TYPE_N48 => {
-                let ptr = (self.0 & NODE_PTR_MASK) as *mut Node48<K, V, MAX_PARTIAL_LEN>;
-                drop(unsafe { Box::from_raw(ptr) });
-            }
-            NODE_TYPE_N256 => {
-                let ptr = (self.0 & NODE_PTR_MASK) as *mut Node256<K, V, MAX_PARTIAL_LEN>;
-                drop(unsafe { Box::from_raw(ptr) });
-            }
-            NODE_TYPE_LEAF => {
-                let ptr = (self.0 & NODE_PTR_MASK) as *mut Leaf<K, V>;
-                drop(unsafe { Box::from_raw(ptr) });
-            }
-            _ => unreachable!(),
-        }
+        assert!(
+            self.is_none() || std::thread::panicking(),
+            "a node is dropped without its allocator"
+        );
     }
 }
 
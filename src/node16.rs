@@ -3,14 +3,10 @@
 use crate::node::ArtNode;
 use crate::node4::Node4;
 use crate::node48::Node48;
+use crate::simd;
+use crate::validate::Violation;
 use crate::ArtKey;
 use crate::Header;
-#[cfg(target_arch = "aarch64")]
-use std::arch::aarch64::*;
-#[cfg(target_arch = "x86")]
-use std::arch::x86::*;
-#[cfg(target_arch = "x86_64")]
-use std::arch::x86_64::*;
 
 pub(crate) struct Node16<K: ArtKey, V, const MAX_PARTIAL_LEN: usize> {
     pub(crate) header: Header<MAX_PARTIAL_LEN>,
@@ -19,6 +15,19 @@ pub(crate) struct Node16<K: ArtKey, V, const MAX_PARTIAL_LEN: usize> {
     pub(crate) prefixed_child: ArtNode<K, V, MAX_PARTIAL_LEN>,
 }
 
+/// Structural equality, the keys and children past the children count are ignored.
+impl<K: ArtKey + PartialEq, V: PartialEq, const MAX_PARTIAL_LEN: usize> PartialEq
+    for Node16<K, V, MAX_PARTIAL_LEN>
+{
+    fn eq(&self, other: &Node16<K, V, MAX_PARTIAL_LEN>) -> bool {
+        let count = self.header.non_null_children as usize;
+        self.header == other.header
+            && self.prefixed_child == other.prefixed_child
+            && self.key[..count] == other.key[..count]
+            && self.children[..count] == other.children[..count]
+    }
+}
+
 impl<K: ArtKey, V, const MAX_PARTIAL_LEN: usize> Default for Node16<K, V, MAX_PARTIAL_LEN> {
     fn default() -> Node16<K, V, MAX_PARTIAL_LEN> {
         // Why dont' i use macro `vec![]` initialize the children?
@@ -60,52 +69,48 @@ impl<K: ArtKey, V, const MAX_PARTIAL_LEN: usize> Node16<K, V, MAX_PARTIAL_LEN> {
     #[inline(always)]
     pub(crate) fn minimum_child(&self) -> Option<&ArtNode<K, V, MAX_PARTIAL_LEN>> {
         if !self.prefixed_child.is_none() {
-            Some(&self.prefixed_child)
-        } else {
-            Some(&self.children[0])
+            return Some(&self.prefixed_child);
         }
+
+        let count = self.header.non_null_children as usize;
+        self.children[..count].iter().find(|child| !child.is_none())
     }
 
-    #[inline]
-    fn find_less_than_index(&mut self, key: u8) -> u16 {
-        let mask = (1 << self.header.non_null_children) - 1;
-        #[cfg(feature = "simd")]
-        #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
-        if is_x86_feature_detected!("sse2") {
-            unsafe {
-                let lt = _mm_cmplt_epi8(
-                    _mm_set1_epi8(std::mem::transmute::<u8, i8>(key)),
-                    _mm_loadu_si128(self.key.as_ptr() as *const __m128i),
-                );
-                let bit_fields = _mm_movemask_epi8(lt) & mask;
-                if bit_fields != 0 {
-                    return bit_fields.trailing_zeros() as u16;
-                }
+    pub(crate) fn maximum_child(&self) -> Option<&ArtNode<K, V, MAX_PARTIAL_LEN>> {
+        let count = self.header.non_null_children as usize;
+        self.children[..count]
+            .iter()
+            .rev()
+            .chain(std::iter::once(&self.prefixed_child))
+            .find(|child| !child.is_none())
+    }
 
-                return self.header.non_null_children;
-            }
-        }
+    /// Returns the number of slots walked by ordered iteration: slot 0 is the
+    /// prefixed child and slot `i + 1` is the child at `key[i]`.
+    #[inline(always)]
+    pub(crate) fn slots(&self) -> usize {
+        self.header.non_null_children as usize + 1
+    }
 
-        #[cfg(feature = "simd")]
-        #[cfg(any(target_arch = "aarch64", target_arch = "arm"))]
-        if std::arch::is_aarch64_feature_detected!("neon") {
-            unsafe {
-                let lt = vcltq_u8(vdupq_n_u8(key), vld1q_u8(self.key.as_ptr()));
-                let bit_fields = _mm_movemask_epi8(lt) & mask;
-                if bit_fields != 0 {
-                    return bit_fields.trailing_zeros() as u16;
-                }
+    #[inline]
+    pub(crate) fn child_at(&self, slot: usize) -> Option<&ArtNode<K, V, MAX_PARTIAL_LEN>> {
+        let child = match slot {
+            0 => &self.prefixed_child,
+            _ => &self.children[slot - 1],
+        };
 
-                return self.header.non_null_children;
-            }
-        }
+        (!child.is_none()).then_some(child)
+    }
 
-        let mut index = 0;
-        while (index < self.header.non_null_children) && self.key[index as usize] < key {
-            index += 1;
-        }
+    /// Returns the first slot whose key byte is greater than or equal to `byte`.
+    #[inline]
+    pub(crate) fn slot_of(&self, byte: u8) -> usize {
+        simd::lower_bound(&self.key, self.header.non_null_children as usize, byte) + 1
+    }
 
-        index
+    #[inline]
+    fn find_less_than_index(&mut self, key: u8) -> u16 {
+        simd::lower_bound(&self.key, self.header.non_null_children as usize, key) as u16
     }
 
     /// Safety: grow.
@@ -114,12 +119,12 @@ impl<K: ArtKey, V, const MAX_PARTIAL_LEN: usize> Node16<K, V, MAX_PARTIAL_LEN> {
         valid_key: (u8, bool),
         mut new_child: ArtNode<K, V, MAX_PARTIAL_LEN>,
     ) {
-        assert!(self.header.non_null_children < 16);
         if !valid_key.1 {
-            assert!(self.prefixed_child.is_none());
+            debug_assert!(self.prefixed_child.is_none());
             std::mem::swap(&mut self.prefixed_child, &mut new_child);
             return;
         }
+        debug_assert!(self.header.non_null_children < 16);
 
         let index = self.find_less_than_index(valid_key.0);
         if !self.children[index as usize].is_none() {
@@ -161,51 +166,12 @@ impl<K: ArtKey, V, const MAX_PARTIAL_LEN: usize> Node16<K, V, MAX_PARTIAL_LEN> {
 
     #[inline]
     fn find_child_index(&self, key: u8) -> Option<usize> {
-        // simd is preferred
-        #[cfg(feature = "simd")]
-        #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
-        if is_x86_feature_detected!("sse2") {
-            unsafe {
-                // vectorized node16 keys.
-                // create selection mask by search byte
-                let mask = _mm_set1_epi8(std::mem::transmute::<u8, i8>(key));
-                // if byte in node16, where all elem in simd_keys that are equal to simd_mask equal
-                // one, else 0.
-                // vectorized node16 keys.
-                let cmp =
-                    _mm_cmpeq_epi8(mask, _mm_loadu_si128(self.key.as_ptr() as *const __m128i));
-                return match _mm_movemask_epi8(cmp) & ((1 << self.header.non_null_children) - 1) {
-                    0 => None,
-                    bit_fields => Some(bit_fields.trailing_zeros() as usize),
-                };
-            };
-        }
-
-        #[cfg(feature = "simd")]
-        #[cfg(any(target_arch = "aarch64", target_arch = "arm"))]
-        if std::arch::is_aarch64_feature_detected!("neon") {
-            unsafe {
-                let simd_keys = vld1q_u8(self.key.as_ptr());
-                let simd_mask = vdupq_n_u8(key);
-                let eq = vceqq_u8(simd_mask, simd_keys);
-                let mask = (1 << self.header.non_null_children) - 1;
-                let bit_fields = _mm_movemask_epi8(eq) & mask;
-                if bit_fields == 0 {
-                    return None;
-                }
-                return Some(bit_fields.trailing_zeros() as usize);
-            }
-        }
-        // slow path, binary search used.
-        match self.key[0..self.header.non_null_children as usize].binary_search(&key) {
-            Ok(idx) => Some(idx),
-            Err(_) => None,
-        }
+        simd::find_key(&self.key, self.header.non_null_children as usize, key)
     }
 
     #[inline(always)]
-    pub(crate) fn grow(&mut self) -> Box<Node48<K, V, MAX_PARTIAL_LEN>> {
-        let mut node48: Box<Node48<K, V, MAX_PARTIAL_LEN>> = Box::default();
+    pub(crate) fn grow(&mut self) -> Node48<K, V, MAX_PARTIAL_LEN> {
+        let mut node48: Node48<K, V, MAX_PARTIAL_LEN> = Node48::default();
         // copy invalid child
         std::mem::swap(&mut self.prefixed_child, &mut node48.prefixed_child);
         // copy children and key
@@ -233,7 +199,7 @@ impl<K: ArtKey, V, const MAX_PARTIAL_LEN: usize> Node16<K, V, MAX_PARTIAL_LEN> {
         valid_key: (u8, bool),
     ) -> Option<ArtNode<K, V, MAX_PARTIAL_LEN>> {
         if !valid_key.1 {
-            assert_eq!(self.prefixed_child.is_none(), false);
+            assert!(!self.prefixed_child.is_none());
             return Some(std::mem::take(&mut self.prefixed_child));
         }
 
@@ -263,8 +229,8 @@ impl<K: ArtKey, V, const MAX_PARTIAL_LEN: usize> Node16<K, V, MAX_PARTIAL_LEN> {
         Some(child)
     }
 
-    pub(crate) fn shrink_to_fit(&mut self) -> Box<Node4<K, V, MAX_PARTIAL_LEN>> {
-        let mut node4: Box<Node4<K, V, MAX_PARTIAL_LEN>> = Box::default();
+    pub(crate) fn shrink_to_fit(&mut self) -> Node4<K, V, MAX_PARTIAL_LEN> {
+        let mut node4: Node4<K, V, MAX_PARTIAL_LEN> = Node4::default();
         let mut node4_index = 0;
         for i in 0..self.header.non_null_children as usize {
             std::mem::swap(&mut self.children[i], &mut node4.children[node4_index]);
@@ -278,27 +244,28 @@ impl<K: ArtKey, V, const MAX_PARTIAL_LEN: usize> Node16<K, V, MAX_PARTIAL_LEN> {
         node4.header.non_null_children = node4_index as u16;
         node4
     }
-}
 
-#[inline]
-#[cfg(feature = "simd")]
-#[cfg(any(target_arch = "aarch64", target_arch = "arm"))]
-unsafe fn _mm_movemask_epi8(input: uint8x16_t) -> i32 {
-    // Example input (half scale):
-    // 0x89 FF 1D C0 00 10 99 33
-    // Shift out everything but the sign bits
-    // 0x01 01 00 01 00 00 01 00
-    let high_bits = vreinterpretq_u16_u8(vshrq_n_u8::<7>(input));
-    // Merge the even lanes together with vsra. The '??' bytes are garbage.
-    // vsri could also be used, but it is slightly slower on aarch64.
-    // 0x??03 ??02 ??00 ??01
-    let paired16 = vreinterpretq_u32_u16(vsraq_n_u16::<7>(high_bits, high_bits));
-    // Repeat with wider lanes.
-    // 0x??????0B ??????04
-    let paired32 = vreinterpretq_u64_u32(vsraq_n_u32::<14>(paired16, paired16));
-    // 0x??????????????4B
-    let paired64 = vreinterpretq_u8_u64(vsraq_n_u64::<28>(paired32, paired32));
-    // Extract the low 8 bits from each lane and join.
-    // 0x4B
-    vgetq_lane_u8::<0>(paired64) as i32 | (vgetq_lane_u8::<8>(paired64) as i32) << 8
+    /// Checks that the children count matches the children, and that the key bytes of the
+    /// children are strictly increasing.
+    pub(crate) fn check(&self) -> Result<(), Violation> {
+        let count = self.header.non_null_children as usize;
+        let (counted, rest) = self.children.split_at(count.min(16));
+        let found = counted.iter().filter(|child| !child.is_none()).count();
+        if found != count {
+            return Err(Violation::ChildCount {
+                header: count,
+                found,
+            });
+        }
+        if rest.iter().any(|child| !child.is_none()) {
+            return Err(Violation::StrayChild);
+        }
+        if self.key[..counted.len()]
+            .windows(2)
+            .any(|pair| pair[0] >= pair[1])
+        {
+            return Err(Violation::UnsortedKeys);
+        }
+        Ok(())
+    }
 }
@@ -1,5 +1,7 @@
 use crate::node::ArtNode;
 use crate::node48::Node48;
+use crate::simd;
+use crate::validate::Violation;
 use crate::ArtKey;
 use crate::Header;
 
@@ -11,6 +13,16 @@ pub(crate) struct Node256<K: ArtKey, V, const MAX_PARTIAL_LEN: usize> {
     pub(crate) prefixed_child: ArtNode<K, V, MAX_PARTIAL_LEN>,
 }
 
+impl<K: ArtKey + PartialEq, V: PartialEq, const MAX_PARTIAL_LEN: usize> PartialEq
+    for Node256<K, V, MAX_PARTIAL_LEN>
+{
+    fn eq(&self, other: &Node256<K, V, MAX_PARTIAL_LEN>) -> bool {
+        self.header == other.header
+            && self.prefixed_child == other.prefixed_child
+            && self.children == other.children
+    }
+}
+
 impl<K: ArtKey, V, const MAX_PARTIAL_LEN: usize> Default for Node256<K, V, MAX_PARTIAL_LEN> {
     fn default() -> Node256<K, V, MAX_PARTIAL_LEN> {
         // Why dont' i use macro `vec![]` initialize the children?
@@ -301,13 +313,48 @@ impl<K: ArtKey, V, const MAX_PARTIAL_LEN: usize> Node256<K, V, MAX_PARTIAL_LEN>
         if !self.prefixed_child.is_none() {
             return Some(&self.prefixed_child);
         }
-        // TODO: simd split
-        for node in self.children.iter() {
-            if !node.is_none() {
-                return Some(node);
-            }
+        let byte = simd::first_occupied(self.words())?;
+        Some(&self.children[byte])
+    }
+
+    pub(crate) fn maximum_child(&self) -> Option<&ArtNode<K, V, MAX_PARTIAL_LEN>> {
+        match simd::last_occupied(self.words()) {
+            Some(byte) => Some(&self.children[byte]),
+            None => (!self.prefixed_child.is_none()).then_some(&self.prefixed_child),
+        }
+    }
+
+    /// Returns the children as their tagged pointers, an empty child is zero.
+    #[inline(always)]
+    fn words(&self) -> &[usize; 256] {
+        // ArtNode is a transparent wrapper of its tagged pointer.
+        unsafe {
+            &*(&self.children as *const [ArtNode<K, V, MAX_PARTIAL_LEN>; 256]
+                as *const [usize; 256])
         }
-        return None;
+    }
+
+    /// Returns the number of slots walked by ordered iteration: slot 0 is the
+    /// prefixed child and slot `byte + 1` is the child of the key byte.
+    #[inline(always)]
+    pub(crate) fn slots(&self) -> usize {
+        257
+    }
+
+    #[inline]
+    pub(crate) fn child_at(&self, slot: usize) -> Option<&ArtNode<K, V, MAX_PARTIAL_LEN>> {
+        let child = match slot {
+            0 => &self.prefixed_child,
+            _ => &self.children[slot - 1],
+        };
+
+        (!child.is_none()).then_some(child)
+    }
+
+    /// Returns the first slot whose key byte is greater than or equal to `byte`.
+    #[inline(always)]
+    pub(crate) fn slot_of(&self, byte: u8) -> usize {
+        byte as usize + 1
     }
 
     #[inline]
@@ -316,7 +363,7 @@ impl<K: ArtKey, V, const MAX_PARTIAL_LEN: usize> Node256<K, V, MAX_PARTIAL_LEN>
             return Some(&self.prefixed_child);
         }
 
-        return Some(&self.children[key.0 as usize]);
+        Some(&self.children[key.0 as usize])
     }
 
     pub(crate) fn get_mut_child(
@@ -327,7 +374,7 @@ impl<K: ArtKey, V, const MAX_PARTIAL_LEN: usize> Node256<K, V, MAX_PARTIAL_LEN>
             return Some(&mut self.prefixed_child);
         }
 
-        return Some(&mut self.children[key.0 as usize]);
+        Some(&mut self.children[key.0 as usize])
     }
 
     /// Safety: grow.
@@ -336,28 +383,21 @@ impl<K: ArtKey, V, const MAX_PARTIAL_LEN: usize> Node256<K, V, MAX_PARTIAL_LEN>
         key: (u8, bool),
         mut new_child: ArtNode<K, V, MAX_PARTIAL_LEN>,
     ) {
-        assert_eq!(self.header.non_null_children, 48);
-        let mut assert_cnt = 0;
-        for child in self.children.iter() {
-            if !child.is_none() {
-                assert_cnt += 1;
-            }
-        }
-        assert_eq!(assert_cnt, self.header.non_null_children);
-
         if !key.1 {
-            assert!(self.prefixed_child.is_none());
+            debug_assert!(self.prefixed_child.is_none());
             std::mem::swap(&mut self.prefixed_child, &mut new_child);
             return;
         }
+
+        debug_assert!(self.children[key.0 as usize].is_none());
         self.header.non_null_children += 1;
         std::mem::swap(&mut self.children[key.0 as usize], &mut new_child);
-        assert_eq!(new_child.is_none(), true);
-        assert_eq!(self.children[key.0 as usize].is_none(), false);
     }
 
+    /// A Node256 shrinks as soon as its children fit a Node48, the type a fresh build
+    /// would give them.
     pub fn is_few(&self) -> bool {
-        self.header.non_null_children < 48
+        self.header.non_null_children <= 48
     }
 
     pub(crate) fn remove_child(
@@ -373,8 +413,8 @@ impl<K: ArtKey, V, const MAX_PARTIAL_LEN: usize> Node256<K, V, MAX_PARTIAL_LEN>
         Some(std::mem::take(&mut self.children[valid_key.0 as usize]))
     }
 
-    pub(crate) fn shrink_to_fit(&mut self) -> Box<Node48<K, V, MAX_PARTIAL_LEN>> {
-        let mut node48: Box<Node48<K, V, MAX_PARTIAL_LEN>> = Box::default();
+    pub(crate) fn shrink_to_fit(&mut self) -> Node48<K, V, MAX_PARTIAL_LEN> {
+        let mut node48: Node48<K, V, MAX_PARTIAL_LEN> = Node48::default();
         let mut node48_index = 0;
 
         for (idx, child) in self.children.iter_mut().enumerate() {
@@ -391,4 +431,21 @@ impl<K: ArtKey, V, const MAX_PARTIAL_LEN: usize> Node256<K, V, MAX_PARTIAL_LEN>
         node48.header.non_null_children = node48_index as u16;
         node48
     }
+
+    /// Checks that the children count matches the non-empty children.
+    pub(crate) fn check(&self) -> Result<(), Violation> {
+        let count = self.header.non_null_children as usize;
+        let found = self
+            .children
+            .iter()
+            .filter(|child| !child.is_none())
+            .count();
+        if found != count {
+            return Err(Violation::ChildCount {
+                header: count,
+                found,
+            });
+        }
+        Ok(())
+    }
 }
@@ -1,5 +1,6 @@
 use crate::node::ArtNode;
 use crate::node16::Node16;
+use crate::validate::Violation;
 use crate::ArtKey;
 use crate::Header;
 
@@ -10,6 +11,19 @@ pub(crate) struct Node4<K: ArtKey, V, const MAX_PARTIAL_LEN: usize> {
     pub(crate) prefixed_child: ArtNode<K, V, MAX_PARTIAL_LEN>,
 }
 
+/// Structural equality, the keys and children past the children count are ignored.
+impl<K: ArtKey + PartialEq, V: PartialEq, const MAX_PARTIAL_LEN: usize> PartialEq
+    for Node4<K, V, MAX_PARTIAL_LEN>
+{
+    fn eq(&self, other: &Node4<K, V, MAX_PARTIAL_LEN>) -> bool {
+        let count = self.header.non_null_children as usize;
+        self.header == other.header
+            && self.prefixed_child == other.prefixed_child
+            && self.key[..count] == other.key[..count]
+            && self.children[..count] == other.children[..count]
+    }
+}
+
 impl<K: ArtKey, V, const MAX_PARTIAL_LEN: usize> Default for Node4<K, V, MAX_PARTIAL_LEN> {
     fn default() -> Node4<K, V, MAX_PARTIAL_LEN> {
         Node4 {
@@ -35,10 +49,44 @@ impl<K: ArtKey, V, const MAX_PARTIAL_LEN: usize> Node4<K, V, MAX_PARTIAL_LEN> {
     #[inline(always)]
     pub(crate) fn minimum_child(&self) -> Option<&ArtNode<K, V, MAX_PARTIAL_LEN>> {
         if !self.prefixed_child.is_none() {
-            Some(&self.prefixed_child)
-        } else {
-            Some(&self.children[0])
+            return Some(&self.prefixed_child);
         }
+
+        let count = self.header.non_null_children as usize;
+        self.children[..count].iter().find(|child| !child.is_none())
+    }
+
+    pub(crate) fn maximum_child(&self) -> Option<&ArtNode<K, V, MAX_PARTIAL_LEN>> {
+        let count = self.header.non_null_children as usize;
+        self.children[..count]
+            .iter()
+            .rev()
+            .chain(std::iter::once(&self.prefixed_child))
+            .find(|child| !child.is_none())
+    }
+
+    /// Returns the number of slots walked by ordered iteration: slot 0 is the
+    /// prefixed child and slot `i + 1` is the child at `key[i]`.
+    #[inline(always)]
+    pub(crate) fn slots(&self) -> usize {
+        self.header.non_null_children as usize + 1
+    }
+
+    #[inline]
+    pub(crate) fn child_at(&self, slot: usize) -> Option<&ArtNode<K, V, MAX_PARTIAL_LEN>> {
+        let child = match slot {
+            0 => &self.prefixed_child,
+            _ => &self.children[slot - 1],
+        };
+
+        (!child.is_none()).then_some(child)
+    }
+
+    /// Returns the first slot whose key byte is greater than or equal to `byte`.
+    #[inline]
+    pub(crate) fn slot_of(&self, byte: u8) -> usize {
+        let keys = &self.key[..self.header.non_null_children as usize];
+        keys.iter().take_while(|key| **key < byte).count() + 1
     }
 
     #[inline]
@@ -67,11 +115,11 @@ impl<K: ArtKey, V, const MAX_PARTIAL_LEN: usize> Node4<K, V, MAX_PARTIAL_LEN> {
         mut new_child: ArtNode<K, V, MAX_PARTIAL_LEN>,
     ) {
         if !valid_key.1 {
-            assert!(self.prefixed_child.is_none());
+            debug_assert!(self.prefixed_child.is_none());
             std::mem::swap(&mut self.prefixed_child, &mut new_child);
             return;
         }
-        assert!(self.header.non_null_children < 4);
+        debug_assert!(self.header.non_null_children < 4);
 
         let key_byte = valid_key.0;
         // find first index greater than or equal to key_byte
@@ -92,18 +140,13 @@ impl<K: ArtKey, V, const MAX_PARTIAL_LEN: usize> Node4<K, V, MAX_PARTIAL_LEN> {
 
         self.key[index as usize] = key_byte;
         std::mem::swap(&mut self.children[index as usize], &mut new_child);
-
-        if !new_child.is_none() && !new_child.is_leaf() {
-            assert_eq!(0, new_child.header().non_null_children);
-        }
-
         self.header.non_null_children += 1;
     }
 
     #[inline(always)]
-    pub(crate) fn grow(&mut self) -> Box<Node16<K, V, MAX_PARTIAL_LEN>> {
+    pub(crate) fn grow(&mut self) -> Node16<K, V, MAX_PARTIAL_LEN> {
         assert_eq!(self.header.non_null_children, 4);
-        let mut node16: Box<Node16<K, V, MAX_PARTIAL_LEN>> = Box::default();
+        let mut node16: Node16<K, V, MAX_PARTIAL_LEN> = Node16::default();
         // copy invalid child
         std::mem::swap(&mut self.prefixed_child, &mut node16.prefixed_child);
         // copy child
@@ -136,7 +179,7 @@ impl<K: ArtKey, V, const MAX_PARTIAL_LEN: usize> Node4<K, V, MAX_PARTIAL_LEN> {
         valid_key: (u8, bool),
     ) -> Option<ArtNode<K, V, MAX_PARTIAL_LEN>> {
         if !valid_key.1 {
-            assert_eq!(self.prefixed_child.is_none(), false);
+            assert!(!self.prefixed_child.is_none());
             return Some(std::mem::take(&mut self.prefixed_child));
         }
 
@@ -179,7 +222,7 @@ impl<K: ArtKey, V, const MAX_PARTIAL_LEN: usize> Node4<K, V, MAX_PARTIAL_LEN> {
     pub(crate) fn shrink_to_fit(&mut self) -> ArtNode<K, V, MAX_PARTIAL_LEN> {
         let mut single_child = std::mem::take(&mut self.children[0]);
         if single_child.is_none() {
-            assert_eq!(self.prefixed_child.is_none(), false);
+            assert!(!self.prefixed_child.is_none());
             single_child = std::mem::take(&mut self.prefixed_child);
         }
 
@@ -210,8 +253,32 @@ impl<K: ArtKey, V, const MAX_PARTIAL_LEN: usize> Node4<K, V, MAX_PARTIAL_LEN> {
 
         let minimum = std::cmp::min(prefix_len, MAX_PARTIAL_LEN);
         header.partial.data[..minimum].copy_from_slice(&self.header.partial.data[..minimum]);
-        header.partial.len += (self.header.partial.len + 1) as u32;
+        header.partial.len += self.header.partial.len + 1;
         std::mem::swap(single_child.header_mut(), &mut header);
         single_child
     }
+
+    /// Checks that the children count matches the children, and that the key bytes of the
+    /// children are strictly increasing.
+    pub(crate) fn check(&self) -> Result<(), Violation> {
+        let count = self.header.non_null_children as usize;
+        let (counted, rest) = self.children.split_at(count.min(4));
+        let found = counted.iter().filter(|child| !child.is_none()).count();
+        if found != count {
+            return Err(Violation::ChildCount {
+                header: count,
+                found,
+            });
+        }
+        if rest.iter().any(|child| !child.is_none()) {
+            return Err(Violation::StrayChild);
+        }
+        if self.key[..counted.len()]
+            .windows(2)
+            .any(|pair| pair[0] >= pair[1])
+        {
+            return Err(Violation::UnsortedKeys);
+        }
+        Ok(())
+    }
 }
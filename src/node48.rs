@@ -1,6 +1,8 @@
 use crate::node::ArtNode;
 use crate::node16::Node16;
 use crate::node256::Node256;
+use crate::simd;
+use crate::validate::Violation;
 use crate::ArtKey;
 use crate::Header;
 
@@ -13,6 +15,18 @@ pub(crate) struct Node48<K: ArtKey, V, const MAX_PARTIAL_LEN: usize> {
     pub(crate) prefixed_child: ArtNode<K, V, MAX_PARTIAL_LEN>,
 }
 
+/// Structural equality, children are compared by key byte rather than by their position
+/// in `children`.
+impl<K: ArtKey + PartialEq, V: PartialEq, const MAX_PARTIAL_LEN: usize> PartialEq
+    for Node48<K, V, MAX_PARTIAL_LEN>
+{
+    fn eq(&self, other: &Node48<K, V, MAX_PARTIAL_LEN>) -> bool {
+        self.header == other.header
+            && self.prefixed_child == other.prefixed_child
+            && (1..self.slots()).all(|slot| self.child_at(slot) == other.child_at(slot))
+    }
+}
+
 impl<K: ArtKey, V, const MAX_PARTIAL_LEN: usize> Default for Node48<K, V, MAX_PARTIAL_LEN> {
     fn default() -> Node48<K, V, MAX_PARTIAL_LEN> {
         Node48 {
@@ -85,13 +99,38 @@ impl<K: ArtKey, V, const MAX_PARTIAL_LEN: usize> Node48<K, V, MAX_PARTIAL_LEN> {
             return Some(&self.prefixed_child);
         }
 
-        // TODO: simd split
-        for i in 0..256 {
-            if self.child_index[i] != EMPTY_INDEX {
-                return Some(&self.children[i]);
-            }
+        let byte = simd::first_indexed(&self.child_index, EMPTY_INDEX)?;
+        Some(&self.children[self.child_index[byte] as usize])
+    }
+
+    pub(crate) fn maximum_child(&self) -> Option<&ArtNode<K, V, MAX_PARTIAL_LEN>> {
+        match simd::last_indexed(&self.child_index, EMPTY_INDEX) {
+            Some(byte) => Some(&self.children[self.child_index[byte] as usize]),
+            None => (!self.prefixed_child.is_none()).then_some(&self.prefixed_child),
         }
-        return None;
+    }
+
+    /// Returns the number of slots walked by ordered iteration: slot 0 is the
+    /// prefixed child and slot `byte + 1` is the child of the key byte.
+    #[inline(always)]
+    pub(crate) fn slots(&self) -> usize {
+        257
+    }
+
+    #[inline]
+    pub(crate) fn child_at(&self, slot: usize) -> Option<&ArtNode<K, V, MAX_PARTIAL_LEN>> {
+        let child = match slot {
+            0 => &self.prefixed_child,
+            _ => &self.children[self.find_child_index((slot - 1) as u8)?],
+        };
+
+        (!child.is_none()).then_some(child)
+    }
+
+    /// Returns the first slot whose key byte is greater than or equal to `byte`.
+    #[inline(always)]
+    pub(crate) fn slot_of(&self, byte: u8) -> usize {
+        byte as usize + 1
     }
 
     #[inline]
@@ -131,12 +170,12 @@ impl<K: ArtKey, V, const MAX_PARTIAL_LEN: usize> Node48<K, V, MAX_PARTIAL_LEN> {
         mut new_child: ArtNode<K, V, MAX_PARTIAL_LEN>,
     ) {
         if !key.1 {
-            assert!(self.prefixed_child.is_none());
+            debug_assert!(self.prefixed_child.is_none());
             std::mem::swap(&mut self.prefixed_child, &mut new_child);
             return;
         }
 
-        assert!(self.header.non_null_children < 48);
+        debug_assert!(self.header.non_null_children < 48);
         let mut pos = self.header.non_null_children as usize;
         // When the next position is none, we should preferentially
         // attempt to insert at the next position.
@@ -148,19 +187,17 @@ impl<K: ArtKey, V, const MAX_PARTIAL_LEN: usize> Node48<K, V, MAX_PARTIAL_LEN> {
         }
 
         std::mem::swap(&mut self.children[pos], &mut new_child);
-        assert_eq!(self.children[pos].is_none(), false);
-        assert_eq!(new_child.is_none(), true);
         self.child_index[key.0 as usize] = pos as u8;
         self.header.non_null_children += 1;
     }
 
     /// Grow node48 to node256.
     #[inline(always)]
-    pub(crate) fn grow(&mut self) -> Box<Node256<K, V, MAX_PARTIAL_LEN>> {
-        let mut node256: Box<Node256<K, V, MAX_PARTIAL_LEN>> = Box::default();
+    pub(crate) fn grow(&mut self) -> Node256<K, V, MAX_PARTIAL_LEN> {
+        let mut node256: Node256<K, V, MAX_PARTIAL_LEN> = Node256::default();
         for (byte, index) in self.child_index.iter_mut().enumerate() {
             if *index != EMPTY_INDEX {
-                assert_eq!(self.children[*index as usize].is_none(), false);
+                assert!(!self.children[*index as usize].is_none());
                 std::mem::swap(
                     &mut node256.children[byte],
                     &mut self.children[*index as usize],
@@ -189,15 +226,15 @@ impl<K: ArtKey, V, const MAX_PARTIAL_LEN: usize> Node48<K, V, MAX_PARTIAL_LEN> {
         }
 
         let index = self.find_child_index(valid_key.0)?;
-        assert_eq!(self.children[index].is_none(), false);
+        assert!(!self.children[index].is_none());
         let removed = std::mem::take(&mut self.children[index]);
         self.child_index[valid_key.0 as usize] = EMPTY_INDEX;
         self.header.non_null_children -= 1;
-        return Some(removed);
+        Some(removed)
     }
 
-    pub(crate) fn shrink_to_fit(&mut self) -> Box<Node16<K, V, MAX_PARTIAL_LEN>> {
-        let mut node16: Box<Node16<K, V, MAX_PARTIAL_LEN>> = Box::default();
+    pub(crate) fn shrink_to_fit(&mut self) -> Node16<K, V, MAX_PARTIAL_LEN> {
+        let mut node16: Node16<K, V, MAX_PARTIAL_LEN> = Node16::default();
         let mut node16_index = 0;
         for idx in 0..256 {
             if self.child_index[idx] != EMPTY_INDEX {
@@ -216,4 +253,39 @@ impl<K: ArtKey, V, const MAX_PARTIAL_LEN: usize> Node48<K, V, MAX_PARTIAL_LEN> {
         node16.header.non_null_children = node16_index as u16;
         node16
     }
+
+    /// Checks that every indexed key byte points to a non-empty child of its own, and that
+    /// the children count matches the indexed children.
+    pub(crate) fn check(&self) -> Result<(), Violation> {
+        let mut indexed = [false; 48];
+        for (byte, index) in self.child_index.iter().enumerate() {
+            let index = *index as usize;
+            if index == EMPTY_INDEX as usize {
+                continue;
+            }
+            if index >= 48 || indexed[index] || self.children[index].is_none() {
+                return Err(Violation::BadIndex { byte: byte as u8 });
+            }
+            indexed[index] = true;
+        }
+
+        let count = self.header.non_null_children as usize;
+        let found = indexed.iter().filter(|indexed| **indexed).count();
+        if found != count {
+            return Err(Violation::ChildCount {
+                header: count,
+                found,
+            });
+        }
+        if self
+            .children
+            .iter()
+            .filter(|child| !child.is_none())
+            .count()
+            != found
+        {
+            return Err(Violation::StrayChild);
+        }
+        Ok(())
+    }
 }
@@ -0,0 +1,662 @@
+//! A persistent adaptive radix tree, where every update returns a new tree.
+//!
+//! Children are reference counted, so an update copies only the inner nodes on the path
+//! to its key and shares every other subtree with the tree it started from.
+
+use std::borrow::Borrow;
+use std::fmt::{self, Debug};
+use std::sync::Arc;
+
+use crate::leaf::Leaf;
+use crate::ArtKey;
+
+const EMPTY_INDEX: u8 = u8::MAX;
+
+enum Node<K: ArtKey, V> {
+    Leaf(Leaf<K, V>),
+    Inner(Inner<K, V>),
+}
+
+struct Inner<K: ArtKey, V> {
+    /// The compressed path, stored in full.
+    prefix: Box<[u8]>,
+    /// The leaf of the key that ends at this node.
+    prefixed_child: Option<Arc<Node<K, V>>>,
+    children: Children<K, V>,
+}
+
+/// The children of an inner node, in the smallest node type that holds them. Node48 and
+/// Node256 are boxed so that leaves and small nodes are not as large as them.
+enum Children<K: ArtKey, V> {
+    Node4(SortedChildren<K, V, 4>),
+    Node16(SortedChildren<K, V, 16>),
+    Node48(Box<Node48<K, V>>),
+    Node256(Box<Node256<K, V>>),
+}
+
+struct SortedChildren<K: ArtKey, V, const N: usize> {
+    count: usize,
+    keys: [u8; N],
+    children: [Option<Arc<Node<K, V>>>; N],
+}
+
+struct Node48<K: ArtKey, V> {
+    index: [u8; 256],
+    children: [Option<Arc<Node<K, V>>>; 48],
+}
+
+struct Node256<K: ArtKey, V> {
+    children: [Option<Arc<Node<K, V>>>; 256],
+}
+
+impl<K: ArtKey, V> Children<K, V> {
+    /// Builds the children from pairs sorted by their key byte.
+    fn from_sorted(children: Vec<(u8, Arc<Node<K, V>>)>) -> Children<K, V> {
+        match children.len() {
+            0..=4 => Children::Node4(SortedChildren::from_sorted(children)),
+            5..=16 => Children::Node16(SortedChildren::from_sorted(children)),
+            17..=48 => {
+                let mut node = Box::new(Node48 {
+                    index: [EMPTY_INDEX; 256],
+                    children: std::array::from_fn(|_| None),
+                });
+                for (slot, (byte, child)) in children.into_iter().enumerate() {
+                    node.index[byte as usize] = slot as u8;
+                    node.children[slot] = Some(child);
+                }
+                Children::Node48(node)
+            }
+            _ => {
+                let mut node = Box::new(Node256 {
+                    children: std::array::from_fn(|_| None),
+                });
+                for (byte, child) in children {
+                    node.children[byte as usize] = Some(child);
+                }
+                Children::Node256(node)
+            }
+        }
+    }
+
+    fn get(&self, byte: u8) -> Option<&Arc<Node<K, V>>> {
+        match self {
+            Children::Node4(n4) => n4.get(byte),
+            Children::Node16(n16) => n16.get(byte),
+            Children::Node48(n48) => match n48.index[byte as usize] {
+                EMPTY_INDEX => None,
+                slot => n48.children[slot as usize].as_ref(),
+            },
+            Children::Node256(n256) => n256.children[byte as usize].as_ref(),
+        }
+    }
+
+    /// Returns the children in key order.
+    fn entries(&self) -> Vec<(u8, &Arc<Node<K, V>>)> {
+        let mut entries = Vec::new();
+        match self {
+            Children::Node4(n4) => entries.extend(n4.entries()),
+            Children::Node16(n16) => entries.extend(n16.entries()),
+            Children::Node48(_) | Children::Node256(_) => {
+                for byte in 0..=u8::MAX {
+                    entries.extend(self.get(byte).map(|child| (byte, child)));
+                }
+            }
+        }
+        entries
+    }
+}
+
+impl<K: ArtKey, V, const N: usize> SortedChildren<K, V, N> {
+    fn from_sorted(children: Vec<(u8, Arc<Node<K, V>>)>) -> SortedChildren<K, V, N> {
+        let mut node = SortedChildren {
+            count: children.len(),
+            keys: [0; N],
+            children: std::array::from_fn(|_| None),
+        };
+        for (i, (byte, child)) in children.into_iter().enumerate() {
+            node.keys[i] = byte;
+            node.children[i] = Some(child);
+        }
+        node
+    }
+
+    fn get(&self, byte: u8) -> Option<&Arc<Node<K, V>>> {
+        let i = self.keys[..self.count]
+            .iter()
+            .position(|&key| key == byte)?;
+        self.children[i].as_ref()
+    }
+
+    fn entries(&self) -> impl Iterator<Item = (u8, &Arc<Node<K, V>>)> {
+        let children = self.children[..self.count].iter().flatten();
+        self.keys[..self.count].iter().copied().zip(children)
+    }
+}
+
+impl<K: ArtKey, V> Inner<K, V> {
+    fn new(
+        prefix: Box<[u8]>,
+        prefixed_child: Option<Arc<Node<K, V>>>,
+        children: Vec<(u8, Arc<Node<K, V>>)>,
+    ) -> Inner<K, V> {
+        Inner {
+            prefix,
+            prefixed_child,
+            children: Children::from_sorted(children),
+        }
+    }
+
+    /// Builds a node storing two children that split at the end of the prefix.
+    fn pair(
+        prefix: &[u8],
+        a: ((u8, bool), Arc<Node<K, V>>),
+        b: ((u8, bool), Arc<Node<K, V>>),
+    ) -> Inner<K, V> {
+        let mut prefixed_child = None;
+        let mut children = Vec::with_capacity(2);
+        for ((byte, valid), child) in [a, b] {
+            match valid {
+                true => children.push((byte, child)),
+                false => prefixed_child = Some(child),
+            }
+        }
+        children.sort_unstable_by_key(|c| c.0);
+        Inner::new(prefix.into(), prefixed_child, children)
+    }
+
+    fn child(&self, valid_key: (u8, bool)) -> Option<&Arc<Node<K, V>>> {
+        match valid_key {
+            (byte, true) => self.children.get(byte),
+            (_, false) => self.prefixed_child.as_ref(),
+        }
+    }
+
+    /// Returns new references to the children in key order.
+    fn shared_children(&self) -> Vec<(u8, Arc<Node<K, V>>)> {
+        let children = self.children.entries().into_iter();
+        children
+            .map(|(byte, child)| (byte, child.clone()))
+            .collect()
+    }
+
+    /// Copies the node with another prefix, sharing its children.
+    fn with_prefix(&self, prefix: Box<[u8]>) -> Inner<K, V> {
+        Inner::new(prefix, self.prefixed_child.clone(), self.shared_children())
+    }
+
+    /// Copies the node with the child of the key replaced, added or removed, sharing the
+    /// other children. The copy has the smallest node type that holds its children.
+    fn with_child(&self, valid_key: (u8, bool), child: Option<Arc<Node<K, V>>>) -> Inner<K, V> {
+        let (byte, valid) = valid_key;
+        let mut children = self.shared_children();
+        if !valid {
+            return Inner::new(self.prefix.clone(), child, children);
+        }
+
+        let pos = children.partition_point(|c| c.0 < byte);
+        let occupied = children.get(pos).is_some_and(|c| c.0 == byte);
+        match (child, occupied) {
+            (Some(child), true) => children[pos].1 = child,
+            (Some(child), false) => children.insert(pos, (byte, child)),
+            (None, true) => {
+                children.remove(pos);
+            }
+            (None, false) => {}
+        }
+        Inner::new(self.prefix.clone(), self.prefixed_child.clone(), children)
+    }
+
+    /// Replaces a node left with a single child by the child, whose path then takes in
+    /// the node's own path.
+    fn collapse(self) -> Option<Arc<Node<K, V>>> {
+        let mut entries = self.children.entries();
+        match (&self.prefixed_child, entries.len()) {
+            (None, 0) => None,
+            // a prefixed child is always a leaf.
+            (Some(leaf), 0) => Some(leaf.clone()),
+            (None, 1) => {
+                let (byte, child) = entries.pop().unwrap();
+                match &**child {
+                    Node::Leaf(_) => Some(child.clone()),
+                    Node::Inner(inner) => {
+                        let prefix = [&self.prefix[..], &[byte], &inner.prefix[..]].concat();
+                        Some(Arc::new(Node::Inner(inner.with_prefix(prefix.into()))))
+                    }
+                }
+            }
+            _ => Some(Arc::new(Node::Inner(self))),
+        }
+    }
+}
+
+impl<K: ArtKey, V> Node<K, V> {
+    /// Moves the children out of an inner node.
+    fn take_children(&mut self, taken: &mut Vec<Arc<Node<K, V>>>) {
+        let Node::Inner(inner) = self else {
+            return;
+        };
+        taken.extend(inner.prefixed_child.take());
+        let children = match &mut inner.children {
+            Children::Node4(n4) => &mut n4.children[..],
+            Children::Node16(n16) => &mut n16.children[..],
+            Children::Node48(n48) => &mut n48.children[..],
+            Children::Node256(n256) => &mut n256.children[..],
+        };
+        taken.extend(children.iter_mut().filter_map(Option::take));
+    }
+}
+
+/// Drops the subtree with a work stack rather than recursively. A child shared with
+/// another tree only loses a reference, the children owned by this node alone are taken
+/// apart in turn.
+impl<K: ArtKey, V> Drop for Node<K, V> {
+    fn drop(&mut self) {
+        let mut stack = Vec::new();
+        self.take_children(&mut stack);
+        while let Some(child) = stack.pop() {
+            if let Ok(mut node) = Arc::try_unwrap(child) {
+                node.take_children(&mut stack);
+            }
+        }
+    }
+}
+
+fn valid(key: &[u8], depth: usize) -> (u8, bool) {
+    match key.get(depth) {
+        Some(byte) => (*byte, true),
+        None => (0, false),
+    }
+}
+
+/// Returns a copy of the tree with the leaf inserted, and whether it replaced a leaf.
+///
+/// The inner nodes on the path to the key are recorded on the way down, and copied bottom-up
+/// around the changed subtree, so deep trees do not overflow the call stack.
+fn insert<K: ArtKey, V>(
+    root: &Arc<Node<K, V>>,
+    key: &[u8],
+    leaf: Arc<Node<K, V>>,
+) -> (Arc<Node<K, V>>, bool) {
+    let mut path = Vec::new();
+    let mut node = root;
+    let mut depth = 0;
+    let (mut copy, replaced) = loop {
+        match &**node {
+            Node::Leaf(old) => {
+                let old_key = old.key.get_bytes();
+                let old_key = old_key.as_ref();
+                if old_key == key {
+                    break (leaf, true);
+                }
+
+                let lcp = old_key[depth..]
+                    .iter()
+                    .zip(&key[depth..])
+                    .take_while(|(a, b)| a == b)
+                    .count();
+                let new_depth = depth + lcp;
+                let expanded = Inner::pair(
+                    &key[depth..new_depth],
+                    (valid(old_key, new_depth), node.clone()),
+                    (valid(key, new_depth), leaf),
+                );
+                break (Arc::new(Node::Inner(expanded)), false);
+            }
+            Node::Inner(inner) => {
+                let prefix = &inner.prefix;
+                let matched = prefix
+                    .iter()
+                    .zip(&key[depth..])
+                    .take_while(|(a, b)| a == b)
+                    .count();
+                if matched < prefix.len() {
+                    let rest =
+                        Arc::new(Node::Inner(inner.with_prefix(prefix[matched + 1..].into())));
+                    let split = Inner::pair(
+                        &prefix[..matched],
+                        ((prefix[matched], true), rest),
+                        (valid(key, depth + matched), leaf),
+                    );
+                    break (Arc::new(Node::Inner(split)), false);
+                }
+
+                depth += matched;
+                let valid_key = valid(key, depth);
+                path.push((inner, valid_key));
+                match inner.child(valid_key) {
+                    Some(child) => node = child,
+                    None => break (leaf, false),
+                }
+                depth += 1;
+            }
+        }
+    };
+
+    for (inner, valid_key) in path.into_iter().rev() {
+        copy = Arc::new(Node::Inner(inner.with_child(valid_key, Some(copy))));
+    }
+    (copy, replaced)
+}
+
+/// Returns a copy of the tree without the key, or `None` if the key is absent and the tree
+/// is unchanged. The path is copied bottom-up as by [insert].
+fn remove<K: ArtKey, V>(root: &Arc<Node<K, V>>, key: &[u8]) -> Option<Option<Arc<Node<K, V>>>> {
+    let mut path = Vec::new();
+    let mut node = root;
+    let mut depth = 0;
+    while let Node::Inner(inner) = &**node {
+        if !key[depth..].starts_with(&inner.prefix) {
+            return None;
+        }
+        depth += inner.prefix.len();
+        let valid_key = valid(key, depth);
+        path.push((inner, valid_key));
+        node = inner.child(valid_key)?;
+        depth += 1;
+    }
+    let Node::Leaf(leaf) = &**node else {
+        unreachable!()
+    };
+    if !leaf.matches(key) {
+        return None;
+    }
+
+    let mut copy = None;
+    for (inner, valid_key) in path.into_iter().rev() {
+        copy = inner.with_child(valid_key, copy).collapse();
+    }
+    Some(copy)
+}
+
+/// A persistent adaptive radix tree.
+///
+/// [insert](PersistentArt::insert) and [remove](PersistentArt::remove) leave the tree
+/// unchanged and return an updated tree, which shares all subtrees the update did not
+/// touch. Cloning the tree is O(1), so a clone is a cheap snapshot that stays consistent
+/// while the original keeps changing.
+///
+/// # Examples
+/// ```rust
+/// use artful::PersistentArt;
+///
+/// let v1 = PersistentArt::<u32, &str>::new().insert(1, "a").insert(2, "b");
+/// let snapshot = v1.clone();
+/// let v2 = v1.insert(2, "c").remove(&1);
+///
+/// assert_eq!(snapshot.get(&1), Some(&"a"));
+/// assert_eq!(snapshot.get(&2), Some(&"b"));
+/// assert_eq!(v2.get(&1), None);
+/// assert_eq!(v2.get(&2), Some(&"c"));
+/// ```
+pub struct PersistentArt<K: ArtKey, V> {
+    root: Option<Arc<Node<K, V>>>,
+    size: usize,
+}
+
+impl<K: ArtKey, V> Clone for PersistentArt<K, V> {
+    fn clone(&self) -> Self {
+        PersistentArt {
+            root: self.root.clone(),
+            size: self.size,
+        }
+    }
+}
+
+impl<K: ArtKey, V> Default for PersistentArt<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: ArtKey + Debug, V: Debug> Debug for PersistentArt<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<K: ArtKey, V> PersistentArt<K, V> {
+    pub fn new() -> PersistentArt<K, V> {
+        PersistentArt {
+            root: None,
+            size: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.size
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.size == 0
+    }
+
+    /// Returns a reference to the value corresponding to the key.
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: ArtKey + ?Sized,
+    {
+        let key = key.get_bytes();
+        let key = key.as_ref();
+        let mut node = self.root.as_ref()?;
+        let mut depth = 0;
+        loop {
+            match &**node {
+                Node::Leaf(leaf) => return leaf.matches(key).then_some(&leaf.val),
+                Node::Inner(inner) => {
+                    if !key[depth..].starts_with(&inner.prefix) {
+                        return None;
+                    }
+                    depth += inner.prefix.len();
+                    node = inner.child(valid(key, depth))?;
+                    depth += 1;
+                }
+            }
+        }
+    }
+
+    /// Returns true if the tree contains a value for the key.
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: ArtKey + ?Sized,
+    {
+        self.get(key).is_some()
+    }
+
+    /// Returns a tree with the key-value pair inserted, replacing the old value of the key.
+    ///
+    /// Only the nodes on the path to the key are copied.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use artful::PersistentArt;
+    ///
+    /// let empty = PersistentArt::<String, u32>::new();
+    /// let art = empty.insert("a".to_string(), 1);
+    /// assert_eq!(art.get("a"), Some(&1));
+    /// assert!(empty.is_empty());
+    /// ```
+    pub fn insert(&self, key: K, val: V) -> PersistentArt<K, V> {
+        let leaf = Arc::new(Node::Leaf(Leaf::new(key, val)));
+        let Node::Leaf(new_leaf) = &*leaf else {
+            unreachable!()
+        };
+        let key = new_leaf.key.get_bytes();
+        let (root, replaced) = match &self.root {
+            Some(root) => insert(root, key.as_ref(), leaf.clone()),
+            None => (leaf.clone(), false),
+        };
+        PersistentArt {
+            root: Some(root),
+            size: if replaced { self.size } else { self.size + 1 },
+        }
+    }
+
+    /// Returns a tree without the key, or a clone of this tree if the key is absent.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use artful::PersistentArt;
+    ///
+    /// let art = PersistentArt::<String, u32>::new().insert("a".to_string(), 1);
+    /// let removed = art.remove("a");
+    /// assert_eq!(removed.get("a"), None);
+    /// assert_eq!(art.get("a"), Some(&1));
+    /// ```
+    pub fn remove<Q>(&self, key: &Q) -> PersistentArt<K, V>
+    where
+        K: Borrow<Q>,
+        Q: ArtKey + ?Sized,
+    {
+        let removed = self
+            .root
+            .as_ref()
+            .and_then(|root| remove(root, key.get_bytes().as_ref()));
+        match removed {
+            Some(root) => PersistentArt {
+                root,
+                size: self.size - 1,
+            },
+            None => self.clone(),
+        }
+    }
+
+    /// Gets an iterator over the entries of the tree, sorted by key.
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        Iter {
+            stack: self.root.iter().map(|root| &**root).collect(),
+            length: self.size,
+        }
+    }
+}
+
+/// An iterator over the entries of a [PersistentArt], sorted by key.
+pub struct Iter<'a, K: ArtKey, V> {
+    /// The subtrees still to visit, the next one last.
+    stack: Vec<&'a Node<K, V>>,
+    length: usize,
+}
+
+impl<'a, K: ArtKey, V> Iterator for Iter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.stack.pop()? {
+                Node::Leaf(leaf) => {
+                    self.length -= 1;
+                    return Some((&leaf.key, &leaf.val));
+                }
+                Node::Inner(inner) => {
+                    let children = inner.children.entries();
+                    self.stack
+                        .extend(children.into_iter().rev().map(|(_, c)| &**c));
+                    self.stack.extend(inner.prefixed_child.as_deref());
+                }
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.length, Some(self.length))
+    }
+}
+
+impl<K: ArtKey, V> ExactSizeIterator for Iter<'_, K, V> {}
+
+impl<'a, K: ArtKey, V> IntoIterator for &'a PersistentArt<K, V> {
+    type Item = (&'a K, &'a V);
+    type IntoIter = Iter<'a, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Node, PersistentArt};
+    use std::collections::BTreeMap;
+    use std::sync::Arc;
+
+    #[test]
+    fn versions_against_btree() {
+        let mut versions = vec![(PersistentArt::<u32, u32>::new(), BTreeMap::new())];
+        // dense low bytes grow Node256, the sparse keys split compressed paths.
+        let keys = (0..600u32).chain((0..64).map(|i| i << 20 | i));
+        for key in keys.clone() {
+            let (art, btree) = versions.last().unwrap();
+            let mut btree = btree.clone();
+            btree.insert(key, key * 2);
+            versions.push((art.insert(key, key * 2), btree));
+        }
+        for key in keys.filter(|key| key % 3 != 0) {
+            let (art, btree) = versions.last().unwrap();
+            let mut btree = btree.clone();
+            btree.remove(&key);
+            versions.push((art.remove(&key), btree));
+        }
+
+        for (art, btree) in versions.iter().step_by(37) {
+            assert_eq!(art.len(), btree.len());
+            assert!(art.iter().eq(btree.iter()));
+            for key in btree.keys() {
+                assert_eq!(art.get(key), btree.get(key));
+            }
+        }
+    }
+
+    #[test]
+    fn deep_tree_on_small_stack() {
+        // as the Art test of the same name, the tree is a chain of one node per key.
+        const DEPTH: usize = 3000;
+        let worker = std::thread::Builder::new()
+            .stack_size(64 << 10)
+            .spawn(|| {
+                let key = |i: usize| {
+                    let mut key = vec![b'a'; i];
+                    key.push(b'b');
+                    key
+                };
+                let mut art = PersistentArt::<Vec<u8>, usize>::new();
+                for i in 0..DEPTH {
+                    art = art.insert(key(i), i);
+                }
+                let snapshot = art.clone();
+                for i in (0..DEPTH).step_by(2) {
+                    art = art.remove(&key(i));
+                }
+                assert_eq!(art.len(), DEPTH / 2);
+                assert_eq!(art.get(&key(DEPTH - 1)), Some(&(DEPTH - 1)));
+                assert_eq!(snapshot.get(&key(0)), Some(&0));
+                drop(snapshot);
+                drop(art);
+            })
+            .unwrap();
+        worker.join().unwrap();
+    }
+
+    #[test]
+    fn updates_share_untouched_subtrees() {
+        let mut art = PersistentArt::<String, usize>::new();
+        for (i, key) in ["apple", "apricot", "banana", "band", "bandana"]
+            .iter()
+            .enumerate()
+        {
+            art = art.insert(key.to_string(), i);
+        }
+
+        let updated = art.insert("bandit".to_string(), 5).remove("bandana");
+        let child = |art: &PersistentArt<String, usize>, byte| match art.root.as_deref() {
+            Some(Node::Inner(inner)) => inner.child((byte, true)).unwrap().clone(),
+            _ => unreachable!(),
+        };
+        assert!(Arc::ptr_eq(&child(&art, b'a'), &child(&updated, b'a')));
+        assert!(!Arc::ptr_eq(&child(&art, b'b'), &child(&updated, b'b')));
+        assert_eq!(art.len(), 5);
+        assert_eq!(updated.len(), 5);
+        assert_eq!(updated.get("bandana"), None);
+        assert_eq!(art.get("bandana"), Some(&4));
+    }
+}
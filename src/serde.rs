@@ -0,0 +1,105 @@
+use std::fmt;
+use std::marker::PhantomData;
+
+use serde::de::{MapAccess, Visitor};
+use serde::ser::SerializeMap;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::alloc::NodeAllocator;
+use crate::bulk::BulkBuilder;
+use crate::{Art, ArtKey};
+
+/// The map is written in key order.
+impl<K, V, const MAX_PARTIAL_LEN: usize, A> Serialize for Art<K, V, MAX_PARTIAL_LEN, A>
+where
+    A: NodeAllocator,
+    K: ArtKey + Serialize,
+    V: Serialize,
+{
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.size()))?;
+        for (key, val) in self {
+            map.serialize_entry(key, val)?;
+        }
+        map.end()
+    }
+}
+
+/// A map written in key order, as by [Serialize], is built bottom-up like
+/// [Art::from_sorted_iter]. From the first key out of order on, the remaining entries are
+/// inserted one by one, and a repeated key keeps its last value.
+impl<'de, K, V, const MAX_PARTIAL_LEN: usize, A> Deserialize<'de> for Art<K, V, MAX_PARTIAL_LEN, A>
+where
+    A: NodeAllocator + Default,
+    K: ArtKey + Deserialize<'de>,
+    V: Deserialize<'de>,
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_map(ArtVisitor(PhantomData))
+    }
+}
+
+struct ArtVisitor<K: ArtKey, V, const MAX_PARTIAL_LEN: usize, A: NodeAllocator>(
+    PhantomData<Art<K, V, MAX_PARTIAL_LEN, A>>,
+);
+
+impl<'de, K, V, const MAX_PARTIAL_LEN: usize, A> Visitor<'de>
+    for ArtVisitor<K, V, MAX_PARTIAL_LEN, A>
+where
+    A: NodeAllocator + Default,
+    K: ArtKey + Deserialize<'de>,
+    V: Deserialize<'de>,
+{
+    type Value = Art<K, V, MAX_PARTIAL_LEN, A>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a map")
+    }
+
+    fn visit_map<M: MapAccess<'de>>(self, mut map: M) -> Result<Self::Value, M::Error> {
+        let mut builder = BulkBuilder::new_in(A::default());
+        while let Some((key, val)) = map.next_entry::<K, V>()? {
+            if builder.check(key.get_bytes().as_ref()).is_ok() {
+                builder
+                    .push(key, val)
+                    .expect("the key follows the previous key");
+                continue;
+            }
+
+            let mut art = Art::from_builder(builder);
+            art.insert(key, val);
+            while let Some((key, val)) = map.next_entry()? {
+                art.insert(key, val);
+            }
+            return Ok(art);
+        }
+        Ok(Art::from_builder(builder))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::Art;
+
+    #[test]
+    fn round_trip() {
+        let mut art = Art::<String, u32, 4>::new();
+        for i in 0..2000u32 {
+            art.insert(format!("key{}", i * 7919 % 2000), i);
+        }
+
+        let json = serde_json::to_string(&art).unwrap();
+        let loaded: Art<String, u32, 4> = serde_json::from_str(&json).unwrap();
+        assert_eq!(loaded, art);
+        assert!(json.starts_with(r#"{"key0":0,"key1":"#));
+    }
+
+    #[test]
+    fn unsorted_input() {
+        let json = r#"{"b":1,"ab":2,"a":3,"b":4,"c":5}"#;
+        let art: Art<String, u32> = serde_json::from_str(json).unwrap();
+        let entries: Vec<(&str, u32)> = art.iter().map(|(k, v)| (k.as_str(), *v)).collect();
+        assert_eq!(entries, [("a", 3), ("ab", 2), ("b", 4), ("c", 5)]);
+        assert_eq!(art.size(), 4);
+    }
+}
@@ -1,22 +1,377 @@
+//! Search kernels for the node types, picked at runtime from the features of the CPU.
+//!
+//! The features are detected once, on the first search. SSE2 or NEON searches and orders
+//! the keys of a Node16, AVX2 scans the index of a Node48 and the children of a Node256, and every
+//! other search is scalar.
+
+use std::sync::atomic::{AtomicU8, Ordering};
+
+#[cfg(target_arch = "aarch64")]
+use std::arch::aarch64::*;
+#[cfg(target_arch = "x86")]
+use std::arch::x86::*;
+#[cfg(target_arch = "x86_64")]
+use std::arch::x86_64::*;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Level {
+    Scalar = 1,
+    Sse2,
+    Avx2,
+    Neon,
+}
+
+/// The detected level, zero until the first search.
+static LEVEL: AtomicU8 = AtomicU8::new(0);
+
+#[inline]
+pub(crate) fn level() -> Level {
+    match LEVEL.load(Ordering::Relaxed) {
+        1 => Level::Scalar,
+        2 => Level::Sse2,
+        3 => Level::Avx2,
+        4 => Level::Neon,
+        _ => {
+            let level = detect();
+            LEVEL.store(level as u8, Ordering::Relaxed);
+            level
+        }
+    }
+}
+
+fn detect() -> Level {
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    {
+        if is_x86_feature_detected!("avx2") {
+            return Level::Avx2;
+        }
+        if is_x86_feature_detected!("sse2") {
+            return Level::Sse2;
+        }
+    }
+
+    #[cfg(target_arch = "aarch64")]
+    if std::arch::is_aarch64_feature_detected!("neon") {
+        return Level::Neon;
+    }
+
+    Level::Scalar
+}
+
+/// Returns the position of `byte` in the first `count` sorted keys of a Node16.
+#[inline]
+pub(crate) fn find_key(keys: &[u8; 16], count: usize, byte: u8) -> Option<usize> {
+    find_key_at(level(), keys, count, byte)
+}
+
+/// Returns the number of the first `count` sorted keys of a Node16 that are less than
+/// `byte`, the position where `byte` is inserted.
+#[inline]
+pub(crate) fn lower_bound(keys: &[u8; 16], count: usize, byte: u8) -> usize {
+    lower_bound_at(level(), keys, count, byte)
+}
+
+/// Returns the first key byte of a Node48 whose index is not `empty`.
+#[inline]
+pub(crate) fn first_indexed(index: &[u8; 256], empty: u8) -> Option<usize> {
+    first_indexed_at(level(), index, empty)
+}
+
+/// Returns the last key byte of a Node48 whose index is not `empty`.
+#[inline]
+pub(crate) fn last_indexed(index: &[u8; 256], empty: u8) -> Option<usize> {
+    last_indexed_at(level(), index, empty)
+}
+
+/// Returns the first key byte of a Node256 with a child, an empty child is zero.
+#[inline]
+pub(crate) fn first_occupied(children: &[usize; 256]) -> Option<usize> {
+    first_occupied_at(level(), children)
+}
+
+/// Returns the last key byte of a Node256 with a child, an empty child is zero.
+#[inline]
+pub(crate) fn last_occupied(children: &[usize; 256]) -> Option<usize> {
+    last_occupied_at(level(), children)
+}
+
+#[inline]
+fn find_key_at(level: Level, keys: &[u8; 16], count: usize, byte: u8) -> Option<usize> {
+    let mask = (1 << count) - 1;
+    let matched = match level {
+        #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+        Level::Sse2 | Level::Avx2 => unsafe { sse2::eq_mask(keys, byte) },
+        #[cfg(target_arch = "aarch64")]
+        Level::Neon => unsafe { neon::eq_mask(keys, byte) },
+        _ => return keys[..count].binary_search(&byte).ok(),
+    };
+    match matched & mask {
+        0 => None,
+        bits => Some(bits.trailing_zeros() as usize),
+    }
+}
+
+#[inline]
+fn lower_bound_at(level: Level, keys: &[u8; 16], count: usize, byte: u8) -> usize {
+    let mask = (1 << count) - 1;
+    let less = match level {
+        #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+        Level::Sse2 | Level::Avx2 => unsafe { sse2::lt_mask(keys, byte) },
+        #[cfg(target_arch = "aarch64")]
+        Level::Neon => unsafe { neon::lt_mask(keys, byte) },
+        _ => return keys[..count].partition_point(|key| *key < byte),
+    };
+    // the keys are sorted, so the keys less than `byte` come first.
+    (less & mask).count_ones() as usize
+}
+
+#[inline]
+fn first_indexed_at(level: Level, index: &[u8; 256], empty: u8) -> Option<usize> {
+    match level {
+        #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+        Level::Avx2 => unsafe { avx2::first_indexed(index, empty) },
+        _ => index.iter().position(|slot| *slot != empty),
+    }
+}
+
 #[inline]
-#[cfg(feature = "simd")]
-#[cfg(any(target_arch = "aarch64", target_arch = "arm"))]
-pub(crate) unsafe fn _mm_movemask_epi8(input: uint8x16_t) -> i32 {
-    // Example input (half scale):
-    // 0x89 FF 1D C0 00 10 99 33
-    // Shift out everything but the sign bits
-    // 0x01 01 00 01 00 00 01 00
-    let high_bits = vreinterpretq_u16_u8(vshrq_n_u8::<7>(input));
-    // Merge the even lanes together with vsra. The '??' bytes are garbage.
-    // vsri could also be used, but it is slightly slower on aarch64.
-    // 0x??03 ??02 ??00 ??01
-    let paired16 = vreinterpretq_u32_u16(vsraq_n_u16::<7>(high_bits, high_bits));
-    // Repeat with wider lanes.
-    // 0x??????0B ??????04
-    let paired32 = vreinterpretq_u64_u32(vsraq_n_u32::<14>(paired16, paired16));
-    // 0x??????????????4B
-    let paired64 = vreinterpretq_u8_u64(vsraq_n_u64::<28>(paired32, paired32));
-    // Extract the low 8 bits from each lane and join.
-    // 0x4B
-    vgetq_lane_u8::<0>(paired64) as i32 | (vgetq_lane_u8::<8>(paired64) as i32) << 8
+fn last_indexed_at(level: Level, index: &[u8; 256], empty: u8) -> Option<usize> {
+    match level {
+        #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+        Level::Avx2 => unsafe { avx2::last_indexed(index, empty) },
+        _ => index.iter().rposition(|slot| *slot != empty),
+    }
+}
+
+#[inline]
+fn first_occupied_at(level: Level, children: &[usize; 256]) -> Option<usize> {
+    match level {
+        #[cfg(target_arch = "x86_64")]
+        Level::Avx2 => unsafe { avx2::first_occupied(children) },
+        _ => children.iter().position(|child| *child != 0),
+    }
+}
+
+#[inline]
+fn last_occupied_at(level: Level, children: &[usize; 256]) -> Option<usize> {
+    match level {
+        #[cfg(target_arch = "x86_64")]
+        Level::Avx2 => unsafe { avx2::last_occupied(children) },
+        _ => children.iter().rposition(|child| *child != 0),
+    }
+}
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+mod sse2 {
+    use super::*;
+
+    /// Returns a bit for every key equal to `byte`.
+    #[target_feature(enable = "sse2")]
+    pub(super) unsafe fn eq_mask(keys: &[u8; 16], byte: u8) -> u32 {
+        let keys = _mm_loadu_si128(keys.as_ptr() as *const __m128i);
+        let eq = _mm_cmpeq_epi8(_mm_set1_epi8(byte as i8), keys);
+        _mm_movemask_epi8(eq) as u32
+    }
+
+    /// Returns a bit for every key less than `byte`. SSE2 only compares signed bytes, so
+    /// both sides are biased by 0x80 first, which maps the unsigned order onto the signed
+    /// one.
+    #[target_feature(enable = "sse2")]
+    pub(super) unsafe fn lt_mask(keys: &[u8; 16], byte: u8) -> u32 {
+        let bias = _mm_set1_epi8(i8::MIN);
+        let keys = _mm_xor_si128(_mm_loadu_si128(keys.as_ptr() as *const __m128i), bias);
+        let byte = _mm_xor_si128(_mm_set1_epi8(byte as i8), bias);
+        _mm_movemask_epi8(_mm_cmplt_epi8(keys, byte)) as u32
+    }
+}
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+mod avx2 {
+    use super::*;
+
+    /// Returns a bit for every byte of the 32 at `chunk` that is not `empty`.
+    #[inline]
+    #[target_feature(enable = "avx2")]
+    unsafe fn indexed_mask(index: &[u8; 256], chunk: usize, empty: u8) -> u32 {
+        let bytes = _mm256_loadu_si256(index.as_ptr().add(chunk * 32) as *const __m256i);
+        let eq = _mm256_cmpeq_epi8(bytes, _mm256_set1_epi8(empty as i8));
+        !(_mm256_movemask_epi8(eq) as u32)
+    }
+
+    #[target_feature(enable = "avx2")]
+    pub(super) unsafe fn first_indexed(index: &[u8; 256], empty: u8) -> Option<usize> {
+        for chunk in 0..8 {
+            let bits = indexed_mask(index, chunk, empty);
+            if bits != 0 {
+                return Some(chunk * 32 + bits.trailing_zeros() as usize);
+            }
+        }
+        None
+    }
+
+    #[target_feature(enable = "avx2")]
+    pub(super) unsafe fn last_indexed(index: &[u8; 256], empty: u8) -> Option<usize> {
+        for chunk in (0..8).rev() {
+            let bits = indexed_mask(index, chunk, empty);
+            if bits != 0 {
+                return Some(chunk * 32 + 31 - bits.leading_zeros() as usize);
+            }
+        }
+        None
+    }
+
+    /// Returns a bit for every non-zero word of the 4 at `chunk`.
+    #[cfg(target_arch = "x86_64")]
+    #[inline]
+    #[target_feature(enable = "avx2")]
+    unsafe fn occupied_mask(children: &[usize; 256], chunk: usize) -> u32 {
+        let words = _mm256_loadu_si256(children.as_ptr().add(chunk * 4) as *const __m256i);
+        if _mm256_testz_si256(words, words) == 1 {
+            return 0;
+        }
+        let eq = _mm256_cmpeq_epi64(words, _mm256_setzero_si256());
+        !(_mm256_movemask_pd(_mm256_castsi256_pd(eq)) as u32) & 0xF
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    #[target_feature(enable = "avx2")]
+    pub(super) unsafe fn first_occupied(children: &[usize; 256]) -> Option<usize> {
+        for chunk in 0..64 {
+            let bits = occupied_mask(children, chunk);
+            if bits != 0 {
+                return Some(chunk * 4 + bits.trailing_zeros() as usize);
+            }
+        }
+        None
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    #[target_feature(enable = "avx2")]
+    pub(super) unsafe fn last_occupied(children: &[usize; 256]) -> Option<usize> {
+        for chunk in (0..64).rev() {
+            let bits = occupied_mask(children, chunk);
+            if bits != 0 {
+                return Some(chunk * 4 + 31 - bits.leading_zeros() as usize);
+            }
+        }
+        None
+    }
+}
+
+#[cfg(target_arch = "aarch64")]
+mod neon {
+    use super::*;
+
+    /// Returns a bit for every key equal to `byte`.
+    #[target_feature(enable = "neon")]
+    pub(super) unsafe fn eq_mask(keys: &[u8; 16], byte: u8) -> u32 {
+        let eq = vceqq_u8(vdupq_n_u8(byte), vld1q_u8(keys.as_ptr()));
+        movemask(eq)
+    }
+
+    /// Returns a bit for every key less than `byte`.
+    #[target_feature(enable = "neon")]
+    pub(super) unsafe fn lt_mask(keys: &[u8; 16], byte: u8) -> u32 {
+        let lt = vcltq_u8(vld1q_u8(keys.as_ptr()), vdupq_n_u8(byte));
+        movemask(lt)
+    }
+
+    /// Gathers the top bit of every byte, as `_mm_movemask_epi8` does on x86.
+    #[inline]
+    #[target_feature(enable = "neon")]
+    unsafe fn movemask(input: uint8x16_t) -> u32 {
+        // Example input (half scale):
+        // 0x89 FF 1D C0 00 10 99 33
+        // Shift out everything but the sign bits
+        // 0x01 01 00 01 00 00 01 00
+        let high_bits = vreinterpretq_u16_u8(vshrq_n_u8::<7>(input));
+        // Merge the even lanes together with vsra. The '??' bytes are garbage.
+        // vsri could also be used, but it is slightly slower on aarch64.
+        // 0x??03 ??02 ??00 ??01
+        let paired16 = vreinterpretq_u32_u16(vsraq_n_u16::<7>(high_bits, high_bits));
+        // Repeat with wider lanes.
+        // 0x??????0B ??????04
+        let paired32 = vreinterpretq_u64_u32(vsraq_n_u32::<14>(paired16, paired16));
+        // 0x??????????????4B
+        let paired64 = vreinterpretq_u8_u64(vsraq_n_u64::<28>(paired32, paired32));
+        // Extract the low 8 bits from each lane and join.
+        // 0x4B
+        vgetq_lane_u8::<0>(paired64) as u32 | (vgetq_lane_u8::<8>(paired64) as u32) << 8
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// The levels the CPU supports, every kernel is checked against the scalar one.
+    fn levels() -> Vec<Level> {
+        let mut levels = vec![Level::Scalar];
+        match level() {
+            Level::Avx2 => levels.extend([Level::Sse2, Level::Avx2]),
+            Level::Scalar => {}
+            level => levels.push(level),
+        }
+        levels
+    }
+
+    /// A pseudo-random sequence, the same on every run.
+    fn bytes(seed: u32) -> impl Iterator<Item = u8> {
+        let mut state = seed.wrapping_mul(2654435761) | 1;
+        std::iter::repeat_with(move || {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            state as u8
+        })
+    }
+
+    #[test]
+    fn node16_search_matches_scalar() {
+        for seed in 0..64 {
+            let mut keys: Vec<u8> = bytes(seed).take(16).collect();
+            keys.sort_unstable();
+            keys.dedup();
+            let count = keys.len().min(seed as usize % 17);
+            let mut node_keys = [0u8; 16];
+            node_keys[..keys.len()].copy_from_slice(&keys);
+
+            for byte in 0..=u8::MAX {
+                let expected = node_keys[..count].iter().position(|key| *key == byte);
+                let less = node_keys[..count].iter().filter(|key| **key < byte).count();
+                for level in levels() {
+                    assert_eq!(find_key_at(level, &node_keys, count, byte), expected);
+                    assert_eq!(lower_bound_at(level, &node_keys, count, byte), less);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn scans_match_scalar() {
+        let mut patterns: Vec<Vec<usize>> = vec![vec![], (0..256).collect()];
+        patterns.extend((0..256).map(|byte| vec![byte]));
+        patterns.extend((1..64).map(|seed| {
+            let bytes: Vec<u8> = bytes(seed).take(seed as usize % 48 + 1).collect();
+            bytes.into_iter().map(usize::from).collect()
+        }));
+
+        for occupied in patterns {
+            let mut index = [48u8; 256];
+            let mut children = [0usize; 256];
+            for (slot, byte) in occupied.iter().enumerate() {
+                index[*byte] = slot as u8 % 48;
+                children[*byte] = (slot + 1) << 3 | 4;
+            }
+
+            let first = occupied.iter().min().copied();
+            let last = occupied.iter().max().copied();
+            for level in levels() {
+                assert_eq!(first_indexed_at(level, &index, 48), first);
+                assert_eq!(last_indexed_at(level, &index, 48), last);
+                assert_eq!(first_occupied_at(level, &children), first);
+                assert_eq!(last_occupied_at(level, &children), last);
+            }
+        }
+    }
 }
@@ -0,0 +1,112 @@
+use crate::leaf::Leaf;
+use crate::node::{
+    layout, ArtNode, NODE_TYPE_LEAF, NODE_TYPE_N16, NODE_TYPE_N256, NODE_TYPE_N4, NODE_TYPE_N48,
+};
+use crate::node16::Node16;
+use crate::node256::Node256;
+use crate::node4::Node4;
+use crate::node48::Node48;
+use crate::ArtKey;
+
+/// The number of nodes of one type and the bytes of their allocations.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct NodeStats {
+    pub count: usize,
+    pub bytes: usize,
+}
+
+impl NodeStats {
+    fn add(&mut self, bytes: usize) {
+        self.count += 1;
+        self.bytes += bytes;
+    }
+}
+
+/// The shape and memory usage of a tree, returned by [Art::stats](crate::Art::stats).
+///
+/// The bytes count the allocations of the nodes and leaves only, not the heap memory
+/// owned by the keys and values, such as the buffer of a `String`.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct ArtStats {
+    pub node4: NodeStats,
+    pub node16: NodeStats,
+    pub node48: NodeStats,
+    pub node256: NodeStats,
+    pub leaves: NodeStats,
+    /// The inner nodes whose compressed path is longer than `MAX_PARTIAL_LEN`, so that only
+    /// its first bytes are stored and lookups verify the rest at the leaf.
+    pub optimistic_paths: usize,
+    /// The inner nodes with a prefixed child, the leaf of a key ending at the node.
+    pub prefixed_children: usize,
+    /// The largest number of inner nodes above a leaf.
+    pub max_depth: usize,
+    /// The average number of inner nodes above a leaf.
+    pub avg_depth: f64,
+}
+
+impl ArtStats {
+    /// Returns the bytes of all nodes and leaves.
+    pub fn total_bytes(&self) -> usize {
+        self.inner_bytes() + self.leaves.bytes
+    }
+
+    /// Returns the bytes of the inner nodes, the overhead of the tree over its leaves.
+    pub fn inner_bytes(&self) -> usize {
+        self.node4.bytes + self.node16.bytes + self.node48.bytes + self.node256.bytes
+    }
+
+    /// Counts the size of the blocks the nodes are allocated with, which are padded to the
+    /// alignment of the node type tag.
+    pub(crate) fn collect<K: ArtKey, V, const MAX_PARTIAL_LEN: usize>(
+        root: &ArtNode<K, V, MAX_PARTIAL_LEN>,
+    ) -> ArtStats {
+        let mut stats = ArtStats::default();
+        let mut total_depth = 0;
+        let mut stack = Vec::new();
+        if !root.is_none() {
+            stack.push((root, 0));
+        }
+
+        while let Some((node, depth)) = stack.pop() {
+            match node.node_type() {
+                NODE_TYPE_LEAF => {
+                    stats.leaves.add(layout::<Leaf<K, V>>().size());
+                    stats.max_depth = stats.max_depth.max(depth);
+                    total_depth += depth;
+                    continue;
+                }
+                NODE_TYPE_N4 => stats
+                    .node4
+                    .add(layout::<Node4<K, V, MAX_PARTIAL_LEN>>().size()),
+                NODE_TYPE_N16 => stats
+                    .node16
+                    .add(layout::<Node16<K, V, MAX_PARTIAL_LEN>>().size()),
+                NODE_TYPE_N48 => stats
+                    .node48
+                    .add(layout::<Node48<K, V, MAX_PARTIAL_LEN>>().size()),
+                NODE_TYPE_N256 => stats
+                    .node256
+                    .add(layout::<Node256<K, V, MAX_PARTIAL_LEN>>().size()),
+                _ => unreachable!(),
+            }
+
+            if node.header().partial.len as usize > MAX_PARTIAL_LEN {
+                stats.optimistic_paths += 1;
+            }
+            if let Some(child) = node.prefixed_child() {
+                stats.prefixed_children += 1;
+                stack.push((child, depth + 1));
+            }
+            stack.extend(
+                node.children()
+                    .into_iter()
+                    .map(|(_, child)| (child, depth + 1)),
+            );
+        }
+
+        if stats.leaves.count > 0 {
+            stats.avg_depth = total_depth as f64 / stats.leaves.count as f64;
+        }
+        stats
+    }
+}
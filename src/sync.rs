@@ -0,0 +1,1075 @@
+//! A concurrent adaptive radix tree, following "The ART of Practical Synchronization".
+//!
+//! Every inner node carries a version lock, and readers never take it. A node is only
+//! changed in place in ways a reader can not observe half done: a child pointer is swapped,
+//! a child is added to a free slot of a Node48 or Node256, or removed from a Node256. Every
+//! other change, a node growing, shrinking or having its compressed path split, builds a
+//! new node and swaps it into the parent, so a writer locks the parent and the replaced
+//! node, and marks the replaced node obsolete. Writers that meet an obsolete node restart
+//! from the root.
+//!
+//! Replaced nodes and leaves are freed by epoch-based reclamation once no reader that could
+//! have reached them is still running.
+
+use std::borrow::Borrow;
+use std::marker::PhantomData;
+use std::sync::atomic::{AtomicU16, AtomicU64, AtomicU8, AtomicUsize, Ordering};
+
+use crossbeam_epoch::{self as epoch, Guard};
+
+use crate::leaf::Leaf;
+use crate::ArtKey;
+
+const NODE_TYPE_N4: usize = 1;
+const NODE_TYPE_N16: usize = 2;
+const NODE_TYPE_N48: usize = 3;
+const NODE_TYPE_N256: usize = 4;
+const NODE_TYPE_LEAF: usize = 5;
+const NODE_TYPE_MASK: usize = 7;
+const NODE_PTR_MASK: usize = usize::MAX - NODE_TYPE_MASK;
+
+const LOCKED: u64 = 0b01;
+const OBSOLETE: u64 = 0b10;
+const VERSION: u64 = 0b100;
+
+const EMPTY_INDEX: u8 = u8::MAX;
+
+/// The operation saw a node that another writer changed, and starts again from the root.
+struct Restart;
+
+/// A spin lock whose version is bumped by every unlock.
+struct VersionLock(AtomicU64);
+
+impl VersionLock {
+    fn new() -> VersionLock {
+        VersionLock(AtomicU64::new(0))
+    }
+
+    /// Spins until the lock is taken, or returns false when the node has been replaced.
+    fn lock(&self) -> bool {
+        loop {
+            let version = self.0.load(Ordering::Relaxed);
+            if version & OBSOLETE != 0 {
+                return false;
+            }
+            if version & LOCKED == 0
+                && self
+                    .0
+                    .compare_exchange_weak(
+                        version,
+                        version | LOCKED,
+                        Ordering::Acquire,
+                        Ordering::Relaxed,
+                    )
+                    .is_ok()
+            {
+                return true;
+            }
+            std::hint::spin_loop();
+        }
+    }
+
+    fn unlock(&self) {
+        self.0.fetch_add(VERSION - LOCKED, Ordering::Release);
+    }
+
+    /// Unlocks a node that has been replaced, so every writer waiting for it restarts.
+    fn unlock_obsolete(&self) {
+        self.0
+            .fetch_add(VERSION - LOCKED + OBSOLETE, Ordering::Release);
+    }
+}
+
+/// Every inner node starts with a header, whose alignment leaves the low bits of a node
+/// address free for the node type tag of a [Ptr].
+#[repr(C, align(8))]
+struct Header {
+    lock: VersionLock,
+    /// The number of children, without the prefixed child.
+    count: AtomicU16,
+    /// The compressed path, which never changes once the node is in the tree.
+    prefix: Box<[u8]>,
+    /// The leaf of the key that ends at this node.
+    prefixed_child: AtomicUsize,
+}
+
+impl Header {
+    fn new(prefix: Box<[u8]>, prefixed_child: Ptr, count: usize) -> Header {
+        Header {
+            lock: VersionLock::new(),
+            count: AtomicU16::new(count as u16),
+            prefix,
+            prefixed_child: AtomicUsize::new(prefixed_child.0),
+        }
+    }
+}
+
+/// Node4 and Node16, which keep their keys sorted and are copied on every added or
+/// removed child.
+#[repr(C)]
+struct SortedNode<const N: usize> {
+    header: Header,
+    keys: [AtomicU8; N],
+    children: [AtomicUsize; N],
+}
+
+type Node4 = SortedNode<4>;
+type Node16 = SortedNode<16>;
+
+/// Children are only added in place, to the first unused slot, so a slot is never reused
+/// for another key byte while a reader may still hold its index.
+#[repr(C)]
+struct Node48 {
+    header: Header,
+    index: [AtomicU8; 256],
+    children: [AtomicUsize; 48],
+}
+
+#[repr(C)]
+struct Node256 {
+    header: Header,
+    children: [AtomicUsize; 256],
+}
+
+impl<const N: usize> SortedNode<N> {
+    fn new(header: Header, children: &[(u8, Ptr)]) -> SortedNode<N> {
+        SortedNode {
+            header,
+            keys: std::array::from_fn(|i| AtomicU8::new(children.get(i).map_or(0, |c| c.0))),
+            children: std::array::from_fn(|i| {
+                AtomicUsize::new(children.get(i).map_or(0, |c| (c.1).0))
+            }),
+        }
+    }
+
+    fn children(&self) -> impl Iterator<Item = (u8, Ptr)> + '_ {
+        let count = self.header.count.load(Ordering::Relaxed) as usize;
+        let keys = self.keys[..count]
+            .iter()
+            .map(|key| key.load(Ordering::Relaxed));
+        keys.zip(self.children[..count].iter().map(Ptr::load))
+    }
+
+    fn slot(&self, byte: u8) -> Option<&AtomicUsize> {
+        let count = self.header.count.load(Ordering::Relaxed) as usize;
+        (0..count)
+            .find(|&i| self.keys[i].load(Ordering::Relaxed) == byte)
+            .map(|i| &self.children[i])
+    }
+}
+
+impl Node48 {
+    fn new(header: Header, children: &[(u8, Ptr)]) -> Node48 {
+        let node = Node48 {
+            header,
+            index: std::array::from_fn(|_| AtomicU8::new(EMPTY_INDEX)),
+            children: std::array::from_fn(|i| {
+                AtomicUsize::new(children.get(i).map_or(0, |c| (c.1).0))
+            }),
+        };
+        for (slot, (byte, _)) in children.iter().enumerate() {
+            node.index[*byte as usize].store(slot as u8, Ordering::Relaxed);
+        }
+        node
+    }
+
+    fn slot(&self, byte: u8) -> Option<&AtomicUsize> {
+        match self.index[byte as usize].load(Ordering::Acquire) {
+            EMPTY_INDEX => None,
+            slot => Some(&self.children[slot as usize]),
+        }
+    }
+}
+
+impl Node256 {
+    fn new(header: Header, children: &[(u8, Ptr)]) -> Node256 {
+        let node = Node256 {
+            header,
+            children: std::array::from_fn(|_| AtomicUsize::new(0)),
+        };
+        for (byte, child) in children {
+            node.children[*byte as usize].store(child.0, Ordering::Relaxed);
+        }
+        node
+    }
+}
+
+enum NodeRef<'g> {
+    Node4(&'g Node4),
+    Node16(&'g Node16),
+    Node48(&'g Node48),
+    Node256(&'g Node256),
+}
+
+/// A leaf aligned as the inner nodes, as `Leaf` of small keys and values may have an
+/// alignment too small for the tag.
+#[repr(C, align(8))]
+struct TaggedLeaf<K: ArtKey, V>(Leaf<K, V>);
+
+/// A tagged pointer to a leaf or an inner node, as [ArtNode](crate::node::ArtNode).
+///
+/// A pointer loaded from the tree stays valid while the guard it was loaded under is
+/// pinned, which bounds the references handed out below.
+#[derive(Clone, Copy, PartialEq, Eq)]
+struct Ptr(usize);
+
+impl Ptr {
+    const NONE: Ptr = Ptr(0);
+
+    fn load(slot: &AtomicUsize) -> Ptr {
+        Ptr(slot.load(Ordering::Acquire))
+    }
+
+    fn store(self, slot: &AtomicUsize) {
+        slot.store(self.0, Ordering::Release)
+    }
+
+    fn leaf<K: ArtKey, V>(key: K, val: V) -> Ptr {
+        let leaf = Box::into_raw(Box::new(TaggedLeaf(Leaf::new(key, val))));
+        Ptr(leaf as usize | NODE_TYPE_LEAF)
+    }
+
+    fn is_none(self) -> bool {
+        self.0 == 0
+    }
+
+    fn is_leaf(self) -> bool {
+        self.0 & NODE_TYPE_MASK == NODE_TYPE_LEAF
+    }
+
+    fn as_leaf<K: ArtKey, V>(self, _guard: &Guard) -> &Leaf<K, V> {
+        debug_assert!(self.is_leaf());
+        unsafe { &(*((self.0 & NODE_PTR_MASK) as *const TaggedLeaf<K, V>)).0 }
+    }
+
+    fn as_inner<'g>(self, _guard: &'g Guard) -> NodeRef<'g> {
+        let ptr = self.0 & NODE_PTR_MASK;
+        match self.0 & NODE_TYPE_MASK {
+            NODE_TYPE_N4 => NodeRef::Node4(unsafe { &*(ptr as *const Node4) }),
+            NODE_TYPE_N16 => NodeRef::Node16(unsafe { &*(ptr as *const Node16) }),
+            NODE_TYPE_N48 => NodeRef::Node48(unsafe { &*(ptr as *const Node48) }),
+            NODE_TYPE_N256 => NodeRef::Node256(unsafe { &*(ptr as *const Node256) }),
+            _ => unreachable!(),
+        }
+    }
+
+    fn header(self, guard: &Guard) -> &Header {
+        match self.as_inner(guard) {
+            NodeRef::Node4(n4) => &n4.header,
+            NodeRef::Node16(n16) => &n16.header,
+            NodeRef::Node48(n48) => &n48.header,
+            NodeRef::Node256(n256) => &n256.header,
+        }
+    }
+
+    /// Builds an inner node of the smallest type that holds the children, which are sorted
+    /// by their key byte.
+    fn inner(prefix: Box<[u8]>, prefixed_child: Ptr, children: &[(u8, Ptr)]) -> Ptr {
+        let header = Header::new(prefix, prefixed_child, children.len());
+        let (ptr, tag) = match children.len() {
+            0..=4 => (
+                Box::into_raw(Box::new(Node4::new(header, children))) as usize,
+                NODE_TYPE_N4,
+            ),
+            5..=16 => (
+                Box::into_raw(Box::new(Node16::new(header, children))) as usize,
+                NODE_TYPE_N16,
+            ),
+            17..=48 => (
+                Box::into_raw(Box::new(Node48::new(header, children))) as usize,
+                NODE_TYPE_N48,
+            ),
+            _ => (
+                Box::into_raw(Box::new(Node256::new(header, children))) as usize,
+                NODE_TYPE_N256,
+            ),
+        };
+        Ptr(ptr | tag)
+    }
+
+    /// Builds a Node4 storing two children that split at the end of the prefix.
+    fn pair(prefix: &[u8], a: ((u8, bool), Ptr), b: ((u8, bool), Ptr)) -> Ptr {
+        let mut prefixed_child = Ptr::NONE;
+        let mut children = Vec::with_capacity(2);
+        for ((byte, valid), child) in [a, b] {
+            match valid {
+                true => children.push((byte, child)),
+                false => prefixed_child = child,
+            }
+        }
+        children.sort_unstable_by_key(|c| c.0);
+        Ptr::inner(prefix.into(), prefixed_child, &children)
+    }
+
+    fn slot(self, valid_key: (u8, bool), guard: &Guard) -> Option<&AtomicUsize> {
+        let (byte, valid) = valid_key;
+        if !valid {
+            return Some(&self.header(guard).prefixed_child);
+        }
+        match self.as_inner(guard) {
+            NodeRef::Node4(n4) => n4.slot(byte),
+            NodeRef::Node16(n16) => n16.slot(byte),
+            NodeRef::Node48(n48) => n48.slot(byte),
+            NodeRef::Node256(n256) => Some(&n256.children[byte as usize]),
+        }
+    }
+
+    fn child(self, valid_key: (u8, bool), guard: &Guard) -> Ptr {
+        self.slot(valid_key, guard).map_or(Ptr::NONE, Ptr::load)
+    }
+
+    /// Returns the prefixed child and the other children in key order. The node must be
+    /// locked for the result to stay current.
+    fn children(self, guard: &Guard) -> (Ptr, Vec<(u8, Ptr)>) {
+        let prefixed_child = Ptr::load(&self.header(guard).prefixed_child);
+        let mut children = Vec::new();
+        match self.as_inner(guard) {
+            NodeRef::Node4(n4) => children.extend(n4.children()),
+            NodeRef::Node16(n16) => children.extend(n16.children()),
+            NodeRef::Node48(n48) => {
+                for byte in 0..=u8::MAX {
+                    children.extend(n48.slot(byte).map(|slot| (byte, Ptr::load(slot))));
+                }
+            }
+            NodeRef::Node256(n256) => {
+                for (byte, slot) in n256.children.iter().enumerate() {
+                    children.push((byte as u8, Ptr::load(slot)));
+                }
+            }
+        }
+        children.retain(|(_, child)| !child.is_none());
+        (prefixed_child, children)
+    }
+
+    /// Returns whether a child can be added without replacing the node.
+    fn has_room(self, valid_key: (u8, bool), guard: &Guard) -> bool {
+        let count = self.header(guard).count.load(Ordering::Relaxed);
+        match self.as_inner(guard) {
+            _ if !valid_key.1 => true,
+            NodeRef::Node48(_) => count < 48,
+            NodeRef::Node256(_) => true,
+            _ => false,
+        }
+    }
+
+    /// Adds a child in place, the node must be locked and have room.
+    fn add_in_place(self, valid_key: (u8, bool), child: Ptr, guard: &Guard) -> bool {
+        if !self.has_room(valid_key, guard) || !self.child(valid_key, guard).is_none() {
+            return false;
+        }
+        let (byte, valid) = valid_key;
+        let header = self.header(guard);
+        let count = header.count.load(Ordering::Relaxed);
+        match self.as_inner(guard) {
+            _ if !valid => child.store(&header.prefixed_child),
+            NodeRef::Node48(n48) => {
+                // the child is stored before the index, so a reader finding the index
+                // finds the child too.
+                child.store(&n48.children[count as usize]);
+                n48.index[byte as usize].store(count as u8, Ordering::Release);
+                header.count.store(count + 1, Ordering::Release);
+            }
+            NodeRef::Node256(n256) => {
+                child.store(&n256.children[byte as usize]);
+                header.count.store(count + 1, Ordering::Release);
+            }
+            _ => unreachable!(),
+        }
+        true
+    }
+
+    /// Returns whether a child can be removed without replacing the node, that is when the
+    /// node keeps more than one child and does not shrink.
+    fn can_remove_in_place(self, valid_key: (u8, bool), guard: &Guard) -> bool {
+        let count = self.header(guard).count.load(Ordering::Relaxed);
+        match self.as_inner(guard) {
+            _ if !valid_key.1 => count >= 2,
+            NodeRef::Node256(_) => count > 48,
+            _ => false,
+        }
+    }
+
+    /// Removes a child in place, the node must be locked.
+    fn remove_in_place(self, valid_key: (u8, bool), guard: &Guard) -> bool {
+        if !self.can_remove_in_place(valid_key, guard) {
+            return false;
+        }
+        let header = self.header(guard);
+        match self.as_inner(guard) {
+            _ if !valid_key.1 => Ptr::NONE.store(&header.prefixed_child),
+            NodeRef::Node256(n256) => {
+                Ptr::NONE.store(&n256.children[valid_key.0 as usize]);
+                header.count.fetch_sub(1, Ordering::Release);
+            }
+            _ => unreachable!(),
+        }
+        true
+    }
+
+    /// Frees the inner node, but not its children.
+    fn free_node(self) {
+        let ptr = self.0 & NODE_PTR_MASK;
+        unsafe {
+            match self.0 & NODE_TYPE_MASK {
+                NODE_TYPE_N4 => drop(Box::from_raw(ptr as *mut Node4)),
+                NODE_TYPE_N16 => drop(Box::from_raw(ptr as *mut Node16)),
+                NODE_TYPE_N48 => drop(Box::from_raw(ptr as *mut Node48)),
+                NODE_TYPE_N256 => drop(Box::from_raw(ptr as *mut Node256)),
+                _ => unreachable!(),
+            }
+        }
+    }
+
+    fn free_leaf<K: ArtKey, V>(self) {
+        drop(unsafe { Box::from_raw((self.0 & NODE_PTR_MASK) as *mut TaggedLeaf<K, V>) });
+    }
+
+    /// Frees the subtree, the caller must own it.
+    fn free_tree<K: ArtKey, V>(self, guard: &Guard) {
+        let mut stack = vec![self];
+        while let Some(node) = stack.pop() {
+            if node.is_none() {
+                continue;
+            }
+            if node.is_leaf() {
+                node.free_leaf::<K, V>();
+                continue;
+            }
+            let (prefixed_child, children) = node.children(guard);
+            stack.push(prefixed_child);
+            stack.extend(children.into_iter().map(|(_, child)| child));
+            node.free_node();
+        }
+    }
+
+    /// Frees a replaced inner node once no reader can reach it.
+    fn retire_node(self, guard: &Guard) {
+        unsafe { guard.defer_unchecked(move || self.free_node()) }
+    }
+
+    /// Frees a replaced or removed leaf once no reader can reach it.
+    fn retire_leaf<K: ArtKey + Send + 'static, V: Send + 'static>(self, guard: &Guard) {
+        unsafe { guard.defer_unchecked(move || self.free_leaf::<K, V>()) }
+    }
+}
+
+/// The slot a node was loaded from, either the root or a child slot of an inner node.
+#[derive(Clone, Copy)]
+enum Parent {
+    Root,
+    Node(Ptr, (u8, bool)),
+}
+
+/// A concurrent adaptive radix tree.
+///
+/// Lookups take no locks and never wait for writers, while a writer only locks the one or
+/// two nodes it changes, so writers to different parts of the tree do not block each other.
+///
+/// Values are returned by cloning, as a replaced value may still be read by other threads.
+/// Replaced keys and values are dropped later, possibly on another thread, which is why
+/// inserting and removing require `K` and `V` to be `Send + 'static`.
+///
+/// Unlike [Art](crate::Art), the compressed paths are stored in full in every node.
+///
+/// Only available with the `sync` feature, which brings in `crossbeam-epoch`.
+///
+/// # Examples
+/// ```rust
+/// use artful::ConcurrentArt;
+///
+/// let art = ConcurrentArt::<u32, u32>::new();
+/// std::thread::scope(|s| {
+///     for t in 0..4 {
+///         let art = &art;
+///         s.spawn(move || {
+///             for i in 0..100 {
+///                 art.insert(t * 100 + i, i);
+///             }
+///         });
+///     }
+/// });
+/// assert_eq!(art.len(), 400);
+/// assert_eq!(art.get(&250), Some(50));
+/// ```
+pub struct ConcurrentArt<K: ArtKey, V> {
+    root: AtomicUsize,
+    /// Locks the root slot, as the lock of the parent of the root node.
+    root_lock: VersionLock,
+    size: AtomicUsize,
+    marker: PhantomData<(Box<Leaf<K, V>>, *const ())>,
+}
+
+unsafe impl<K: ArtKey + Send + Sync, V: Send + Sync> Send for ConcurrentArt<K, V> {}
+unsafe impl<K: ArtKey + Send + Sync, V: Send + Sync> Sync for ConcurrentArt<K, V> {}
+
+impl<K: ArtKey, V> Default for ConcurrentArt<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: ArtKey, V> Drop for ConcurrentArt<K, V> {
+    fn drop(&mut self) {
+        // no other thread can reach the tree any more.
+        let guard = unsafe { epoch::unprotected() };
+        Ptr::load(&self.root).free_tree::<K, V>(guard);
+    }
+}
+
+impl<K: ArtKey, V> ConcurrentArt<K, V> {
+    pub fn new() -> ConcurrentArt<K, V> {
+        ConcurrentArt {
+            root: AtomicUsize::new(0),
+            root_lock: VersionLock::new(),
+            size: AtomicUsize::new(0),
+            marker: PhantomData,
+        }
+    }
+
+    /// Returns the number of keys, which may be stale while other threads write.
+    pub fn len(&self) -> usize {
+        self.size.load(Ordering::Relaxed)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns true if the tree contains a value for the key.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use artful::ConcurrentArt;
+    ///
+    /// let art = ConcurrentArt::<String, u32>::new();
+    /// art.insert("abc".to_string(), 1);
+    /// assert!(art.contains_key("abc"));
+    /// assert!(!art.contains_key("ab"));
+    /// ```
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: ArtKey + ?Sized,
+    {
+        let guard = &epoch::pin();
+        self.find(key.get_bytes().as_ref(), guard).is_some()
+    }
+
+    /// Returns a clone of the value corresponding to the key.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use artful::ConcurrentArt;
+    ///
+    /// let art = ConcurrentArt::<String, u32>::new();
+    /// art.insert("abc".to_string(), 1);
+    /// assert_eq!(art.get("abc"), Some(1));
+    /// assert_eq!(art.get("abd"), None);
+    /// ```
+    pub fn get<Q>(&self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: ArtKey + ?Sized,
+        V: Clone,
+    {
+        let guard = &epoch::pin();
+        self.find(key.get_bytes().as_ref(), guard)
+            .map(|leaf| leaf.val.clone())
+    }
+
+    fn find<'g>(&self, key: &[u8], guard: &'g Guard) -> Option<&'g Leaf<K, V>> {
+        let mut node = Ptr::load(&self.root);
+        let mut depth = 0;
+        loop {
+            if node.is_none() {
+                return None;
+            }
+            if node.is_leaf() {
+                let leaf = node.as_leaf::<K, V>(guard);
+                return leaf.matches(key).then_some(leaf);
+            }
+
+            let prefix = &node.header(guard).prefix;
+            if !key[depth..].starts_with(prefix) {
+                return None;
+            }
+            depth += prefix.len();
+            node = node.child(valid(key, depth), guard);
+            depth += 1;
+        }
+    }
+
+    /// Locks the parent of a node, after checking that the node is still its child.
+    fn lock_parent<'g>(
+        &'g self,
+        parent: Parent,
+        node: Ptr,
+        guard: &'g Guard,
+    ) -> Result<(&'g VersionLock, &'g AtomicUsize), Restart> {
+        let (lock, slot) = match parent {
+            Parent::Root => (&self.root_lock, Some(&self.root)),
+            Parent::Node(parent, valid_key) => {
+                (&parent.header(guard).lock, parent.slot(valid_key, guard))
+            }
+        };
+        if !lock.lock() {
+            return Err(Restart);
+        }
+        match slot {
+            Some(slot) if Ptr::load(slot) == node => Ok((lock, slot)),
+            _ => {
+                lock.unlock();
+                Err(Restart)
+            }
+        }
+    }
+
+    /// Swaps a node for another in its parent.
+    fn replace(&self, parent: Parent, old: Ptr, new: Ptr, guard: &Guard) -> Result<(), Restart> {
+        let (lock, slot) = self.lock_parent(parent, old, guard)?;
+        new.store(slot);
+        lock.unlock();
+        Ok(())
+    }
+}
+
+impl<K: ArtKey + Send + 'static, V: Clone + Send + 'static> ConcurrentArt<K, V> {
+    /// Inserts a key-value pair into the tree, and returns a clone of the old value if the
+    /// key was present.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use artful::ConcurrentArt;
+    ///
+    /// let art = ConcurrentArt::<u64, &str>::new();
+    /// assert_eq!(art.insert(7, "a"), None);
+    /// assert_eq!(art.insert(7, "b"), Some("a"));
+    /// assert_eq!(art.get(&7), Some("b"));
+    /// ```
+    pub fn insert(&self, key: K, val: V) -> Option<V> {
+        let guard = &epoch::pin();
+        let leaf = Ptr::leaf(key, val);
+        // the leaf is boxed, so its key stays in place while the leaf is linked.
+        let key = leaf.as_leaf::<K, V>(guard).key.get_bytes();
+        loop {
+            if let Ok(old) = self.try_insert(key.as_ref(), leaf, guard) {
+                if old.is_none() {
+                    self.size.fetch_add(1, Ordering::Relaxed);
+                }
+                return old;
+            }
+        }
+    }
+
+    fn try_insert(&self, key: &[u8], leaf: Ptr, guard: &Guard) -> Result<Option<V>, Restart> {
+        let mut parent = Parent::Root;
+        let mut node = Ptr::load(&self.root);
+        let mut depth = 0;
+        loop {
+            if node.is_none() {
+                // empty child slots are handled by add_child, so this is an empty tree.
+                self.replace(parent, node, leaf, guard)?;
+                return Ok(None);
+            }
+
+            if node.is_leaf() {
+                let old = node.as_leaf::<K, V>(guard);
+                let old_key = old.key.get_bytes();
+                let old_key = old_key.as_ref();
+                if old_key == key {
+                    self.replace(parent, node, leaf, guard)?;
+                    node.retire_leaf::<K, V>(guard);
+                    return Ok(Some(old.val.clone()));
+                }
+
+                let lcp = old_key[depth..]
+                    .iter()
+                    .zip(&key[depth..])
+                    .take_while(|(a, b)| a == b)
+                    .count();
+                let new_depth = depth + lcp;
+                let expanded = Ptr::pair(
+                    &key[depth..new_depth],
+                    (valid(old_key, new_depth), node),
+                    (valid(key, new_depth), leaf),
+                );
+                if let Err(restart) = self.replace(parent, node, expanded, guard) {
+                    expanded.free_node();
+                    return Err(restart);
+                }
+                return Ok(None);
+            }
+
+            let prefix = &node.header(guard).prefix;
+            let matched = prefix
+                .iter()
+                .zip(&key[depth..])
+                .take_while(|(a, b)| a == b)
+                .count();
+            if matched < prefix.len() {
+                self.compression(parent, node, depth, matched, key, leaf, guard)?;
+                return Ok(None);
+            }
+
+            depth += matched;
+            let valid_key = valid(key, depth);
+            let child = node.child(valid_key, guard);
+            if child.is_none() {
+                self.add_child(parent, node, valid_key, leaf, guard)?;
+                return Ok(None);
+            }
+            parent = Parent::Node(node, valid_key);
+            node = child;
+            depth += 1;
+        }
+    }
+
+    /// Splits the compressed path of a node that mismatches the key at `matched`, the node
+    /// is replaced by a copy with the rest of the path under a new Node4.
+    #[allow(clippy::too_many_arguments)]
+    fn compression(
+        &self,
+        parent: Parent,
+        node: Ptr,
+        depth: usize,
+        matched: usize,
+        key: &[u8],
+        leaf: Ptr,
+        guard: &Guard,
+    ) -> Result<(), Restart> {
+        let (parent_lock, slot) = self.lock_parent(parent, node, guard)?;
+        let header = node.header(guard);
+        if !header.lock.lock() {
+            parent_lock.unlock();
+            return Err(Restart);
+        }
+
+        let (prefixed_child, children) = node.children(guard);
+        let prefix = &header.prefix;
+        let copy = Ptr::inner(prefix[matched + 1..].into(), prefixed_child, &children);
+        let split = Ptr::pair(
+            &prefix[..matched],
+            ((prefix[matched], true), copy),
+            (valid(key, depth + matched), leaf),
+        );
+        split.store(slot);
+        header.lock.unlock_obsolete();
+        parent_lock.unlock();
+        node.retire_node(guard);
+        Ok(())
+    }
+
+    /// Adds a leaf to a node without a child for the key byte, in place when the node has
+    /// room, or else by replacing the node with a grown copy.
+    fn add_child(
+        &self,
+        parent: Parent,
+        node: Ptr,
+        valid_key: (u8, bool),
+        leaf: Ptr,
+        guard: &Guard,
+    ) -> Result<(), Restart> {
+        let header = node.header(guard);
+        if node.has_room(valid_key, guard) {
+            if !header.lock.lock() {
+                return Err(Restart);
+            }
+            let added = node.add_in_place(valid_key, leaf, guard);
+            header.lock.unlock();
+            return if added { Ok(()) } else { Err(Restart) };
+        }
+
+        let (parent_lock, slot) = self.lock_parent(parent, node, guard)?;
+        if !header.lock.lock() {
+            parent_lock.unlock();
+            return Err(Restart);
+        }
+        let (prefixed_child, mut children) = node.children(guard);
+        let pos = children.partition_point(|c| c.0 < valid_key.0);
+        if children.get(pos).is_some_and(|c| c.0 == valid_key.0) {
+            // another writer added the key byte in place before the node was locked.
+            header.lock.unlock();
+            parent_lock.unlock();
+            return Err(Restart);
+        }
+        children.insert(pos, (valid_key.0, leaf));
+        let grown = Ptr::inner(header.prefix.clone(), prefixed_child, &children);
+        grown.store(slot);
+        header.lock.unlock_obsolete();
+        parent_lock.unlock();
+        node.retire_node(guard);
+        Ok(())
+    }
+
+    /// Removes the key from the tree, and returns a clone of its value if it was present.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use artful::ConcurrentArt;
+    ///
+    /// let art = ConcurrentArt::<u64, &str>::new();
+    /// art.insert(7, "a");
+    /// assert_eq!(art.remove(&7), Some("a"));
+    /// assert_eq!(art.remove(&7), None);
+    /// assert!(art.is_empty());
+    /// ```
+    pub fn remove<Q>(&self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: ArtKey + ?Sized,
+    {
+        let guard = &epoch::pin();
+        let key = key.get_bytes();
+        loop {
+            if let Ok(removed) = self.try_remove(key.as_ref(), guard) {
+                if removed.is_some() {
+                    self.size.fetch_sub(1, Ordering::Relaxed);
+                }
+                return removed;
+            }
+        }
+    }
+
+    fn try_remove(&self, key: &[u8], guard: &Guard) -> Result<Option<V>, Restart> {
+        let mut parent = Parent::Root;
+        let mut node = Ptr::load(&self.root);
+        let mut depth = 0;
+        loop {
+            if node.is_none() {
+                return Ok(None);
+            }
+
+            if node.is_leaf() {
+                // leaves below the root are removed from their parent, see below.
+                let leaf = node.as_leaf::<K, V>(guard);
+                if !leaf.matches(key) {
+                    return Ok(None);
+                }
+                self.replace(parent, node, Ptr::NONE, guard)?;
+                node.retire_leaf::<K, V>(guard);
+                return Ok(Some(leaf.val.clone()));
+            }
+
+            let prefix = &node.header(guard).prefix;
+            if !key[depth..].starts_with(prefix) {
+                return Ok(None);
+            }
+            depth += prefix.len();
+            let valid_key = valid(key, depth);
+            let child = node.child(valid_key, guard);
+            if child.is_leaf() {
+                let leaf = child.as_leaf::<K, V>(guard);
+                if !leaf.matches(key) {
+                    return Ok(None);
+                }
+                self.remove_child(parent, node, valid_key, child, guard)?;
+                child.retire_leaf::<K, V>(guard);
+                return Ok(Some(leaf.val.clone()));
+            }
+            parent = Parent::Node(node, valid_key);
+            node = child;
+            depth += 1;
+        }
+    }
+
+    /// Removes a leaf from its node, in place when the node neither shrinks nor is left
+    /// with a single child, or else by replacing the node with a shrunk copy. A node left
+    /// with a single child is replaced by the child, whose path then absorbs the node's.
+    fn remove_child(
+        &self,
+        parent: Parent,
+        node: Ptr,
+        valid_key: (u8, bool),
+        leaf: Ptr,
+        guard: &Guard,
+    ) -> Result<(), Restart> {
+        let header = node.header(guard);
+        if node.can_remove_in_place(valid_key, guard) {
+            if !header.lock.lock() {
+                return Err(Restart);
+            }
+            let removed =
+                node.child(valid_key, guard) == leaf && node.remove_in_place(valid_key, guard);
+            header.lock.unlock();
+            return if removed { Ok(()) } else { Err(Restart) };
+        }
+
+        let (parent_lock, slot) = self.lock_parent(parent, node, guard)?;
+        if !header.lock.lock() {
+            parent_lock.unlock();
+            return Err(Restart);
+        }
+        if node.child(valid_key, guard) != leaf {
+            header.lock.unlock();
+            parent_lock.unlock();
+            return Err(Restart);
+        }
+
+        let (mut prefixed_child, mut children) = node.children(guard);
+        match valid_key {
+            (byte, true) => children.retain(|c| c.0 != byte),
+            (_, false) => prefixed_child = Ptr::NONE,
+        }
+        let mut merged_child = None;
+        let shrunk = match (prefixed_child.is_none(), children.as_slice()) {
+            // a prefixed child is always a leaf.
+            (false, []) => prefixed_child,
+            (true, &[(_, child)]) if child.is_leaf() => child,
+            (true, &[(byte, child)]) => {
+                let child_header = child.header(guard);
+                if !child_header.lock.lock() {
+                    header.lock.unlock();
+                    parent_lock.unlock();
+                    return Err(Restart);
+                }
+                merged_child = Some(child);
+                let prefix = [&header.prefix[..], &[byte], &child_header.prefix[..]].concat();
+                let (prefixed_child, children) = child.children(guard);
+                Ptr::inner(prefix.into(), prefixed_child, &children)
+            }
+            _ => Ptr::inner(header.prefix.clone(), prefixed_child, &children),
+        };
+        shrunk.store(slot);
+        if let Some(child) = merged_child {
+            child.header(guard).lock.unlock_obsolete();
+            child.retire_node(guard);
+        }
+        header.lock.unlock_obsolete();
+        parent_lock.unlock();
+        node.retire_node(guard);
+        Ok(())
+    }
+}
+
+fn valid(key: &[u8], depth: usize) -> (u8, bool) {
+    match key.get(depth) {
+        Some(byte) => (*byte, true),
+        None => (0, false),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{ConcurrentArt, TaggedLeaf, NODE_TYPE_MASK};
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn against_btree() {
+        let art = ConcurrentArt::<String, usize>::new();
+        let mut btree = BTreeMap::new();
+        let alphabet = ['a', 'b', 'c', 'd', 'e'];
+        let mut keys = vec![String::new()];
+        for len in 1..=3 {
+            let mut word = vec![0; len];
+            loop {
+                keys.push(word.iter().map(|&i| alphabet[i]).collect());
+                match word.iter().rposition(|&i| i + 1 < alphabet.len()) {
+                    Some(pos) => {
+                        word[pos] += 1;
+                        word[pos + 1..].iter_mut().for_each(|i| *i = 0);
+                    }
+                    None => break,
+                }
+            }
+        }
+
+        for (i, key) in keys.iter().enumerate() {
+            assert_eq!(art.insert(key.clone(), i), btree.insert(key.clone(), i));
+        }
+        for (i, key) in keys.iter().enumerate().filter(|(i, _)| i % 3 != 1) {
+            assert_eq!(art.remove(key), btree.remove(key));
+            assert_eq!(art.remove(key), None, "removed {} twice", i);
+        }
+        assert_eq!(art.len(), btree.len());
+        for key in &keys {
+            assert_eq!(art.get(key), btree.get(key).copied());
+        }
+    }
+
+    #[test]
+    fn grows_and_shrinks_node256() {
+        let art = ConcurrentArt::<u16, u16>::new();
+        for key in 0..1024 {
+            assert_eq!(art.insert(key, key), None);
+        }
+        for key in (0..1024).filter(|key| key % 7 != 0) {
+            assert_eq!(art.remove(&key), Some(key));
+        }
+        for key in 0..1024 {
+            assert_eq!(art.get(&key), (key % 7 == 0).then_some(key));
+        }
+        assert_eq!(art.len(), 147);
+    }
+
+    #[test]
+    fn deep_tree_on_small_stack() {
+        // as the test of the same name for Art, the tree is a chain of one Node4 per key.
+        const DEPTH: usize = 3000;
+        let worker = std::thread::Builder::new()
+            .stack_size(64 << 10)
+            .spawn(|| {
+                let art = ConcurrentArt::<Vec<u8>, usize>::new();
+                let key = |i: usize| {
+                    let mut key = vec![b'a'; i];
+                    key.push(b'b');
+                    key
+                };
+                for i in 0..DEPTH {
+                    assert_eq!(art.insert(key(i), i), None);
+                }
+                assert_eq!(art.get(&key(DEPTH - 1)), Some(DEPTH - 1));
+                drop(art);
+            })
+            .unwrap();
+        worker.join().unwrap();
+    }
+
+    #[test]
+    fn tags_leaves_of_small_types() {
+        assert_eq!(
+            std::mem::align_of::<TaggedLeaf<bool, ()>>(),
+            NODE_TYPE_MASK + 1
+        );
+        let art = ConcurrentArt::<bool, ()>::new();
+        assert_eq!(art.insert(true, ()), None);
+        assert_eq!(art.insert(false, ()), None);
+        assert_eq!(art.get(&true), Some(()));
+        assert_eq!(art.remove(&false), Some(()));
+        assert!(!art.contains_key(&false));
+    }
+
+    #[test]
+    fn concurrent_writers_and_readers() {
+        const THREADS: u64 = 4;
+        const KEYS: u64 = 20_000;
+
+        let art = ConcurrentArt::<u64, u64>::new();
+        // sparse keys split compressed paths, dense low bytes grow Node256.
+        let key = |i: u64| if i % 2 == 0 { i } else { i << 32 | i };
+        std::thread::scope(|s| {
+            for t in 0..THREADS {
+                let art = &art;
+                s.spawn(move || {
+                    for i in (t..KEYS).step_by(THREADS as usize) {
+                        assert_eq!(art.insert(key(i), i), None);
+                    }
+                    for i in (t..KEYS).step_by(THREADS as usize).filter(|i| i % 3 == 0) {
+                        assert_eq!(art.remove(&key(i)), Some(i));
+                    }
+                });
+                s.spawn(move || {
+                    for i in 0..KEYS {
+                        if let Some(val) = art.get(&key(i)) {
+                            assert_eq!(val, i);
+                        }
+                    }
+                });
+            }
+        });
+
+        for i in 0..KEYS {
+            assert_eq!(art.get(&key(i)), (i % 3 != 0).then_some(i));
+        }
+        assert_eq!(art.len(), (0..KEYS).filter(|i| i % 3 != 0).count());
+    }
+}
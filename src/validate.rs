@@ -0,0 +1,170 @@
+use std::fmt;
+
+use crate::node::{
+    ArtNode, NODE_TYPE_LEAF, NODE_TYPE_N16, NODE_TYPE_N256, NODE_TYPE_N4, NODE_TYPE_N48,
+};
+use crate::ArtKey;
+
+/// A broken invariant of a tree, returned by [Art::validate](crate::Art::validate).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidationError {
+    /// The key bytes leading to the bad node: the compressed paths of its ancestors and the
+    /// key bytes selecting each child on the way.
+    pub path: Vec<u8>,
+    pub violation: Violation,
+}
+
+/// The invariant broken by a node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Violation {
+    /// The children count in the header differs from the number of children found.
+    ChildCount { header: usize, found: usize },
+    /// A Node4 or Node16 holds a child past its children count, or a Node48 holds a child
+    /// missing from its index.
+    StrayChild,
+    /// The key bytes of a Node4 or Node16 are not strictly increasing.
+    UnsortedKeys,
+    /// The Node48 index of the key byte is out of range, or points to an empty child or to
+    /// the child of another key byte.
+    BadIndex { byte: u8 },
+    /// The node holds fewer children than a removal leaves in its type before shrinking it.
+    Underfull { children: usize },
+    /// The prefixed child is an inner node rather than a leaf.
+    InnerPrefixedChild,
+    /// The compressed path of the node differs from the key of a leaf below it.
+    PartialMismatch,
+    /// The key of the leaf does not match the path to it.
+    KeyMismatch,
+    /// The size of the tree differs from its number of leaves.
+    Size { size: usize, leaves: usize },
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.violation {
+            Violation::ChildCount { header, found } => {
+                write!(f, "header counts {} children but {} found", header, found)?
+            }
+            Violation::StrayChild => write!(f, "child outside the counted children")?,
+            Violation::UnsortedKeys => write!(f, "key bytes are not sorted")?,
+            Violation::BadIndex { byte } => write!(f, "bad index for key byte {}", byte)?,
+            Violation::Underfull { children } => {
+                write!(f, "node is underfull with {} children", children)?
+            }
+            Violation::InnerPrefixedChild => write!(f, "prefixed child is an inner node")?,
+            Violation::PartialMismatch => write!(f, "compressed path differs from a leaf")?,
+            Violation::KeyMismatch => write!(f, "leaf key differs from its path")?,
+            Violation::Size { size, leaves } => {
+                write!(f, "size is {} but {} leaves found", size, leaves)?
+            }
+        }
+        write!(f, " at path {:?}", self.path)
+    }
+}
+
+impl std::error::Error for ValidationError {}
+
+/// How a node is reached from its parent.
+#[derive(Clone, Copy)]
+enum Edge {
+    Root,
+    Prefixed,
+    Child(u8),
+}
+
+/// Walks the tree in key order and returns the first broken invariant.
+pub(crate) fn validate<K: ArtKey, V, const MAX_PARTIAL_LEN: usize>(
+    root: &ArtNode<K, V, MAX_PARTIAL_LEN>,
+    size: usize,
+) -> Result<(), ValidationError> {
+    let error = |path: &[u8], violation| ValidationError {
+        path: path.to_vec(),
+        violation,
+    };
+
+    let mut path = Vec::new();
+    // the start and the end in `path` of the compressed path of every node above.
+    let mut prefixes: Vec<(usize, usize)> = Vec::new();
+    let mut leaves = 0;
+    let mut stack = Vec::new();
+    if !root.is_none() {
+        stack.push((root, 0, Edge::Root, 0));
+    }
+
+    while let Some((node, path_len, edge, level)) = stack.pop() {
+        path.truncate(path_len);
+        prefixes.truncate(level);
+        if let Edge::Child(byte) = edge {
+            path.push(byte);
+        }
+
+        if node.node_type() == NODE_TYPE_LEAF {
+            leaves += 1;
+            let key = node.static_cast_ref_leaf().key.get_bytes();
+            let key = key.as_ref();
+            let matched = key.iter().zip(&path).take_while(|(a, b)| a == b).count();
+            if matched < path.len() {
+                // the first byte off the key is either in the compressed path of an
+                // ancestor, or the key byte selecting a child on the way.
+                let ancestor = prefixes
+                    .iter()
+                    .find(|(start, end)| *start <= matched && matched < *end);
+                return match ancestor {
+                    Some((start, _)) => Err(error(&path[..*start], Violation::PartialMismatch)),
+                    None => Err(error(&path, Violation::KeyMismatch)),
+                };
+            }
+            if matches!(edge, Edge::Prefixed) && key.len() != path.len() {
+                return Err(error(&path, Violation::KeyMismatch));
+            }
+            continue;
+        }
+
+        node.check().map_err(|violation| error(&path, violation))?;
+        let count = node.header().non_null_children as usize;
+        let prefixed_child = node.prefixed_child();
+        let underfull = match node.node_type() {
+            NODE_TYPE_N4 => count + (prefixed_child.is_some() as usize) < 2,
+            NODE_TYPE_N16 => count < 5,
+            NODE_TYPE_N48 => count < 17,
+            NODE_TYPE_N256 => count < 49,
+            _ => unreachable!(),
+        };
+        if underfull {
+            return Err(error(&path, Violation::Underfull { children: count }));
+        }
+
+        let depth = path.len();
+        let partial = node.header().partial;
+        let partial_len = partial.len as usize;
+        let stored = partial_len.min(MAX_PARTIAL_LEN);
+        path.extend_from_slice(&partial.data[..stored]);
+        if partial_len > stored {
+            // the rest of an optimistic path is taken from the minimum leaf, every leaf
+            // below is then checked against it.
+            let leaf = ArtNode::minimum_child(node).expect("an inner node has a leaf");
+            let key = leaf.key.get_bytes();
+            match key.as_ref().get(depth + stored..depth + partial_len) {
+                Some(rest) => path.extend_from_slice(rest),
+                None => return Err(error(&path[..depth], Violation::PartialMismatch)),
+            }
+        }
+        prefixes.push((depth, path.len()));
+
+        let children = node.children();
+        for (byte, child) in children.into_iter().rev() {
+            stack.push((child, path.len(), Edge::Child(byte), level + 1));
+        }
+        if let Some(child) = prefixed_child {
+            if child.node_type() != NODE_TYPE_LEAF {
+                return Err(error(&path[..depth], Violation::InnerPrefixedChild));
+            }
+            stack.push((child, path.len(), Edge::Prefixed, level + 1));
+        }
+    }
+
+    if leaves != size {
+        return Err(error(&[], Violation::Size { size, leaves }));
+    }
+    Ok(())
+}
@@ -5,7 +5,7 @@ mod dataset_test {
     use std::io::{BufRead, BufReader};
     use std::path::PathBuf;
 
-    const FILES: [&'static str; 3] = [
+    const FILES: [&str; 3] = [
         "tests/data/words.txt",
         "tests/data/uuid.txt",
         "tests/data/hsk_words.txt",
@@ -18,7 +18,7 @@ mod dataset_test {
     fn read_dataset(path: &str) -> BufReader<File> {
         let mut manifest = manifest_dir();
         manifest.push(path);
-        let file = File::open(manifest).expect(format!("open {} failed", path).as_str());
+        let file = File::open(manifest).unwrap_or_else(|_| panic!("open {} failed", path));
         BufReader::new(file)
     }
 
@@ -26,7 +26,7 @@ mod dataset_test {
     fn test_insert() {
         for file in FILES.iter() {
             let mut art = Art::<String, String>::new();
-            for (index, line) in read_dataset(*file).lines().enumerate() {
+            for (index, line) in read_dataset(file).lines().enumerate() {
                 let line = line.expect("read words line failed");
                 assert_eq!(art.size(), index);
                 assert_eq!(art.insert(line.clone(), line.clone()), None);
@@ -39,7 +39,7 @@ mod dataset_test {
         for file in FILES.iter() {
             let mut art = Art::<String, String>::new();
             let mut lines = 0;
-            for line in read_dataset(*file).lines() {
+            for line in read_dataset(file).lines() {
                 let line = line.expect("read words line failed");
                 assert_eq!(art.insert(line.clone(), line.clone()), None);
                 assert_eq!(art.get(&line), Some(&line));
@@ -54,7 +54,7 @@ mod dataset_test {
         for file in FILES.iter() {
             let mut art = Art::<String, String>::new();
             let mut lines = 0;
-            let read_buf = read_dataset(*file);
+            let read_buf = read_dataset(file);
             for line in read_buf.lines() {
                 let line = line.expect("read words line failed");
                 assert_eq!(art.insert(line.clone(), line.clone()), None);
@@ -62,7 +62,7 @@ mod dataset_test {
             }
             assert_eq!(art.size(), lines);
 
-            let read_buf = read_dataset(*file);
+            let read_buf = read_dataset(file);
             for (index, line) in read_buf.lines().enumerate() {
                 let line = line.expect("read words line failed");
                 assert_eq!(art.size(), lines - index);
@@ -78,7 +78,7 @@ mod dataset_test {
     fn test_get_insert_remove_get() {
         for file in FILES.iter() {
             let mut art = Art::<String, String>::new();
-            let read_buf = read_dataset(*file);
+            let read_buf = read_dataset(file);
             for line in read_buf.lines() {
                 let line = line.expect("read words line failed");
                 assert_eq!(art.get(&line), None);
@@ -97,7 +97,7 @@ mod dataset_test {
         for file in FILES.iter() {
             let mut art = Art::<String, String>::new();
             let mut lines = 0;
-            let read_buf = read_dataset(*file);
+            let read_buf = read_dataset(file);
             for line in read_buf.lines() {
                 let line = line.expect("read words line failed");
                 assert_eq!(art.insert(line.clone(), line.clone()), None);
@@ -106,11 +106,11 @@ mod dataset_test {
             assert_eq!(art.size(), lines);
 
             // random keys in the byte len range randomly
-            let read_buf = read_dataset(*file);
+            let read_buf = read_dataset(file);
             let mut hit_lines = 0;
-            for (_index, line) in read_buf.lines().enumerate() {
+            for line in read_buf.lines() {
                 let line = line.expect("read words line failed");
-                let byte_len = line.as_str().as_bytes().len();
+                let byte_len = line.len();
                 if byte_len >= byte_len_range.0 && byte_len <= byte_len_range.1 {
                     assert_eq!(art.remove(&line), Some(line.clone()));
                     hit_lines += 1;
@@ -119,10 +119,10 @@ mod dataset_test {
             assert_eq!(art.size(), lines - hit_lines);
 
             // get insert get
-            let read_buf = read_dataset(*file);
-            for (_index, line) in read_buf.lines().enumerate() {
+            let read_buf = read_dataset(file);
+            for line in read_buf.lines() {
                 let line = line.expect("read words line failed");
-                let byte_len = line.as_str().as_bytes().len();
+                let byte_len = line.len();
                 if byte_len >= byte_len_range.0 && byte_len <= byte_len_range.1 {
                     assert_eq!(art.get(&line), None);
                     assert_eq!(art.insert(line.clone(), line.clone()), None);
@@ -133,6 +133,45 @@ mod dataset_test {
         }
     }
 
+    #[test]
+    fn test_insert_iter() {
+        for file in FILES.iter() {
+            let mut art = Art::<String, String>::new();
+            let mut lines = Vec::new();
+            for line in read_dataset(file).lines() {
+                let line = line.expect("read words line failed");
+                assert_eq!(art.insert(line.clone(), line.clone()), None);
+                lines.push(line);
+            }
+            lines.sort();
+            lines.dedup();
+
+            assert_eq!(art.iter().len(), lines.len());
+            assert!(art.iter().map(|(k, _)| k).eq(lines.iter()));
+            assert!(art.values().rev().eq(lines.iter().rev()));
+        }
+    }
+
+    #[test]
+    fn test_from_sorted_iter() {
+        for file in FILES.iter() {
+            let mut lines: Vec<String> = read_dataset(file)
+                .lines()
+                .map(|line| line.expect("read words line failed"))
+                .collect();
+            lines.sort();
+            lines.dedup();
+
+            let pairs = lines.iter().map(|line| (line.clone(), line.clone()));
+            let art = Art::<String, String>::from_sorted_iter(pairs).unwrap();
+            assert_eq!(art.size(), lines.len());
+            assert!(art.keys().eq(lines.iter()));
+            for line in lines.iter() {
+                assert_eq!(art.get(line), Some(line));
+            }
+        }
+    }
+
     #[test]
     fn test_short_prefixed() {
         let mut art = Art::<String, i32>::new();
@@ -0,0 +1,127 @@
+//! Random sequences of operations applied to `Art` and to a `BTreeMap`, which must agree.
+
+use std::collections::BTreeMap;
+use std::ops::Bound;
+
+use artful::Art;
+use proptest::prelude::*;
+
+#[derive(Debug, Clone)]
+enum Op {
+    Insert(Vec<u8>, u32),
+    Remove(Vec<u8>),
+    Get(Vec<u8>),
+    Range(Bound<Vec<u8>>, Bound<Vec<u8>>),
+}
+
+/// Keys that share long compressed paths, end where other keys go on, and fill single
+/// nodes up to their grow and shrink boundaries.
+fn key() -> impl Strategy<Value = Vec<u8>> {
+    const STEMS: [&[u8]; 5] = [
+        b"",
+        b"k",
+        b"stem/",
+        b"a long shared stem/",
+        b"a long shared stem/x",
+    ];
+    let stem = prop::sample::select(&STEMS[..]).prop_map(<[u8]>::to_vec);
+    prop_oneof![
+        // short tails from a small alphabet make keys that are prefixes of other keys.
+        (stem.clone(), prop::collection::vec(b'a'..b'e', 0..4)),
+        // one byte of any value below a stem fills a node up to a Node256.
+        (stem.clone(), any::<u8>().prop_map(|byte| vec![byte])),
+        // a byte from a narrow window keeps the node near the Node16 and Node48 boundaries.
+        (stem, (0u8..20).prop_map(|byte| vec![byte])),
+    ]
+    .prop_map(|(mut stem, tail)| {
+        stem.extend(tail);
+        stem
+    })
+}
+
+fn bound() -> impl Strategy<Value = Bound<Vec<u8>>> {
+    prop_oneof![
+        Just(Bound::Unbounded),
+        key().prop_map(Bound::Included),
+        key().prop_map(Bound::Excluded),
+    ]
+}
+
+fn op() -> impl Strategy<Value = Op> {
+    prop_oneof![
+        4 => (key(), any::<u32>()).prop_map(|(key, val)| Op::Insert(key, val)),
+        3 => key().prop_map(Op::Remove),
+        1 => key().prop_map(Op::Get),
+        1 => (bound(), bound()).prop_map(|(start, end)| Op::Range(start, end)),
+    ]
+}
+
+/// Whether `BTreeMap::range` accepts the bounds, it panics when the start is past the end.
+fn valid_range(start: &Bound<Vec<u8>>, end: &Bound<Vec<u8>>) -> bool {
+    match (start, end) {
+        (Bound::Excluded(start), Bound::Excluded(end)) => start < end,
+        (
+            Bound::Included(start) | Bound::Excluded(start),
+            Bound::Included(end) | Bound::Excluded(end),
+        ) => start <= end,
+        _ => true,
+    }
+}
+
+fn check<const MAX_PARTIAL_LEN: usize>(ops: &[Op]) -> Result<(), TestCaseError> {
+    let mut art = Art::<Vec<u8>, u32, MAX_PARTIAL_LEN>::new();
+    let mut btree = BTreeMap::new();
+    for op in ops {
+        match op {
+            Op::Insert(key, val) => {
+                prop_assert_eq!(
+                    art.insert(key.clone(), *val),
+                    btree.insert(key.clone(), *val)
+                )
+            }
+            Op::Remove(key) => prop_assert_eq!(art.remove(key), btree.remove(key)),
+            Op::Get(key) => prop_assert_eq!(art.get(key), btree.get(key)),
+            Op::Range(start, end) => {
+                if !valid_range(start, end) {
+                    continue;
+                }
+                let range = (start.clone(), end.clone());
+                let found: Vec<_> = art.range::<Vec<u8>, _>(range.clone()).collect();
+                let expected: Vec<_> = btree.range(range.clone()).collect();
+                prop_assert_eq!(&found, &expected);
+                let found: Vec<_> = art.range::<Vec<u8>, _>(range.clone()).rev().collect();
+                let expected: Vec<_> = btree.range(range).rev().collect();
+                prop_assert_eq!(found, expected);
+            }
+        }
+        prop_assert_eq!(art.size(), btree.len());
+    }
+
+    if let Err(error) = art.validate() {
+        return Err(TestCaseError::fail(error.to_string()));
+    }
+    prop_assert!(art.iter().eq(btree.iter()));
+    Ok(())
+}
+
+proptest! {
+    #[test]
+    fn against_btree_max_partial_len_0(ops in prop::collection::vec(op(), 0..400)) {
+        check::<0>(&ops)?;
+    }
+
+    #[test]
+    fn against_btree_max_partial_len_1(ops in prop::collection::vec(op(), 0..400)) {
+        check::<1>(&ops)?;
+    }
+
+    #[test]
+    fn against_btree_max_partial_len_4(ops in prop::collection::vec(op(), 0..400)) {
+        check::<4>(&ops)?;
+    }
+
+    #[test]
+    fn against_btree_max_partial_len_8(ops in prop::collection::vec(op(), 0..400)) {
+        check::<8>(&ops)?;
+    }
+}